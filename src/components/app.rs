@@ -4,18 +4,30 @@ use wasm_bindgen_futures::spawn_local;
 use web_sys::console;
 use yew::classes;
 use yew::html;
+use yew::use_effect_with_deps;
 use yew::use_reducer_eq;
+use yew::use_state;
 use yew::Callback;
 use yew::Html;
+use yew::Properties;
 use yew::Reducible;
 
 use crate::components::collapse::Collapse;
 use crate::components::import::Import;
 use crate::components::init_config::InitConfig;
 use crate::components::vault::Vault;
+use crate::config;
 use crate::data::vault::VaultConfig;
-use crate::hooks::local_storage::use_local_storage;
-use crate::hooks::local_storage::UseLocalStorageHandle;
+use crate::error::Error;
+use crate::hooks::storage::use_storage;
+use crate::hooks::storage::UseStorageHandle;
+use crate::storage;
+use crate::storage::BackendKind;
+use crate::storage::StorageBackend;
+use crate::storage::StoredValue;
+
+/// The key the vault config is stored under.
+const VAULT_KEY: &str = "vault";
 
 #[derive(Clone, Default, PartialEq)]
 struct AppState {
@@ -23,7 +35,7 @@ struct AppState {
 }
 
 enum AppAction {
-    JsOrSerdeError(String),
+    Error(String),
     ClearError,
 }
 
@@ -32,7 +44,7 @@ impl Reducible for AppState {
 
     fn reduce(mut self: Rc<Self>, action: Self::Action) -> Rc<Self> {
         match action {
-            Self::Action::JsOrSerdeError(msg) => {
+            Self::Action::Error(msg) => {
                 Rc::make_mut(&mut self).error = Some(msg);
                 self
             }
@@ -45,23 +57,34 @@ impl Reducible for AppState {
     }
 }
 
+#[derive(Properties)]
+struct MainProps {
+    storage: Rc<dyn StorageBackend>,
+}
+
+impl PartialEq for MainProps {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.storage, &other.storage)
+    }
+}
+
 #[styled_component]
-pub fn App() -> Html {
+fn Main(props: &MainProps) -> Html {
     let state = use_reducer_eq(AppState::default);
 
-    let config: UseLocalStorageHandle<VaultConfig> = use_local_storage("vault").unwrap();
+    let config: UseStorageHandle<VaultConfig> = use_storage(Rc::clone(&props.storage), VAULT_KEY);
 
-    let on_clear_error = {
+    let _on_clear_error = {
         let state = state.clone();
         Callback::from(move |()| {
             state.dispatch(AppAction::ClearError);
         })
     };
 
-    let on_set_error = {
+    let _on_set_error = {
         let state = state.clone();
         Callback::from(move |msg| {
-            state.dispatch(AppAction::JsOrSerdeError(msg));
+            state.dispatch(AppAction::Error(msg));
         })
     };
 
@@ -71,21 +94,45 @@ pub fn App() -> Html {
             let config = config.clone();
             spawn_local(async move {
                 if let Err(err) = VaultConfig::new(s).await.map(|conf| config.set(Some(conf))) {
-                    console::error_2(&"Init failed".into(), &err);
+                    console::error_2(&"Init failed".into(), &err.to_string().into());
                 }
             })
         })
     };
 
+    let imported = use_state(|| None::<Rc<VaultConfig>>);
+
     let on_import_config = {
         let config = config.clone();
+        let imported = imported.clone();
         Callback::from(move |s: String| {
-            if let Err(err) = config.set_from_str(&s) {
+            // Merge into an existing vault, so that nothing only stored locally is lost
+            let result = if config.ok().is_some() {
+                VaultConfig::parse(&s).map(|conf| imported.set(Some(Rc::new(conf))))
+            } else {
+                config.set_from_str(&s)
+            };
+            if let Err(err) = result {
                 console::error_2(&"Import failed".into(), &err.to_string().into());
             }
         })
     };
 
+    let on_import = {
+        let imported = imported.clone();
+        Callback::from(move |conf: Rc<VaultConfig>| imported.set(Some(conf)))
+    };
+
+    let on_import_done = {
+        let imported = imported.clone();
+        Callback::from(move |()| imported.set(None))
+    };
+
+    let on_dismiss_write_error = {
+        let config = config.clone();
+        Callback::from(move |_| config.clear_write_error())
+    };
+
     let on_set_config = {
         let config = config.clone();
         Callback::from(move |new_config: Rc<VaultConfig>| {
@@ -97,14 +144,36 @@ pub fn App() -> Html {
     html! {
         <div class={classes!("wrapper")}>
             <div class={classes!("main-content")}>
+                {
+                    if let Some(msg) = config.write_error() {
+                        html! {
+                            <p>
+                                { "Your last change was not saved: " }{ msg }{ " " }
+                                <button onclick={on_dismiss_write_error}>{ "Dismiss" }</button>
+                            </p>
+                        }
+                    } else {
+                        html! { <></> }
+                    }
+                }
                 <div>
                     {
                         match &*config {
+                            _ if !config.is_loaded() => {
+                                html! {
+                                    <p>{ "Loading vault..." }</p>
+                                }
+                            }
+
                             Some(Ok(config)) => {
                                 html!{
                                     <Vault
                                         {config}
                                         set_config={on_set_config}
+                                        imported={(*imported).clone()}
+                                        {on_import}
+                                        {on_import_done}
+                                        max_file_size={props.storage.max_file_size()}
                                     />
                                 }
                             }
@@ -118,6 +187,12 @@ pub fn App() -> Html {
                                 }
                             }
 
+                            Some(Err((_, err @ Error::NewerVersion(_)))) => {
+                                html! {
+                                    <p>{ err.to_string() }</p>
+                                }
+                            }
+
                             Some(Err(_)) => {
                                 html! {
                                     <p>{ "Vault is corrupted." }</p>
@@ -146,3 +221,50 @@ pub fn App() -> Html {
         </div>
     }
 }
+
+/// Open the configured storage backend, then show the vault kept in it.
+#[styled_component]
+pub fn App() -> Html {
+    let storage = use_state(|| None::<Result<Rc<dyn StorageBackend>, String>>);
+
+    {
+        let storage = storage.clone();
+        use_effect_with_deps(
+            move |_| {
+                spawn_local(async move {
+                    let kind = config::storage::backend();
+                    let result = match storage::open(kind, &[VAULT_KEY]).await {
+                        Err(err) if kind == BackendKind::IndexedDb => {
+                            console::error_2(
+                                &"Failed to open IndexedDB, falling back to localStorage:".into(),
+                                &err.to_string().into(),
+                            );
+                            storage::open(BackendKind::LocalStorage, &[]).await
+                        }
+                        result => result,
+                    };
+                    storage.set(Some(result.map_err(|err| err.to_string())));
+                })
+            },
+            (),
+        );
+    }
+
+    match &*storage {
+        Some(Ok(storage)) => html! {
+            <Main storage={Rc::clone(storage)} />
+        },
+        status => html! {
+            <div class={classes!("wrapper")}>
+                <div class={classes!("main-content")}>
+                    {
+                        match status {
+                            Some(Err(msg)) => html! { <p>{ "Failed to open storage: " }{ msg }</p> },
+                            _ => html! { <p>{ "Opening storage..." }</p> },
+                        }
+                    }
+                </div>
+            </div>
+        },
+    }
+}
@@ -0,0 +1,397 @@
+use std::rc::Rc;
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::console;
+use web_sys::Event;
+use web_sys::HtmlSelectElement;
+use yew::function_component;
+use yew::html;
+use yew::use_state;
+use yew::Callback;
+use yew::Html;
+use yew::Properties;
+use yew::UseStateHandle;
+
+use crate::components::collapse::Collapse;
+use crate::components::import::Import;
+use crate::crypto::identity::SignedIdentity;
+use crate::crypto::WebCrypto;
+use crate::data::contact::SharePackage;
+use crate::data::vault::NameIndex;
+use crate::data::vault::VaultConfig;
+use crate::error::Error;
+use crate::hooks::session::UseSessionHandle;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    pub config: Rc<VaultConfig>,
+    pub session: UseSessionHandle,
+
+    /// The decrypted entry names, if they are encrypted and the vault is unlocked.
+    pub names: Option<Rc<NameIndex>>,
+
+    pub set_config: Callback<Rc<VaultConfig>, Result<(), Error>>,
+}
+
+/// Text to show the user, or to copy and send to a contact.
+#[derive(Clone, PartialEq)]
+enum Output {
+    Message(String),
+    Export { title: String, json: String },
+}
+
+/// Show the outcome of an action, and log failures.
+fn report(output: &UseStateHandle<Option<Output>>, result: Result<Output, Error>) {
+    match result {
+        Ok(out) => output.set(Some(out)),
+        Err(Error::JsError(e)) => {
+            console::log_2(&"Contacts action failed:".into(), &e);
+            output.set(Some(Output::Message("JavaScript error".to_string())));
+        }
+        Err(err) => {
+            console::log_2(&"Contacts action failed:".into(), &err.to_string().into());
+            output.set(Some(Output::Message(err.to_string())));
+        }
+    }
+}
+
+/// Import other users' identities as contacts, and share entries with them.
+#[function_component]
+pub fn Contacts(props: &Props) -> Html {
+    let output = use_state(|| None::<Output>);
+    let selected_entry = use_state(|| None::<String>);
+
+    let entries: Option<Vec<(String, String)>> =
+        match (&props.names, props.config.has_encrypted_names()) {
+            (Some(names), _) => Some(
+                names
+                    .iter()
+                    .map(|(key, name)| (key.to_string(), name.to_string()))
+                    .collect(),
+            ),
+            (None, false) => Some(
+                props
+                    .config
+                    .contents
+                    .keys()
+                    .map(|key| (key.clone(), key.clone()))
+                    .collect(),
+            ),
+            (None, true) => None,
+        }
+        .map(|mut entries: Vec<(String, String)>| {
+            entries.sort_by(|a, b| a.1.cmp(&b.1));
+            entries
+        });
+    let entry_name = |key: &str| -> String {
+        entries
+            .as_ref()
+            .and_then(|entries| entries.iter().find(|(k, _)| k == key))
+            .map(|(_, name)| name.clone())
+            .unwrap_or_else(|| "(locked)".to_string())
+    };
+
+    let on_export_identity = {
+        let config = Rc::clone(&props.config);
+        let session = props.session.clone();
+        let output = output.clone();
+        Callback::from(move |_| {
+            let config = Rc::clone(&config);
+            let session = session.clone();
+            let output = output.clone();
+            spawn_local(async move {
+                let result: Result<Output, Error> = async {
+                    let session = session.unlock(&config).await?;
+                    let signed = config
+                        .export_identity(&WebCrypto, &session.master_key)
+                        .await?;
+                    let fingerprint = signed.identity()?.fingerprint();
+                    Ok(Output::Export {
+                        title: format!(
+                            "Send this to your colleagues, and tell them your fingerprint: {fingerprint}"
+                        ),
+                        json: serde_json::to_string(&signed)?,
+                    })
+                }
+                .await;
+                report(&output, result);
+            });
+        })
+    };
+
+    let on_import_contact = {
+        let config = Rc::clone(&props.config);
+        let set_config = props.set_config.clone();
+        let output = output.clone();
+        Callback::from(move |json: String| {
+            let result: Result<Output, Error> = (|| {
+                let signed: SignedIdentity = serde_json::from_str(&json)?;
+                let mut config = Rc::clone(&config);
+                let identity = Rc::make_mut(&mut config).import_contact(signed)?;
+                set_config.emit(config)?;
+                Ok(Output::Message(format!(
+                    "Imported {}. Check with them that their fingerprint is {}.",
+                    identity.username,
+                    identity.fingerprint()
+                )))
+            })();
+            report(&output, result);
+        })
+    };
+
+    let on_import_shared = {
+        let config = Rc::clone(&props.config);
+        let session = props.session.clone();
+        let set_config = props.set_config.clone();
+        let output = output.clone();
+        Callback::from(move |json: String| {
+            let config = Rc::clone(&config);
+            let session = session.clone();
+            let set_config = set_config.clone();
+            let output = output.clone();
+            spawn_local(async move {
+                let result: Result<Output, Error> = async {
+                    let package: SharePackage = serde_json::from_str(&json)?;
+                    let session = session.unlock(&config).await?;
+                    let mut config = Rc::clone(&config);
+                    let names = Rc::make_mut(&mut config)
+                        .import_shared(&WebCrypto, &package, &session.unlocked, &session.master_key)
+                        .await?;
+                    set_config.emit(config)?;
+                    Ok(Output::Message(format!(
+                        "Added {} entries: {}",
+                        names.len(),
+                        names.join(", ")
+                    )))
+                }
+                .await;
+                report(&output, result);
+            });
+        })
+    };
+
+    let on_select_entry = {
+        let selected_entry = selected_entry.clone();
+        Callback::from(move |e: Event| {
+            if let Some(el) = e
+                .target()
+                .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
+            {
+                selected_entry.set(Some(el.value()).filter(|key| !key.is_empty()));
+            }
+        })
+    };
+
+    let contacts = props
+        .config
+        .contacts
+        .iter()
+        .map(|(contact_key, contact)| {
+            let Ok(identity) = contact.identity() else {
+                return html! { <li key={contact_key.clone()}>{ "Invalid contact" }</li> };
+            };
+
+            let on_share = {
+                let config = Rc::clone(&props.config);
+                let session = props.session.clone();
+                let set_config = props.set_config.clone();
+                let output = output.clone();
+                let contact_key = contact_key.clone();
+                let selected = (*selected_entry)
+                    .clone()
+                    .map(|key| (entry_name(&key), key));
+                Callback::from(move |_| {
+                    let Some((name, key)) = selected.clone() else {
+                        return;
+                    };
+                    let config = Rc::clone(&config);
+                    let session = session.clone();
+                    let set_config = set_config.clone();
+                    let output = output.clone();
+                    let contact_key = contact_key.clone();
+                    spawn_local(async move {
+                        let result: Result<Output, Error> = async {
+                            let session = session.unlock(&config).await?;
+                            let mut config = Rc::clone(&config);
+                            Rc::make_mut(&mut config)
+                                .share_content(
+                                    &WebCrypto,
+                                    &key,
+                                    &name,
+                                    &contact_key,
+                                    &session.unlocked,
+                                    &session.master_key,
+                                )
+                                .await?;
+                            set_config.emit(config)?;
+                            Ok(Output::Message(format!("Shared {name}")))
+                        }
+                        .await;
+                        report(&output, result);
+                    });
+                })
+            };
+
+            let on_export_shared = {
+                let config = Rc::clone(&props.config);
+                let session = props.session.clone();
+                let output = output.clone();
+                let contact_key = contact_key.clone();
+                let username = identity.username.clone();
+                Callback::from(move |_| {
+                    let config = Rc::clone(&config);
+                    let session = session.clone();
+                    let output = output.clone();
+                    let contact_key = contact_key.clone();
+                    let username = username.clone();
+                    spawn_local(async move {
+                        let result: Result<Output, Error> = async {
+                            let session = session.unlock(&config).await?;
+                            let package = config
+                                .share_package(&WebCrypto, &contact_key, &session.master_key)
+                                .await?;
+                            Ok(Output::Export {
+                                title: format!("Send this to {username}"),
+                                json: serde_json::to_string(&package)?,
+                            })
+                        }
+                        .await;
+                        report(&output, result);
+                    });
+                })
+            };
+
+            let on_delete = {
+                let config = Rc::clone(&props.config);
+                let set_config = props.set_config.clone();
+                let output = output.clone();
+                let contact_key = contact_key.clone();
+                Callback::from(move |_| {
+                    let mut config = Rc::clone(&config);
+                    let result = Rc::make_mut(&mut config)
+                        .delete_contact(&contact_key)
+                        .map(|_| ())
+                        .and_then(|()| set_config.emit(config));
+                    if let Err(err) = result {
+                        report(&output, Err(err));
+                    }
+                })
+            };
+
+            let shared = contact
+                .shared
+                .keys()
+                .map(|key| {
+                    let on_unshare = {
+                        let config = Rc::clone(&props.config);
+                        let set_config = props.set_config.clone();
+                        let output = output.clone();
+                        let contact_key = contact_key.clone();
+                        let key = key.clone();
+                        Callback::from(move |_| {
+                            let mut config = Rc::clone(&config);
+                            let result = Rc::make_mut(&mut config)
+                                .unshare_content(&key, &contact_key)
+                                .map(|_| ())
+                                .and_then(|()| set_config.emit(config));
+                            if let Err(err) = result {
+                                report(&output, Err(err));
+                            }
+                        })
+                    };
+                    html! {
+                        <li key={key.clone()}>
+                            { entry_name(key) }
+                            <button onclick={on_unshare}>{ "Stop sharing" }</button>
+                        </li>
+                    }
+                })
+                .collect::<Html>();
+
+            html! {
+                <li key={contact_key.clone()}>
+                    <p>
+                        <strong>{ &identity.username }</strong>
+                        { format!(" ({} keys, fingerprint {})", identity.keys.len(), identity.fingerprint()) }
+                        <button onclick={on_delete}>{ "Delete" }</button>
+                    </p>
+                    <ul>{ shared }</ul>
+                    <button onclick={on_share} disabled={selected_entry.is_none()}>
+                        { "Share selected entry" }
+                    </button>
+                    <button onclick={on_export_shared} disabled={contact.shared.is_empty()}>
+                        { "Export shared entries" }
+                    </button>
+                </li>
+            }
+        })
+        .collect::<Html>();
+
+    html! {
+        <div>
+            <h2>{ "Contacts" }</h2>
+            <p>
+                <button onclick={on_export_identity}>{ "Export my identity" }</button>
+            </p>
+            {
+                if props.config.contacts.is_empty() {
+                    html! { <></> }
+                } else {
+                    html! {
+                        <>
+                            {
+                                match &entries {
+                                    Some(entries) => html! {
+                                        <select onchange={on_select_entry}>
+                                            <option value="" selected={selected_entry.is_none()}>
+                                                { "Entry to share" }
+                                            </option>
+                                            {
+                                                entries.iter()
+                                                    .map(|(key, name)| html! {
+                                                        <option
+                                                            key={key.clone()}
+                                                            value={key.clone()}
+                                                            selected={selected_entry.as_ref() == Some(key)}
+                                                        >
+                                                            { name }
+                                                        </option>
+                                                    })
+                                                    .collect::<Html>()
+                                            }
+                                        </select>
+                                    },
+                                    None => html! { <p>{ "Unlock the vault to share entries." }</p> },
+                                }
+                            }
+                            <ul>{ contacts }</ul>
+                        </>
+                    }
+                }
+            }
+            {
+                match &*output {
+                    Some(Output::Message(msg)) => html! { <p>{ msg }</p> },
+                    Some(Output::Export { title, json }) => html! {
+                        <>
+                            <p>{ title }</p>
+                            <textarea readonly={true} value={json.clone()} />
+                        </>
+                    },
+                    None => html! { <></> },
+                }
+            }
+            <Collapse button_text="Import contact" start_expanded={false}>
+                <Import on_import={on_import_contact}>
+                    <p>{ "Paste the identity exported by a colleague:" }</p>
+                </Import>
+            </Collapse>
+            <Collapse button_text="Import shared entries" start_expanded={false}>
+                <Import on_import={on_import_shared}>
+                    <p>{ "Paste the entries exported for you by one of your contacts:" }</p>
+                </Import>
+            </Collapse>
+        </div>
+    }
+}
@@ -1,4 +1,3 @@
-use js_sys::Uint8Array;
 use std::rc::Rc;
 use wasm_bindgen_futures::spawn_local;
 use web_sys::console;
@@ -12,6 +11,7 @@ use crate::crypto::create_credential;
 use crate::crypto::create_wrapped_keypair;
 use crate::crypto::WrappedKeypair;
 use crate::data::vault::VaultConfig;
+use crate::error::Error;
 
 #[derive(PartialEq, Properties)]
 pub struct Props {
@@ -26,21 +26,40 @@ pub fn CreateButton(props: &Props) -> Html {
     let onclick = {
         let config = Rc::clone(&props.config);
         let on_create = props.on_create.clone();
+        let on_begin = props.on_begin.clone();
+        let on_fail = props.on_fail.clone();
         move |_| {
+            on_begin.emit(());
             let config = Rc::clone(&config);
             let on_create = on_create.clone();
+            let on_fail = on_fail.clone();
             spawn_local(async move {
-                if let Ok(cred) = create_credential(&config.user).await {
-                    if let Ok(wrapped_keypair) =
-                        create_wrapped_keypair(&Uint8Array::new(&cred.raw_id()).to_vec()).await
-                    {
-                        console::log_1(&"Finished!".into());
-                        on_create.emit(wrapped_keypair);
-                    } else {
-                        console::log_1(&"Failed to create encryption keypair.".into());
+                match create_credential(&config.user).await {
+                    Ok(cred) => match create_wrapped_keypair(&cred).await {
+                        Ok(wrapped_keypair) => {
+                            console::log_1(&"Finished!".into());
+                            on_create.emit(wrapped_keypair);
+                        }
+                        Err(err) => {
+                            console::log_2(
+                                &"Failed to create encryption keypair:".into(),
+                                &err.to_string().into(),
+                            );
+                            on_fail.emit(err.to_string());
+                        }
+                    },
+                    Err(Error::PrfUnsupported) => {
+                        let msg = "This authenticator does not support the WebAuthn PRF extension, so it cannot be used to encrypt the vault. Please try another authenticator.";
+                        console::log_1(&msg.into());
+                        on_fail.emit(msg.to_string());
+                    }
+                    Err(err) => {
+                        console::log_2(
+                            &"WebAuthn registration failed:".into(),
+                            &err.to_string().into(),
+                        );
+                        on_fail.emit(err.to_string());
                     }
-                } else {
-                    console::log_1(&"WebAuthn registration failed.".into());
                 }
             });
         }
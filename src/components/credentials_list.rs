@@ -16,6 +16,7 @@ use crate::data::CredentialId;
 pub struct CredentialItemProps {
     pub keypair: Rc<WrappedKeypair>,
     pub on_delete: Callback<CredentialId>,
+    pub on_revoke: Callback<CredentialId>,
     pub on_rename: Callback<(CredentialId, String)>,
 }
 
@@ -76,6 +77,14 @@ pub fn CredentialItem(props: &CredentialItemProps) -> Html {
                 }
             };
 
+            let on_revoke = {
+                let on_revoke = props.on_revoke.clone();
+                let cred_id = cred_id.clone();
+                move |_| {
+                    on_revoke.emit(cred_id.clone());
+                }
+            };
+
             let name = props
                 .keypair
                 .nickname
@@ -87,6 +96,7 @@ pub fn CredentialItem(props: &CredentialItemProps) -> Html {
                     { name }
                     <button onclick={move |_| editing.set(true)}>{ "Rename" }</button>
                     <button onclick={on_delete}>{ "Delete" }</button>
+                    <button onclick={on_revoke}>{ "Revoke" }</button>
                 </li>
             }
         }
@@ -103,6 +113,7 @@ pub fn CredentialItem(props: &CredentialItemProps) -> Html {
 pub struct Props {
     pub keypairs: Rc<Vec<Rc<WrappedKeypair>>>,
     pub on_delete: Callback<CredentialId>,
+    pub on_revoke: Callback<CredentialId>,
     pub on_rename: Callback<(CredentialId, String)>,
 }
 
@@ -113,6 +124,7 @@ pub fn CredentialsList(props: &Props) -> Html {
         .iter()
         .map(|cred| {
             let on_delete = props.on_delete.clone();
+            let on_revoke = props.on_revoke.clone();
             let on_rename = props.on_rename.clone();
 
             if let Ok(additional_data) = cred.additional_data() {
@@ -122,6 +134,7 @@ pub fn CredentialsList(props: &Props) -> Html {
                         {key}
                         keypair={Rc::clone(cred)}
                         {on_delete}
+                        {on_revoke}
                         {on_rename}
                     />
                 }
@@ -0,0 +1,258 @@
+use js_sys::Array;
+use js_sys::Uint8Array;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use web_sys::console;
+use web_sys::Blob;
+use web_sys::BlobPropertyBag;
+use web_sys::HtmlAnchorElement;
+use web_sys::Url;
+use yew::classes;
+use yew::function_component;
+use yew::html;
+use yew::use_effect_with_deps;
+use yew::use_state;
+use yew::Callback;
+use yew::Html;
+use yew::Properties;
+
+use crate::data::entry::Attachment;
+use crate::data::entry::Entry;
+use crate::data::entry::Login;
+use crate::data::totp::Totp;
+use crate::error::Error;
+
+fn unix_time() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+#[derive(PartialEq, Properties)]
+struct SecretProps {
+    value: String,
+}
+
+/// A value that is masked until the user chooses to reveal it.
+#[function_component]
+fn Secret(props: &SecretProps) -> Html {
+    let revealed = use_state(|| false);
+
+    let on_toggle = Callback::from({
+        let revealed = revealed.clone();
+        move |_| revealed.set(!*revealed)
+    });
+
+    html! {
+        <>
+            <code>
+                {
+                    if *revealed {
+                        props.value.clone()
+                    } else {
+                        "\u{2022}".repeat(8)
+                    }
+                }
+            </code>
+            <button onclick={on_toggle}>
+                { if *revealed { "Hide" } else { "Reveal" } }
+            </button>
+        </>
+    }
+}
+
+#[derive(PartialEq, Properties)]
+struct TotpCodeProps {
+    totp: Totp,
+}
+
+/// The current TOTP code, refreshed every second with a countdown to the
+/// next one.
+#[function_component]
+fn TotpCode(props: &TotpCodeProps) -> Html {
+    let now = use_state(unix_time);
+
+    {
+        let now = now.clone();
+        use_effect_with_deps(
+            move |_| {
+                let window = web_sys::window();
+                let callback: Closure<dyn FnMut()> = Closure::new(move || now.set(unix_time()));
+                let handle = window.as_ref().and_then(|window| {
+                    window
+                        .set_interval_with_callback_and_timeout_and_arguments_0(
+                            callback.as_ref().unchecked_ref(),
+                            1000,
+                        )
+                        .map_err(|err| console::error_2(&"Failed to start TOTP timer".into(), &err))
+                        .ok()
+                });
+
+                move || {
+                    if let (Some(window), Some(handle)) = (window, handle) {
+                        window.clear_interval_with_handle(handle);
+                    }
+                    drop(callback);
+                }
+            },
+            (),
+        );
+    }
+
+    html! {
+        <>
+            <code>{ props.totp.generate(*now) }</code>
+            { " " }
+            <span>{ format!("({}s)", props.totp.remaining(*now)) }</span>
+        </>
+    }
+}
+
+fn login_view(login: &Login) -> Html {
+    html! {
+        <dl class={classes!("entry-view")}>
+            <dt>{ "Username" }</dt>
+            <dd><code>{ &login.username }</code></dd>
+
+            <dt>{ "Password" }</dt>
+            <dd><Secret value={login.password.clone()} /></dd>
+
+            {
+                if login.urls.is_empty() {
+                    html! { <></> }
+                } else {
+                    html! {
+                        <>
+                            <dt>{ "URLs" }</dt>
+                            <dd>
+                                <ul>
+                                    {
+                                        login.urls.iter()
+                                            .map(|url| html! {
+                                                <li key={url.clone()}>
+                                                    <a href={url.clone()} target="_blank" rel="noopener noreferrer">{ url }</a>
+                                                </li>
+                                            })
+                                            .collect::<Html>()
+                                    }
+                                </ul>
+                            </dd>
+                        </>
+                    }
+                }
+            }
+
+            {
+                if let Some(totp) = &login.totp {
+                    html! {
+                        <>
+                            <dt>{ "TOTP" }</dt>
+                            <dd><TotpCode totp={totp.clone()} /></dd>
+                        </>
+                    }
+                } else {
+                    html! { <></> }
+                }
+            }
+
+            {
+                login.custom_fields.iter()
+                    .map(|field| html! {
+                        <>
+                            <dt>{ &field.name }</dt>
+                            <dd>
+                                {
+                                    if field.hidden {
+                                        html! { <Secret value={field.value.clone()} /> }
+                                    } else {
+                                        html! { <code>{ &field.value }</code> }
+                                    }
+                                }
+                            </dd>
+                        </>
+                    })
+                    .collect::<Html>()
+            }
+
+            {
+                if login.notes.is_empty() {
+                    html! { <></> }
+                } else {
+                    html! {
+                        <>
+                            <dt>{ "Notes" }</dt>
+                            <dd><pre>{ &login.notes }</pre></dd>
+                        </>
+                    }
+                }
+            }
+        </dl>
+    }
+}
+
+/// Save the file through a temporary object URL, with its original filename.
+fn download(attachment: &Attachment) -> Result<(), Error> {
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| Error::InvalidInput("No document to download into".to_string()))?;
+    let blob = Blob::new_with_u8_array_sequence_and_options(
+        &Array::of1(&Uint8Array::from(attachment.data.as_slice())),
+        BlobPropertyBag::new().type_(&attachment.mime_type),
+    )?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+    let link: HtmlAnchorElement = document
+        .create_element("a")?
+        .dyn_into()
+        .map_err(JsValue::from)?;
+    link.set_href(&url);
+    link.set_download(&attachment.filename);
+    link.click();
+    Ok(Url::revoke_object_url(&url)?)
+}
+
+fn file_view(attachment: &Attachment) -> Html {
+    let on_download = Callback::from({
+        let attachment = attachment.clone();
+        move |_| match download(&attachment) {
+            Ok(()) => {}
+            Err(Error::JsError(e)) => {
+                console::log_2(&"Failed to download file:".into(), &e);
+            }
+            Err(err) => {
+                console::log_2(&"Failed to download file:".into(), &err.to_string().into());
+            }
+        }
+    });
+
+    html! {
+        <dl class={classes!("entry-view")}>
+            <dt>{ "Filename" }</dt>
+            <dd><code>{ &attachment.filename }</code></dd>
+
+            <dt>{ "Type" }</dt>
+            <dd><code>{ &attachment.mime_type }</code></dd>
+
+            <dt>{ "Size" }</dt>
+            <dd>{ format!("{} bytes", attachment.data.len()) }</dd>
+
+            <dd><button onclick={on_download}>{ "Download" }</button></dd>
+        </dl>
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    pub entry: Entry,
+}
+
+#[function_component]
+pub fn EntryView(props: &Props) -> Html {
+    match &props.entry {
+        Entry::Login(login) => login_view(login),
+        Entry::Text { text } => html! {
+            <pre>
+                { text }
+            </pre>
+        },
+        Entry::File(attachment) => file_view(attachment),
+    }
+}
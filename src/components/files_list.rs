@@ -9,18 +9,75 @@ use yew::Callback;
 use yew::Html;
 use yew::Properties;
 
+use crate::components::entry_view::EntryView;
 use crate::crypto::decrypt;
+use crate::crypto::decrypt_with;
+use crate::crypto::ContentKey;
 use crate::crypto::EncryptedContent;
+use crate::crypto::WebCrypto;
+use crate::data::entry::Entry;
+use crate::data::vault::NameIndex;
 use crate::data::vault::VaultConfig;
 use crate::data::CredentialId;
-use crate::error::JsOrSerdeError;
+use crate::error::Error;
+use crate::hooks::session::UseSessionHandle;
+
+/// Decrypt `item` with the unlocked session, unlocking it first if `item` is
+/// encrypted with the vault master key. Entries in the format version 2
+/// layout that the session cannot read need a WebAuthn ceremony of their own,
+/// and entries split between several keys need one for each key.
+async fn decrypt_item(
+    session: &UseSessionHandle,
+    config: &VaultConfig,
+    name: &str,
+    item: &EncryptedContent,
+) -> Result<Vec<u8>, Error> {
+    let additional_data = config.content_additional_data(name);
+
+    let session = match session.get() {
+        Some(session) => Some(session),
+        None if matches!(item.key, ContentKey::MasterKey { .. }) => {
+            Some(session.unlock(config).await?)
+        }
+        None => None,
+    };
+
+    match session {
+        Some(session) if session.can_decrypt(item) => {
+            decrypt_with(
+                &WebCrypto,
+                item,
+                &additional_data,
+                &session.unlocked,
+                Some(&session.master_key),
+            )
+            .await
+        }
+        _ => {
+            decrypt(
+                item,
+                &additional_data,
+                config.master_key.as_ref(),
+                &config.user.keypairs,
+            )
+            .await
+        }
+    }
+}
 
 #[derive(PartialEq, Properties)]
 pub struct FileItemProps {
     pub config: Rc<VaultConfig>,
+
+    /// The key of the entry in the vault contents.
     pub name: String,
+
+    /// The name shown for the entry, which differs from [Self::name] if entry
+    /// names are encrypted.
+    pub label: String,
+
     pub item: Rc<EncryptedContent>,
-    pub on_reencrypt: Callback<(String, Vec<u8>)>,
+    pub session: UseSessionHandle,
     pub on_delete: Callback<String>,
 }
 
@@ -38,29 +95,28 @@ pub fn FileItem(props: &FileItemProps) -> Html {
         let decrypted = decrypted.clone();
         let config = Rc::clone(&props.config);
         let item = props.item.clone();
-        let on_reencrypt = props.on_reencrypt.clone();
         let name = props.name.clone();
+        let session = props.session.clone();
         move |_| {
             let decrypted = decrypted.clone();
             let config = Rc::clone(&config);
             let item = item.clone();
-            let on_reencrypt = on_reencrypt.clone();
             let name = name.clone();
+            let session = session.clone();
             spawn_local(async move {
-                match decrypt(&item, &config.user.keypairs).await {
+                match decrypt_item(&session, &config, &name, &item)
+                    .await
+                    .and_then(|dec| Entry::from_bytes(&dec))
+                {
                     Ok(dec) => {
                         console::log_1(&"Finished!".into());
-                        decrypted.set(Some(String::from_utf8(dec.clone()).unwrap()));
-                        on_reencrypt.emit((name, dec));
+                        decrypted.set(Some(dec));
                     }
-                    Err(JsOrSerdeError::JsError(e)) => {
+                    Err(Error::JsError(e)) => {
                         console::log_2(&"Decryption failed:".into(), &e);
                     }
-                    Err(JsOrSerdeError::SerializeError(e)) => {
-                        console::log_2(
-                            &"Decryption failed: (De)serialization failed".into(),
-                            &e.to_string().into(),
-                        );
+                    Err(err) => {
+                        console::log_2(&"Decryption failed:".into(), &err.to_string().into());
                     }
                 }
             });
@@ -82,14 +138,29 @@ pub fn FileItem(props: &FileItemProps) -> Html {
         }
     });
 
+    let recipients = props.config.content_recipients(&props.item);
+    let shared_with: Vec<String> = props
+        .config
+        .shared_with(&props.name)
+        .into_iter()
+        .map(|identity| identity.username)
+        .collect();
+
     html! {
         <div class={classes!("file-item")}>
             <div class={classes!("header")}>
-                <pre>{ &props.name }</pre>
+                <pre>{ &props.label }</pre>
+                {
+                    if props.item.is_bound() {
+                        html! { <></> }
+                    } else {
+                        html! { <span>{ "Not bound to its name" }</span> }
+                    }
+                }
 
                 <button onclick={on_toggle_keys}>
                     { "Keys: " }
-                    { props.item.recipients.len() }
+                    { recipients.len() }
                 </button>
 
                 <button onclick={on_delete}>
@@ -115,11 +186,9 @@ pub fn FileItem(props: &FileItemProps) -> Html {
 
             <div class={classes!("content", Some("expanded").filter(|_| decrypted.is_some()))}>
                 {
-                    if let Some(password) = &*decrypted {
+                    if let Some(entry) = &*decrypted {
                         html! {
-                            <pre>
-                                { password }
-                            </pre>
+                            <EntryView entry={entry.clone()} />
                         }
                     } else {
                         html! {
@@ -133,18 +202,28 @@ pub fn FileItem(props: &FileItemProps) -> Html {
                         html! {
                             <>
                                 <p>
-                                    { "Encrypted to " }
-                                    { props.item.recipients.len() }
-                                    { " keys:" }
+                                    {
+                                        match &props.item.key {
+                                            ContentKey::MasterKey { .. } => format!(
+                                                "Encrypted to {} keys via the vault master key:",
+                                                recipients.len(),
+                                            ),
+                                            ContentKey::Recipients { .. } => {
+                                                format!("Encrypted to {} keys:", recipients.len())
+                                            }
+                                            ContentKey::Threshold { threshold, .. } => format!(
+                                                "Any {threshold} of these {} keys together can decrypt:",
+                                                recipients.len(),
+                                            ),
+                                        }
+                                    }
                                 </p>
                                 <ul>
                                     {
-                                        props.item.recipients.iter()
+                                        recipients.iter()
                                             .map(|wkp| {
                                                 let cred_id = CredentialId::from(wkp.credential_id.clone());
-                                                let name: String = props.config.get_credential_nickname(&cred_id)
-                                                    .map(|s| s.to_string())
-                                                    .unwrap_or_else(|| cred_id.b64_abbrev(24));
+                                                let name: String = props.config.recipient_name(wkp);
                                                 html! {
                                                     <li key={cred_id.b64url()}>
                                                         { name }
@@ -154,6 +233,13 @@ pub fn FileItem(props: &FileItemProps) -> Html {
                                             .collect::<Html>()
                                     }
                                 </ul>
+                                {
+                                    if shared_with.is_empty() {
+                                        html! { <></> }
+                                    } else {
+                                        html! { <p>{ format!("Shared with {}", shared_with.join(", ")) }</p> }
+                                    }
+                                }
                             </>
                         }
                     } else {
@@ -167,27 +253,66 @@ pub fn FileItem(props: &FileItemProps) -> Html {
     }
 }
 
+/// Progress of re-encrypting all entries, for display.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReencryptStatus {
+    pub done: usize,
+    pub total: usize,
+    pub failed: Vec<(String, String)>,
+    pub finished: bool,
+}
+
 #[derive(PartialEq, Properties)]
 pub struct Props {
     pub config: Rc<VaultConfig>,
-    pub on_reencrypt: Callback<(String, Vec<u8>)>,
+    pub session: UseSessionHandle,
+
+    /// The decrypted entry names, if they are encrypted and the vault is unlocked.
+    pub names: Option<Rc<NameIndex>>,
+
+    pub reencrypt_status: Option<ReencryptStatus>,
+    pub on_upgrade: Callback<()>,
+    pub on_reencrypt_all: Callback<()>,
+    pub on_encrypt_names: Callback<()>,
     pub on_delete: Callback<String>,
 }
 
 #[function_component]
 pub fn FilesList(props: &Props) -> Html {
-    let files = props
+    let label = |key: &str| -> String {
+        if !props.config.has_encrypted_names() {
+            key.to_string()
+        } else {
+            props
+                .names
+                .as_ref()
+                .and_then(|names| names.name(key))
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| "(locked)".to_string())
+        }
+    };
+
+    let mut items: Vec<(String, &String, &Rc<EncryptedContent>)> = props
         .config
         .contents
         .iter()
-        .map(|(name, item)| {
+        .map(|(name, item)| (label(name), name, item))
+        .collect();
+    items.sort_by(|(a_label, a_name, _), (b_label, b_name, _)| {
+        (a_label, a_name).cmp(&(b_label, b_name))
+    });
+
+    let files = items
+        .into_iter()
+        .map(|(label, name, item)| {
             html! {
                 <li key={name.to_string()}>
                     <FileItem
                         config={Rc::clone(&props.config)}
                         name={name.clone()}
+                        {label}
                         item={item}
-                        on_reencrypt={props.on_reencrypt.clone()}
+                        session={props.session.clone()}
                         on_delete={props.on_delete.clone()}
                     />
                 </li>
@@ -195,9 +320,133 @@ pub fn FilesList(props: &Props) -> Html {
         })
         .collect::<Html>();
 
+    let num_legacy = props
+        .config
+        .contents
+        .values()
+        .filter(|item| matches!(item.key, ContentKey::Recipients { .. }))
+        .count();
+    let num_unbound = props
+        .config
+        .contents
+        .values()
+        .filter(|item| !item.is_bound())
+        .count();
+
+    let on_upgrade = {
+        let on_upgrade = props.on_upgrade.clone();
+        Callback::from(move |_| on_upgrade.emit(()))
+    };
+
+    let on_reencrypt_all = {
+        let on_reencrypt_all = props.on_reencrypt_all.clone();
+        Callback::from(move |_| on_reencrypt_all.emit(()))
+    };
+
+    let on_encrypt_names = {
+        let on_encrypt_names = props.on_encrypt_names.clone();
+        Callback::from(move |_| on_encrypt_names.emit(()))
+    };
+
     html! {
         <>
             <h2>{ "Vault entries" }</h2>
+            {
+                if num_legacy > 0 {
+                    html! {
+                        <p>
+                            { num_legacy }
+                            { " entries are not yet encrypted with the vault master key. " }
+                            <button onclick={on_upgrade}>{ "Upgrade" }</button>
+                        </p>
+                    }
+                } else {
+                    html! { <></> }
+                }
+            }
+            {
+                if num_unbound > 0 {
+                    html! {
+                        <p>
+                            <strong>{ "Warning: " }</strong>
+                            { num_unbound }
+                            {
+                                " entries are not bound to their names, so they could be \
+                                 swapped with each other without being detected. Upgrade or \
+                                 re-encrypt them to fix this."
+                            }
+                        </p>
+                    }
+                } else {
+                    html! { <></> }
+                }
+            }
+            <p>
+                <button
+                    onclick={on_reencrypt_all}
+                    disabled={props.reencrypt_status.as_ref().is_some_and(|status| !status.finished)}
+                >
+                    { "Re-encrypt all entries to all keys" }
+                </button>
+                {
+                    if props.config.has_encrypted_names() {
+                        html! { <span>{ " Entry names are encrypted." }</span> }
+                    } else if props.config.master_key.is_some() && num_legacy == 0 {
+                        html! {
+                            <button onclick={on_encrypt_names}>
+                                { "Encrypt entry names" }
+                            </button>
+                        }
+                    } else {
+                        html! { <></> }
+                    }
+                }
+            </p>
+            {
+                if let Some(status) = &props.reencrypt_status {
+                    html! {
+                        <>
+                            <p>
+                                {
+                                    if status.finished {
+                                        "Re-encrypted "
+                                    } else {
+                                        "Re-encrypting... "
+                                    }
+                                }
+                                { status.done - status.failed.len() }
+                                { " of " }
+                                { status.total }
+                                { " entries." }
+                            </p>
+                            {
+                                if status.failed.is_empty() {
+                                    html! { <></> }
+                                } else {
+                                    html! {
+                                        <ul>
+                                            {
+                                                status.failed.iter()
+                                                    .map(|(name, err)| html! {
+                                                        <li key={name.clone()}>
+                                                            { "Failed to re-encrypt " }
+                                                            <code>{ label(name) }</code>
+                                                            { ": " }
+                                                            { err }
+                                                        </li>
+                                                    })
+                                                    .collect::<Html>()
+                                            }
+                                        </ul>
+                                    }
+                                }
+                            }
+                        </>
+                    }
+                } else {
+                    html! { <></> }
+                }
+            }
             <ul class={classes!("files-list")}>{files}</ul>
         </>
     }
@@ -1,24 +1,58 @@
 use std::rc::Rc;
 
+use js_sys::Uint8Array;
 use stylist::yew::styled_component;
 use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::console;
 use web_sys::Event;
+use web_sys::File;
 use web_sys::HtmlInputElement;
+use web_sys::HtmlSelectElement;
 use web_sys::HtmlTextAreaElement;
 use web_sys::InputEvent;
 use web_sys::SubmitEvent;
+use yew::function_component;
 use yew::html;
+use yew::use_node_ref;
 use yew::use_state;
 use yew::Callback;
 use yew::Html;
 use yew::Properties;
+use yew::UseStateHandle;
 
+use crate::components::password_generator::PasswordGenerator;
+use crate::data::entry::Attachment;
+use crate::data::entry::CustomField;
+use crate::data::entry::Entry;
+use crate::data::entry::Login;
+use crate::data::totp::Totp;
+use crate::data::vault::NameIndex;
 use crate::data::vault::VaultConfig;
+use crate::data::CredentialId;
+use crate::error::Error;
+
+/// Which keys must be used together to decrypt a new entry.
+#[derive(Clone, PartialEq)]
+pub struct RequiredKeys {
+    pub threshold: u8,
+    pub credential_ids: Vec<CredentialId>,
+}
 
 #[derive(PartialEq, Properties)]
 pub struct Props {
     pub config: Rc<VaultConfig>,
-    pub on_submit: Callback<(String, Vec<u8>)>,
+
+    /// The decrypted entry names, if they are encrypted and the vault is unlocked.
+    pub names: Option<Rc<NameIndex>>,
+
+    /// The largest file, in bytes, that can be added as an entry.
+    pub max_file_size: usize,
+
+    /// Called with the new entry, and the keys it needs if it is to be
+    /// encrypted so that one key is not enough.
+    pub on_submit: Callback<(String, Entry, Option<RequiredKeys>)>,
 }
 
 #[derive(PartialEq, Properties)]
@@ -43,48 +77,412 @@ fn ExistsIndicator(props: &ExistsIndicatorProps) -> Html {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum EntryKind {
+    Login,
+    Text,
+    File,
+}
+
+/// Read `file` into an [Attachment], unless it is larger than `max_size` bytes.
+async fn read_file(file: File, max_size: usize) -> Result<Attachment, Error> {
+    if file.size() > max_size as f64 {
+        return Err(Error::InvalidInput(format!(
+            "{} is {} bytes, but at most {max_size} bytes can be stored",
+            file.name(),
+            file.size(),
+        )));
+    }
+    let buffer = JsFuture::from(file.array_buffer()).await?;
+    Attachment::new(
+        file.name(),
+        file.type_(),
+        Uint8Array::new(&buffer).to_vec(),
+        max_size,
+    )
+}
+
+fn on_input(state: &UseStateHandle<String>) -> Callback<InputEvent> {
+    let state = state.clone();
+    Callback::from(move |e: InputEvent| {
+        if let Some(el) = e
+            .target()
+            .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+        {
+            state.set(el.value());
+        }
+    })
+}
+
+fn on_change_textarea(state: &UseStateHandle<String>) -> Callback<Event> {
+    let state = state.clone();
+    Callback::from(move |e: Event| {
+        if let Some(el) = e
+            .target()
+            .and_then(|t| t.dyn_into::<HtmlTextAreaElement>().ok())
+        {
+            state.set(el.value());
+        }
+    })
+}
+
+#[derive(PartialEq, Properties)]
+struct RequiredKeysEditorProps {
+    config: Rc<VaultConfig>,
+    required_keys: Option<RequiredKeys>,
+    on_change: Callback<Option<RequiredKeys>>,
+}
+
+/// Choose whether a new entry needs several keys to decrypt, and which.
+#[function_component]
+fn RequiredKeysEditor(props: &RequiredKeysEditorProps) -> Html {
+    let credential_ids: Vec<CredentialId> = props
+        .config
+        .user
+        .keypairs
+        .iter()
+        .filter_map(|wkp| wkp.additional_data().ok())
+        .map(|ad| ad.credential_id())
+        .collect();
+
+    let on_toggle = {
+        let credential_ids = credential_ids.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |e: Event| {
+            if let Some(el) = e
+                .target()
+                .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+            {
+                on_change.emit(el.checked().then(|| RequiredKeys {
+                    threshold: 2,
+                    credential_ids: credential_ids.clone(),
+                }));
+            }
+        })
+    };
+
+    let toggle = html! {
+        <label>
+            <input
+                type="checkbox"
+                checked={props.required_keys.is_some()}
+                onchange={on_toggle}
+            />
+            { "Require several keys together to decrypt" }
+        </label>
+    };
+
+    let Some(required_keys) = &props.required_keys else {
+        return html! { <div>{ toggle }</div> };
+    };
+
+    let on_change_threshold = {
+        let required_keys = required_keys.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |e: Event| {
+            if let Some(threshold) = e
+                .target()
+                .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+                .and_then(|el| el.value().parse().ok())
+            {
+                on_change.emit(Some(RequiredKeys {
+                    threshold,
+                    ..required_keys.clone()
+                }));
+            }
+        })
+    };
+
+    html! {
+        <div>
+            { toggle }
+            <p>
+                { "Any " }
+                <input
+                    type="number"
+                    min="1"
+                    max={required_keys.credential_ids.len().to_string()}
+                    value={required_keys.threshold.to_string()}
+                    onchange={on_change_threshold}
+                />
+                { format!(" of these {} keys:", required_keys.credential_ids.len()) }
+            </p>
+            <ul>
+                {
+                    credential_ids.iter()
+                        .map(|cred_id| {
+                            let selected = required_keys.credential_ids.contains(cred_id);
+                            let on_select = {
+                                let cred_id = cred_id.clone();
+                                let required_keys = required_keys.clone();
+                                let on_change = props.on_change.clone();
+                                Callback::from(move |_| {
+                                    let mut required_keys = required_keys.clone();
+                                    if selected {
+                                        required_keys.credential_ids.retain(|id| *id != cred_id);
+                                    } else {
+                                        required_keys.credential_ids.push(cred_id.clone());
+                                    }
+                                    on_change.emit(Some(required_keys));
+                                })
+                            };
+                            html! {
+                                <li key={cred_id.b64url()}>
+                                    <label>
+                                        <input type="checkbox" checked={selected} onchange={on_select} />
+                                        {
+                                            props.config.get_credential_nickname(cred_id)
+                                                .map(|s| s.to_string())
+                                                .unwrap_or_else(|| cred_id.b64_abbrev(24))
+                                        }
+                                    </label>
+                                </li>
+                            }
+                        })
+                        .collect::<Html>()
+                }
+            </ul>
+        </div>
+    }
+}
+
+#[derive(PartialEq, Properties)]
+struct CustomFieldsEditorProps {
+    fields: Vec<CustomField>,
+    on_change: Callback<Vec<CustomField>>,
+}
+
+#[function_component]
+fn CustomFieldsEditor(props: &CustomFieldsEditorProps) -> Html {
+    let update = |index: usize, f: fn(&mut CustomField, &HtmlInputElement)| {
+        let fields = props.fields.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |e: Event| {
+            if let Some(el) = e
+                .target()
+                .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+            {
+                let mut fields = fields.clone();
+                f(&mut fields[index], &el);
+                on_change.emit(fields);
+            }
+        })
+    };
+
+    let on_add = {
+        let fields = props.fields.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |_| {
+            let mut fields = fields.clone();
+            fields.push(CustomField::default());
+            on_change.emit(fields);
+        })
+    };
+
+    html! {
+        <div>
+            <p>{ "Custom fields:" }</p>
+            <ul>
+                {
+                    props.fields.iter().enumerate()
+                        .map(|(index, field)| {
+                            let on_remove = {
+                                let fields = props.fields.clone();
+                                let on_change = props.on_change.clone();
+                                Callback::from(move |_| {
+                                    let mut fields = fields.clone();
+                                    fields.remove(index);
+                                    on_change.emit(fields);
+                                })
+                            };
+                            html! {
+                                <li>
+                                    <input
+                                        type="text"
+                                        placeholder="Name"
+                                        value={field.name.clone()}
+                                        onchange={update(index, |field, el| field.name = el.value())}
+                                    />
+                                    <input
+                                        type={if field.hidden { "password" } else { "text" }}
+                                        placeholder="Value"
+                                        value={field.value.clone()}
+                                        onchange={update(index, |field, el| field.value = el.value())}
+                                    />
+                                    <label>
+                                        <input
+                                            type="checkbox"
+                                            checked={field.hidden}
+                                            onchange={update(index, |field, el| field.hidden = el.checked())}
+                                        />
+                                        { "Hidden" }
+                                    </label>
+                                    <button type="button" onclick={on_remove}>{ "Remove" }</button>
+                                </li>
+                            }
+                        })
+                        .collect::<Html>()
+                }
+            </ul>
+            <button type="button" onclick={on_add}>{ "Add field" }</button>
+        </div>
+    }
+}
+
 #[styled_component]
 pub fn InsertContent(props: &Props) -> Html {
+    let kind = use_state(|| EntryKind::Login);
     let name = use_state(|| "".to_string());
-    let content = use_state(|| "".to_string());
+    let username = use_state(|| "".to_string());
+    let password = use_state(|| "".to_string());
+    let urls = use_state(|| "".to_string());
+    let notes = use_state(|| "".to_string());
+    let custom_fields = use_state(Vec::<CustomField>::new);
+    let totp = use_state(|| "".to_string());
+    let text = use_state(|| "".to_string());
+    let file = use_state(|| None::<Result<Attachment, String>>);
+    let file_input = use_node_ref();
+    let required_keys = use_state(|| None::<RequiredKeys>);
+
+    let parsed_totp: Option<Result<Totp, Error>> =
+        Some(totp.trim()).filter(|s| !s.is_empty()).map(Totp::parse);
+    let totp_invalid = *kind == EntryKind::Login && matches!(parsed_totp, Some(Err(_)));
+    let file_missing = *kind == EntryKind::File && !matches!(*file, Some(Ok(_)));
+    let required_keys_invalid = required_keys.as_ref().is_some_and(|required_keys| {
+        required_keys.threshold == 0
+            || usize::from(required_keys.threshold) > required_keys.credential_ids.len()
+    });
 
     let on_submit = {
+        let kind = kind.clone();
         let name = name.clone();
-        let content = content.clone();
+        let username = username.clone();
+        let password = password.clone();
+        let urls = urls.clone();
+        let notes = notes.clone();
+        let custom_fields = custom_fields.clone();
+        let totp = totp.clone();
+        let text = text.clone();
+        let file = file.clone();
+        let file_input = file_input.clone();
+        let required_keys = required_keys.clone();
         let on_submit = props.on_submit.clone();
         move |e: SubmitEvent| {
             e.prevent_default();
-            on_submit.emit((name.trim().to_string(), Vec::from((*content).clone())));
+            let entry = match *kind {
+                EntryKind::Login => Entry::Login(Login {
+                    username: (*username).clone(),
+                    password: (*password).clone(),
+                    urls: urls
+                        .lines()
+                        .map(str::trim)
+                        .filter(|url| !url.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                    notes: (*notes).clone(),
+                    custom_fields: custom_fields
+                        .iter()
+                        .filter(|field| !field.name.trim().is_empty())
+                        .cloned()
+                        .collect(),
+                    totp: Totp::parse(&totp).ok(),
+                }),
+                EntryKind::Text => Entry::Text {
+                    text: (*text).clone(),
+                },
+                EntryKind::File => match &*file {
+                    Some(Ok(attachment)) => Entry::File(attachment.clone()),
+                    _ => return,
+                },
+            };
+            on_submit.emit((name.trim().to_string(), entry, (*required_keys).clone()));
             name.set("".to_string());
-            content.set("".to_string());
+            username.set("".to_string());
+            password.set("".to_string());
+            urls.set("".to_string());
+            notes.set("".to_string());
+            custom_fields.set(Vec::new());
+            totp.set("".to_string());
+            text.set("".to_string());
+            file.set(None);
+            if let Some(el) = file_input.cast::<HtmlInputElement>() {
+                el.set_value("");
+            }
         }
     };
 
-    let on_change_name = {
-        let name = name.clone();
-        move |e: InputEvent| {
+    let on_change_kind = {
+        let kind = kind.clone();
+        move |e: Event| {
             if let Some(el) = e
                 .target()
-                .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+                .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
             {
-                name.set(el.value());
+                kind.set(match el.value().as_str() {
+                    "text" => EntryKind::Text,
+                    "file" => EntryKind::File,
+                    _ => EntryKind::Login,
+                });
             }
         }
     };
 
-    let on_change_content = {
-        let content = content.clone();
+    let on_change_file = {
+        let name = name.clone();
+        let file = file.clone();
+        let max_file_size = props.max_file_size;
         move |e: Event| {
-            if let Some(el) = e
+            let selected = e
                 .target()
-                .and_then(|t| t.dyn_into::<HtmlTextAreaElement>().ok())
-            {
-                content.set(el.value());
+                .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+                .and_then(|el| el.files())
+                .and_then(|files| files.get(0));
+            let name = name.clone();
+            let file = file.clone();
+            match selected {
+                Some(selected) => spawn_local(async move {
+                    match read_file(selected, max_file_size).await {
+                        Ok(attachment) => {
+                            if name.is_empty() {
+                                name.set(attachment.filename.clone());
+                            }
+                            file.set(Some(Ok(attachment)));
+                        }
+                        Err(Error::JsError(e)) => {
+                            console::log_2(&"Failed to read file:".into(), &e);
+                            file.set(Some(Err("Failed to read file".to_string())));
+                        }
+                        Err(err) => {
+                            file.set(Some(Err(err.to_string())));
+                        }
+                    }
+                }),
+                None => file.set(None),
             }
         }
     };
 
-    let name_exists: bool = props.config.contents.contains_key(&*name);
+    let on_change_custom_fields = {
+        let custom_fields = custom_fields.clone();
+        Callback::from(move |fields| custom_fields.set(fields))
+    };
+
+    let on_change_required_keys = {
+        let required_keys = required_keys.clone();
+        Callback::from(move |keys| required_keys.set(keys))
+    };
+
+    let on_generate_password = {
+        let password = password.clone();
+        Callback::from(move |generated| password.set(generated))
+    };
+
+    let name_exists: bool = match &props.names {
+        Some(names) => names.contains_name(name.trim()),
+        None => props.config.contents.contains_key(name.trim()),
+    };
+    // Checked again when the entry is added, once the names are decrypted
+    let name_unchecked = props.config.has_encrypted_names() && props.names.is_none();
 
     if props.config.user.keypairs.is_empty() {
         html! {
@@ -99,21 +497,149 @@ pub fn InsertContent(props: &Props) -> Html {
                     <input
                         type="text"
                         value={ (*name).clone() }
-                        oninput={on_change_name}
+                        oninput={on_input(&name)}
                     />
                     <ExistsIndicator {name_exists} />
+                    {
+                        if name_unchecked && !name.is_empty() {
+                            html! { <span>{ " Unlock the vault to check for duplicate names" }</span> }
+                        } else {
+                            html! { <></> }
+                        }
+                    }
                 </div>
                 <div>
-                    <p>{ "Content:" }</p>
-                    <textarea
-                        value={ (*content).clone() }
-                        onchange={on_change_content}
-                    />
+                    <p>{ "Type:" }</p>
+                    <select onchange={on_change_kind}>
+                        <option value="login" selected={*kind == EntryKind::Login}>{ "Login" }</option>
+                        <option value="text" selected={*kind == EntryKind::Text}>{ "Text" }</option>
+                        <option value="file" selected={*kind == EntryKind::File}>{ "File" }</option>
+                    </select>
                 </div>
+                {
+                    match *kind {
+                        EntryKind::Login => html! {
+                            <>
+                                <div>
+                                    <p>{ "Username:" }</p>
+                                    <input
+                                        type="text"
+                                        autocomplete="off"
+                                        value={ (*username).clone() }
+                                        oninput={on_input(&username)}
+                                    />
+                                </div>
+                                <div>
+                                    <p>{ "Password:" }</p>
+                                    <input
+                                        type="password"
+                                        autocomplete="new-password"
+                                        value={ (*password).clone() }
+                                        oninput={on_input(&password)}
+                                    />
+                                    <PasswordGenerator on_generate={on_generate_password} />
+                                </div>
+                                <div>
+                                    <p>{ "URLs (one per line):" }</p>
+                                    <textarea
+                                        value={ (*urls).clone() }
+                                        onchange={on_change_textarea(&urls)}
+                                    />
+                                </div>
+                                <div>
+                                    <p>{ "Notes:" }</p>
+                                    <textarea
+                                        value={ (*notes).clone() }
+                                        onchange={on_change_textarea(&notes)}
+                                    />
+                                </div>
+                                <div>
+                                    <p>{ "TOTP secret or otpauth:// URI (optional):" }</p>
+                                    <input
+                                        type="text"
+                                        autocomplete="off"
+                                        value={ (*totp).clone() }
+                                        oninput={on_input(&totp)}
+                                    />
+                                    {
+                                        if let Some(Err(err)) = &parsed_totp {
+                                            html! {
+                                                <span class={css! {
+                                                    color: #ff0000;
+                                                    margin-left: ${"1em"};
+                                                }}>
+                                                    { err.to_string() }
+                                                </span>
+                                            }
+                                        } else {
+                                            html! { <></> }
+                                        }
+                                    }
+                                </div>
+                                <CustomFieldsEditor
+                                    fields={(*custom_fields).clone()}
+                                    on_change={on_change_custom_fields}
+                                />
+                            </>
+                        },
+                        EntryKind::Text => html! {
+                            <div>
+                                <p>{ "Content:" }</p>
+                                <textarea
+                                    value={ (*text).clone() }
+                                    onchange={on_change_textarea(&text)}
+                                />
+                            </div>
+                        },
+                        EntryKind::File => html! {
+                            <div>
+                                <p>{ format!("File (at most {} bytes):", props.max_file_size) }</p>
+                                <input
+                                    type="file"
+                                    ref={file_input.clone()}
+                                    onchange={on_change_file}
+                                />
+                                {
+                                    if let Some(Err(msg)) = &*file {
+                                        html! {
+                                            <span class={css! {
+                                                color: #ff0000;
+                                                margin-left: ${"1em"};
+                                            }}>
+                                                { msg }
+                                            </span>
+                                        }
+                                    } else {
+                                        html! { <></> }
+                                    }
+                                }
+                            </div>
+                        },
+                    }
+                }
+                {
+                    if props.config.user.keypairs.len() > 1 {
+                        html! {
+                            <RequiredKeysEditor
+                                config={Rc::clone(&props.config)}
+                                required_keys={(*required_keys).clone()}
+                                on_change={on_change_required_keys}
+                            />
+                        }
+                    } else {
+                        html! { <></> }
+                    }
+                }
                 <div>
                     <button
                         type="submit"
-                        disabled={name_exists || name.is_empty()}
+                        disabled={
+                            name_exists
+                                || name.is_empty()
+                                || totp_invalid
+                                || file_missing
+                                || required_keys_invalid
+                        }
                     >{ "Encrypt" }</button>
                 </div>
             </form>
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::console;
+use web_sys::Event;
+use web_sys::HtmlSelectElement;
+use yew::function_component;
+use yew::html;
+use yew::use_effect_with_deps;
+use yew::use_state;
+use yew::Callback;
+use yew::Html;
+use yew::Properties;
+
+use crate::crypto::WebCrypto;
+use crate::data::vault::MergeEntry;
+use crate::data::vault::MergePreview;
+use crate::data::vault::MergeResolution;
+use crate::data::vault::VaultConfig;
+use crate::error::Error;
+use crate::hooks::session::UseSessionHandle;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    pub config: Rc<VaultConfig>,
+    pub imported: Rc<VaultConfig>,
+    pub session: UseSessionHandle,
+    pub set_config: Callback<Rc<VaultConfig>, Result<(), Error>>,
+    pub on_done: Callback<()>,
+}
+
+fn list(title: &str, items: &[String]) -> Html {
+    if items.is_empty() {
+        html! { <></> }
+    } else {
+        html! {
+            <>
+                <p>{ title }</p>
+                <ul>
+                    { items.iter().map(|item| html! { <li key={item.clone()}>{ item }</li> }).collect::<Html>() }
+                </ul>
+            </>
+        }
+    }
+}
+
+/// Preview the changes of merging an imported vault config into the local
+/// one, and let the user keep the local state of entries the merge changes.
+#[function_component]
+pub fn MergeImport(props: &Props) -> Html {
+    let preview = use_state(|| None::<Result<Rc<MergePreview>, String>>);
+    let resolutions = use_state(HashMap::<String, MergeResolution>::new);
+
+    {
+        let preview = preview.clone();
+        let session = props.session.clone();
+        let deps = (
+            Rc::clone(&props.config),
+            Rc::clone(&props.imported),
+            props.session.is_unlocked(),
+        );
+        use_effect_with_deps(
+            move |(config, imported, _)| {
+                let config = Rc::clone(config);
+                let imported = Rc::clone(imported);
+                let session = session.get();
+                if config.has_encrypted_names() && session.is_none() {
+                    preview.set(Some(Err(
+                        "Unlock the vault to compare encrypted entry names.".to_string(),
+                    )));
+                } else {
+                    spawn_local(async move {
+                        let master_key = session.as_ref().map(|session| &session.master_key);
+                        preview.set(Some(
+                            config
+                                .merge_preview(&WebCrypto, &imported, master_key)
+                                .await
+                                .map(Rc::new)
+                                .map_err(|err| err.to_string()),
+                        ));
+                    });
+                }
+            },
+            deps,
+        );
+    }
+
+    let on_merge = {
+        let config = Rc::clone(&props.config);
+        let session = props.session.clone();
+        let set_config = props.set_config.clone();
+        let on_done = props.on_done.clone();
+        let preview = preview.clone();
+        let resolutions = resolutions.clone();
+        Callback::from(move |_| {
+            let Some(Ok(merge_preview)) = (*preview).clone() else {
+                return;
+            };
+            let config = Rc::clone(&config);
+            let session = session.get();
+            let set_config = set_config.clone();
+            let on_done = on_done.clone();
+            let resolutions = (*resolutions).clone();
+            spawn_local(async move {
+                let master_key = session.as_ref().map(|session| &session.master_key);
+                let result = config
+                    .merge(&WebCrypto, &merge_preview, &resolutions, master_key)
+                    .await;
+                match result.and_then(|merged| set_config.emit(Rc::new(merged))) {
+                    Ok(()) => {
+                        console::log_1(&"Successfully merged imported config!".into());
+                        on_done.emit(());
+                    }
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Failed to merge imported config:".into(), &e);
+                    }
+                    Err(err) => {
+                        console::log_2(
+                            &"Failed to merge imported config:".into(),
+                            &err.to_string().into(),
+                        );
+                    }
+                }
+            });
+        })
+    };
+
+    let on_cancel = {
+        let on_done = props.on_done.clone();
+        Callback::from(move |_| on_done.emit(()))
+    };
+
+    let resolution_select = |entry: &MergeEntry, keep_label: &str, take_label: &str| {
+        let resolutions = resolutions.clone();
+        let current = resolutions
+            .get(&entry.key)
+            .copied()
+            .unwrap_or(MergeResolution::TakeMerged);
+        let onchange = {
+            let key = entry.key.clone();
+            Callback::from(move |e: Event| {
+                if let Some(el) = e
+                    .target()
+                    .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
+                {
+                    let mut updated = (*resolutions).clone();
+                    updated.insert(
+                        key.clone(),
+                        match el.value().as_str() {
+                            "keep" => MergeResolution::KeepLocal,
+                            _ => MergeResolution::TakeMerged,
+                        },
+                    );
+                    resolutions.set(updated);
+                }
+            })
+        };
+        html! {
+            <li key={entry.key.clone()}>
+                { &entry.name }
+                { " " }
+                <select {onchange}>
+                    <option value="take" selected={current == MergeResolution::TakeMerged}>{ take_label }</option>
+                    <option value="keep" selected={current == MergeResolution::KeepLocal}>{ keep_label }</option>
+                </select>
+            </li>
+        }
+    };
+
+    html! {
+        <div>
+            <h2>{ "Merge imported vault config" }</h2>
+            {
+                match &*preview {
+                    None => html! { <p>{ "Comparing..." }</p> },
+                    Some(Err(msg)) => html! { <p>{ msg }</p> },
+                    Some(Ok(preview)) if preview.is_empty() => html! {
+                        <p>{ "The imported config has no changes." }</p>
+                    },
+                    Some(Ok(preview)) => html! {
+                        <>
+                            { list("Keys to add:", &preview.added_credentials) }
+                            { list("Keys deleted on the other device:", &preview.removed_credentials) }
+                            {
+                                if preview.added_entries.is_empty() {
+                                    html! { <></> }
+                                } else {
+                                    html! {
+                                        <>
+                                            <p>{ "Entries to add:" }</p>
+                                            <ul>
+                                                {
+                                                    preview.added_entries.iter()
+                                                        .map(|entry| resolution_select(entry, "Skip", "Add"))
+                                                        .collect::<Html>()
+                                                }
+                                            </ul>
+                                        </>
+                                    }
+                                }
+                            }
+                            {
+                                if preview.conflicts.is_empty() {
+                                    html! { <></> }
+                                } else {
+                                    html! {
+                                        <>
+                                            <p>{ "Entries changed on the other device:" }</p>
+                                            <ul>
+                                                {
+                                                    preview.conflicts.iter()
+                                                        .map(|entry| resolution_select(entry, "Keep local", "Use newest"))
+                                                        .collect::<Html>()
+                                                }
+                                            </ul>
+                                        </>
+                                    }
+                                }
+                            }
+                            {
+                                if preview.removed_entries.is_empty() {
+                                    html! { <></> }
+                                } else {
+                                    html! {
+                                        <>
+                                            <p>{ "Entries deleted on the other device:" }</p>
+                                            <ul>
+                                                {
+                                                    preview.removed_entries.iter()
+                                                        .map(|entry| resolution_select(entry, "Keep", "Delete"))
+                                                        .collect::<Html>()
+                                                }
+                                            </ul>
+                                        </>
+                                    }
+                                }
+                            }
+                            <button onclick={on_merge}>{ "Merge" }</button>
+                        </>
+                    },
+                }
+            }
+            <button onclick={on_cancel}>{ "Cancel" }</button>
+        </div>
+    }
+}
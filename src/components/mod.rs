@@ -1,9 +1,17 @@
 pub mod app;
 pub mod collapse;
+pub mod contacts;
 pub mod create_button;
 pub mod credentials_list;
+pub mod entry_view;
 pub mod files_list;
 pub mod import;
 pub mod init_config;
 pub mod insert_content;
+pub mod merge_import;
+pub mod padding_select;
+pub mod passphrase;
+pub mod password_generator;
+pub mod recovery;
+pub mod sync_button;
 pub mod vault;
@@ -0,0 +1,75 @@
+use std::rc::Rc;
+
+use wasm_bindgen::JsCast;
+use web_sys::console;
+use web_sys::Event;
+use web_sys::HtmlSelectElement;
+use yew::function_component;
+use yew::html;
+use yew::Callback;
+use yew::Html;
+use yew::Properties;
+
+use crate::crypto::Padding;
+use crate::data::vault::VaultConfig;
+use crate::error::Error;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    pub config: Rc<VaultConfig>,
+    pub set_config: Callback<Rc<VaultConfig>, Result<(), Error>>,
+}
+
+/// Choose how entries are padded, so that their stored size reveals less about
+/// the length of their contents.
+#[function_component]
+pub fn PaddingSelect(props: &Props) -> Html {
+    let onchange = {
+        let config = Rc::clone(&props.config);
+        let set_config = props.set_config.clone();
+        Callback::from(move |e: Event| {
+            if let Some(el) = e
+                .target()
+                .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
+            {
+                let mut config = Rc::clone(&config);
+                let padding = match el.value().as_str() {
+                    "padme" => Some(Padding::Padme),
+                    "power_of_two" => Some(Padding::PowerOfTwo),
+                    _ => None,
+                };
+                let result = Rc::make_mut(&mut config)
+                    .set_padding(padding)
+                    .map(|_| ())
+                    .and_then(|()| set_config.emit(config));
+                match result {
+                    Ok(()) => {}
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Failed to set padding:".into(), &e);
+                    }
+                    Err(err) => {
+                        console::log_2(&"Failed to set padding:".into(), &err.to_string().into());
+                    }
+                }
+            }
+        })
+    };
+
+    let padding = props.config.padding;
+
+    html! {
+        <p>
+            { "Pad entries: " }
+            <select {onchange}>
+                <option value="none" selected={padding.is_none()}>{ "No padding" }</option>
+                <option value="padme" selected={padding == Some(Padding::Padme)}>
+                    { "Padmé (up to 12% larger)" }
+                </option>
+                <option value="power_of_two" selected={padding == Some(Padding::PowerOfTwo)}>
+                    { "Power of two (up to twice as large)" }
+                </option>
+            </select>
+            { " Applies to entries encrypted from now on. Re-encrypt all entries to pad existing ones." }
+        </p>
+    }
+}
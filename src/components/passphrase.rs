@@ -0,0 +1,282 @@
+use std::rc::Rc;
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::console;
+use web_sys::HtmlInputElement;
+use web_sys::InputEvent;
+use web_sys::SubmitEvent;
+use yew::function_component;
+use yew::html;
+use yew::use_state;
+use yew::Callback;
+use yew::Html;
+use yew::Properties;
+use yew::UseStateHandle;
+
+use crate::crypto::passphrase::Argon2Params;
+use crate::crypto::WebCrypto;
+use crate::data::vault::VaultConfig;
+use crate::error::Error;
+use crate::hooks::session::UseSessionHandle;
+
+const WEAKER_WARNING: &str = "A passphrase is weaker than a hardware key: anyone who gets a copy \
+                              of the vault can try to guess it offline, as fast as their \
+                              computers allow. Use it only on browsers without PRF support, and \
+                              choose a long passphrase.";
+
+fn set_from_input(state: UseStateHandle<String>) -> Callback<InputEvent> {
+    Callback::from(move |e: InputEvent| {
+        if let Some(el) = e
+            .target()
+            .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+        {
+            state.set(el.value());
+        }
+    })
+}
+
+#[derive(PartialEq, Properties)]
+pub struct SetupProps {
+    pub config: Rc<VaultConfig>,
+    pub session: UseSessionHandle,
+    pub set_config: Callback<Rc<VaultConfig>, Result<(), Error>>,
+}
+
+/// Set, replace or remove the passphrase and its Argon2id cost.
+#[function_component]
+pub fn PassphraseSetup(props: &SetupProps) -> Html {
+    let defaults = props
+        .config
+        .passphrase_key
+        .as_ref()
+        .map(|key| key.params)
+        .unwrap_or_default();
+    let passphrase = use_state(|| "".to_string());
+    let confirm = use_state(|| "".to_string());
+    let memory_mib = use_state(|| (defaults.memory_kib / 1024).to_string());
+    let iterations = use_state(|| defaults.iterations.to_string());
+    let working = use_state(|| false);
+    let error = use_state(|| None::<String>);
+
+    let update = {
+        let config = Rc::clone(&props.config);
+        let session = props.session.clone();
+        let set_config = props.set_config.clone();
+        let passphrase = passphrase.clone();
+        let confirm = confirm.clone();
+        let working = working.clone();
+        let error = error.clone();
+        move |new_passphrase: Option<String>, params: Argon2Params| {
+            let config = Rc::clone(&config);
+            let session = session.clone();
+            let set_config = set_config.clone();
+            let passphrase = passphrase.clone();
+            let confirm = confirm.clone();
+            let working = working.clone();
+            let error = error.clone();
+            working.set(true);
+            spawn_local(async move {
+                let mut config = Rc::clone(&config);
+                let result = match session.unlock(&config).await {
+                    Ok(session) => Rc::make_mut(&mut config)
+                        .set_passphrase(
+                            &WebCrypto,
+                            new_passphrase.as_deref(),
+                            params,
+                            &session.master_key,
+                        )
+                        .await
+                        .map(|_| ()),
+                    Err(err) => Err(err),
+                };
+                working.set(false);
+                match result.and_then(|()| set_config.emit(config)) {
+                    Ok(()) => {
+                        passphrase.set("".to_string());
+                        confirm.set("".to_string());
+                        error.set(None);
+                    }
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Failed to set passphrase:".into(), &e);
+                        error.set(Some("JavaScript error".to_string()));
+                    }
+                    Err(err) => {
+                        console::log_2(
+                            &"Failed to set passphrase:".into(),
+                            &err.to_string().into(),
+                        );
+                        error.set(Some(err.to_string()));
+                    }
+                }
+            });
+        }
+    };
+
+    let on_submit = {
+        let update = update.clone();
+        let passphrase = passphrase.clone();
+        let confirm = confirm.clone();
+        let memory_mib = memory_mib.clone();
+        let iterations = iterations.clone();
+        let error = error.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            if *passphrase != *confirm {
+                error.set(Some("The passphrases do not match".to_string()));
+                return;
+            }
+            match (memory_mib.parse::<u32>(), iterations.parse::<u32>()) {
+                (Ok(memory_mib), Ok(iterations)) if memory_mib > 0 && iterations > 0 => update(
+                    Some((*passphrase).clone()),
+                    Argon2Params {
+                        memory_kib: memory_mib.saturating_mul(1024),
+                        iterations,
+                        ..Argon2Params::default()
+                    },
+                ),
+                _ => error.set(Some(
+                    "Memory and iterations must be positive integers".to_string(),
+                )),
+            }
+        })
+    };
+
+    let on_remove = Callback::from(move |_| update(None, Argon2Params::default()));
+
+    html! {
+        <form onsubmit={on_submit}>
+            <p><strong>{ "Warning: " }</strong>{ WEAKER_WARNING }</p>
+            <input
+                type="password"
+                autocomplete="new-password"
+                placeholder="Passphrase"
+                value={(*passphrase).clone()}
+                oninput={set_from_input(passphrase.clone())}
+            />
+            <input
+                type="password"
+                autocomplete="new-password"
+                placeholder="Confirm passphrase"
+                value={(*confirm).clone()}
+                oninput={set_from_input(confirm.clone())}
+            />
+            <label>
+                { " Memory (MiB): " }
+                <input
+                    type="number"
+                    min="1"
+                    value={(*memory_mib).clone()}
+                    oninput={set_from_input(memory_mib.clone())}
+                />
+            </label>
+            <label>
+                { " Iterations: " }
+                <input
+                    type="number"
+                    min="1"
+                    value={(*iterations).clone()}
+                    oninput={set_from_input(iterations.clone())}
+                />
+            </label>
+            <button type="submit" disabled={*working || passphrase.is_empty()}>
+                {
+                    if props.config.passphrase_key.is_some() {
+                        "Replace passphrase"
+                    } else {
+                        "Set passphrase"
+                    }
+                }
+            </button>
+            {
+                if props.config.passphrase_key.is_some() {
+                    html! {
+                        <button type="button" onclick={on_remove} disabled={*working}>
+                            { "Remove passphrase" }
+                        </button>
+                    }
+                } else {
+                    html! { <></> }
+                }
+            }
+            {
+                if let Some(msg) = &*error {
+                    html! { <span>{ " " }{ msg }</span> }
+                } else {
+                    html! { <></> }
+                }
+            }
+        </form>
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub struct UnlockProps {
+    pub config: Rc<VaultConfig>,
+    pub session: UseSessionHandle,
+}
+
+/// Unlock the vault with the passphrase, on browsers without PRF support.
+#[function_component]
+pub fn PassphraseUnlock(props: &UnlockProps) -> Html {
+    let passphrase = use_state(|| "".to_string());
+    let working = use_state(|| false);
+    let error = use_state(|| None::<String>);
+
+    let on_submit = {
+        let config = Rc::clone(&props.config);
+        let session = props.session.clone();
+        let passphrase = passphrase.clone();
+        let working = working.clone();
+        let error = error.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let config = Rc::clone(&config);
+            let session = session.clone();
+            let passphrase = passphrase.clone();
+            let working = working.clone();
+            let error = error.clone();
+            working.set(true);
+            spawn_local(async move {
+                let result = config.unlock_with_passphrase(&WebCrypto, &passphrase).await;
+                working.set(false);
+                match result {
+                    Ok((unlocked, master_key)) => {
+                        session.open(unlocked, master_key);
+                        passphrase.set("".to_string());
+                        error.set(None);
+                    }
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Passphrase unlock failed:".into(), &e);
+                        error.set(Some("JavaScript error".to_string()));
+                    }
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+            });
+        })
+    };
+
+    html! {
+        <form onsubmit={on_submit}>
+            { "No PRF support? " }
+            <input
+                type="password"
+                autocomplete="current-password"
+                placeholder="Passphrase"
+                value={(*passphrase).clone()}
+                oninput={set_from_input(passphrase.clone())}
+            />
+            <button type="submit" disabled={*working || passphrase.is_empty()}>
+                { "Unlock with passphrase" }
+            </button>
+            <span>{ " (weaker than a hardware key)" }</span>
+            {
+                if let Some(msg) = &*error {
+                    html! { <span>{ " " }{ msg }</span> }
+                } else {
+                    html! { <></> }
+                }
+            }
+        </form>
+    }
+}
@@ -0,0 +1,194 @@
+use wasm_bindgen::JsCast;
+use web_sys::Event;
+use web_sys::HtmlInputElement;
+use web_sys::HtmlSelectElement;
+use yew::function_component;
+use yew::html;
+use yew::use_state;
+use yew::Callback;
+use yew::Html;
+use yew::Properties;
+use yew::UseStateHandle;
+
+use crate::crypto::WebCrypto;
+use crate::generator::generate_passphrase;
+use crate::generator::generate_password;
+use crate::generator::PassphrasePolicy;
+use crate::generator::PasswordPolicy;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Password,
+    Passphrase,
+}
+
+fn on_toggle(state: &UseStateHandle<bool>) -> Callback<Event> {
+    let state = state.clone();
+    Callback::from(move |e: Event| {
+        if let Some(el) = e
+            .target()
+            .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+        {
+            state.set(el.checked());
+        }
+    })
+}
+
+fn on_number(state: &UseStateHandle<usize>) -> Callback<Event> {
+    let state = state.clone();
+    Callback::from(move |e: Event| {
+        if let Some(value) = e
+            .target()
+            .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+            .and_then(|el| el.value().parse().ok())
+        {
+            state.set(value);
+        }
+    })
+}
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    pub on_generate: Callback<String>,
+}
+
+#[function_component]
+pub fn PasswordGenerator(props: &Props) -> Html {
+    let password_defaults = PasswordPolicy::default();
+    let passphrase_defaults = PassphrasePolicy::default();
+
+    let mode = use_state(|| Mode::Password);
+    let length = use_state(|| password_defaults.length);
+    let lowercase = use_state(|| password_defaults.lowercase);
+    let uppercase = use_state(|| password_defaults.uppercase);
+    let digits = use_state(|| password_defaults.digits);
+    let symbols = use_state(|| password_defaults.symbols);
+    let exclude_lookalikes = use_state(|| password_defaults.exclude_lookalikes);
+    let words = use_state(|| passphrase_defaults.words);
+    let capitalize = use_state(|| passphrase_defaults.capitalize);
+    let result = use_state(|| None::<Result<f64, String>>);
+
+    let on_generate = {
+        let mode = mode.clone();
+        let length = length.clone();
+        let lowercase = lowercase.clone();
+        let uppercase = uppercase.clone();
+        let digits = digits.clone();
+        let symbols = symbols.clone();
+        let exclude_lookalikes = exclude_lookalikes.clone();
+        let words = words.clone();
+        let capitalize = capitalize.clone();
+        let result = result.clone();
+        let on_generate = props.on_generate.clone();
+        Callback::from(move |_| {
+            let generated = match *mode {
+                Mode::Password => generate_password(
+                    &WebCrypto,
+                    &PasswordPolicy {
+                        length: *length,
+                        lowercase: *lowercase,
+                        uppercase: *uppercase,
+                        digits: *digits,
+                        symbols: *symbols,
+                        exclude_lookalikes: *exclude_lookalikes,
+                    },
+                ),
+                Mode::Passphrase => generate_passphrase(
+                    &WebCrypto,
+                    &PassphrasePolicy {
+                        words: *words,
+                        capitalize: *capitalize,
+                        ..PassphrasePolicy::default()
+                    },
+                ),
+            };
+            match generated {
+                Ok(generated) => {
+                    on_generate.emit(generated.value);
+                    result.set(Some(Ok(generated.entropy_bits)));
+                }
+                Err(err) => result.set(Some(Err(err.to_string()))),
+            }
+        })
+    };
+
+    let on_change_mode = {
+        let mode = mode.clone();
+        move |e: Event| {
+            if let Some(el) = e
+                .target()
+                .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
+            {
+                mode.set(match el.value().as_str() {
+                    "passphrase" => Mode::Passphrase,
+                    _ => Mode::Password,
+                });
+            }
+        }
+    };
+
+    html! {
+        <div>
+            <select onchange={on_change_mode}>
+                <option value="password" selected={*mode == Mode::Password}>{ "Password" }</option>
+                <option value="passphrase" selected={*mode == Mode::Passphrase}>{ "Passphrase" }</option>
+            </select>
+            {
+                match *mode {
+                    Mode::Password => html! {
+                        <>
+                            <label>
+                                { "Length: " }
+                                <input type="number" min="1" max="1024" value={length.to_string()} onchange={on_number(&length)} />
+                            </label>
+                            <label>
+                                <input type="checkbox" checked={*lowercase} onchange={on_toggle(&lowercase)} />
+                                { "a-z" }
+                            </label>
+                            <label>
+                                <input type="checkbox" checked={*uppercase} onchange={on_toggle(&uppercase)} />
+                                { "A-Z" }
+                            </label>
+                            <label>
+                                <input type="checkbox" checked={*digits} onchange={on_toggle(&digits)} />
+                                { "0-9" }
+                            </label>
+                            <label>
+                                <input type="checkbox" checked={*symbols} onchange={on_toggle(&symbols)} />
+                                { "Symbols" }
+                            </label>
+                            <label>
+                                <input type="checkbox" checked={*exclude_lookalikes} onchange={on_toggle(&exclude_lookalikes)} />
+                                { "Exclude look-alikes" }
+                            </label>
+                        </>
+                    },
+                    Mode::Passphrase => html! {
+                        <>
+                            <label>
+                                { "Words: " }
+                                <input type="number" min="1" max="64" value={words.to_string()} onchange={on_number(&words)} />
+                            </label>
+                            <label>
+                                <input type="checkbox" checked={*capitalize} onchange={on_toggle(&capitalize)} />
+                                { "Capitalize" }
+                            </label>
+                        </>
+                    },
+                }
+            }
+            <button type="button" onclick={on_generate}>{ "Generate" }</button>
+            {
+                match &*result {
+                    Some(Ok(entropy_bits)) => html! {
+                        <span>{ format!(" About {:.0} bits of entropy", entropy_bits.floor()) }</span>
+                    },
+                    Some(Err(msg)) => html! {
+                        <span>{ " " }{ msg }</span>
+                    },
+                    None => html! { <></> },
+                }
+            }
+        </div>
+    }
+}
@@ -0,0 +1,207 @@
+use std::rc::Rc;
+
+use stylist::yew::styled_component;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::console;
+use web_sys::HtmlInputElement;
+use web_sys::InputEvent;
+use web_sys::SubmitEvent;
+use yew::function_component;
+use yew::html;
+use yew::use_state;
+use yew::Callback;
+use yew::Html;
+use yew::Properties;
+
+use crate::crypto::recovery::RecoveryPhrase;
+use crate::crypto::WebCrypto;
+use crate::data::vault::VaultConfig;
+use crate::error::Error;
+use crate::hooks::session::UseSessionHandle;
+
+#[derive(PartialEq, Properties)]
+pub struct SetupProps {
+    pub config: Rc<VaultConfig>,
+    pub session: UseSessionHandle,
+    pub set_config: Callback<Rc<VaultConfig>, Result<(), Error>>,
+}
+
+#[derive(Clone, PartialEq)]
+enum SetupStatus {
+    Idle,
+    Working,
+
+    /// The new phrase, displayed until dismissed and never again.
+    Created(String),
+    Failed(String),
+}
+
+/// Set up or replace the recovery phrase, and show it once.
+#[styled_component]
+pub fn RecoverySetup(props: &SetupProps) -> Html {
+    let status = use_state(|| SetupStatus::Idle);
+
+    let on_set_up = {
+        let config = Rc::clone(&props.config);
+        let session = props.session.clone();
+        let set_config = props.set_config.clone();
+        let status = status.clone();
+        Callback::from(move |_| {
+            let config = Rc::clone(&config);
+            let session = session.clone();
+            let set_config = set_config.clone();
+            let status = status.clone();
+            status.set(SetupStatus::Working);
+            spawn_local(async move {
+                let mut config = Rc::clone(&config);
+                let result = match session.unlock(&config).await {
+                    Ok(session) => {
+                        Rc::make_mut(&mut config)
+                            .set_up_recovery(&WebCrypto, &session.master_key)
+                            .await
+                    }
+                    Err(err) => Err(err),
+                };
+                match result.and_then(|phrase| set_config.emit(config).map(|()| phrase)) {
+                    Ok(phrase) => status.set(SetupStatus::Created(phrase.to_string())),
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Failed to set up recovery phrase:".into(), &e);
+                        status.set(SetupStatus::Failed("JavaScript error".to_string()));
+                    }
+                    Err(err) => {
+                        console::log_2(
+                            &"Failed to set up recovery phrase:".into(),
+                            &err.to_string().into(),
+                        );
+                        status.set(SetupStatus::Failed(err.to_string()));
+                    }
+                }
+            });
+        })
+    };
+
+    let on_print = Callback::from(|_| {
+        if let Some(Err(err)) = web_sys::window().map(|window| window.print()) {
+            console::error_2(&"Failed to print:".into(), &err);
+        }
+    });
+
+    let on_done = {
+        let status = status.clone();
+        Callback::from(move |_| status.set(SetupStatus::Idle))
+    };
+
+    match &*status {
+        SetupStatus::Created(phrase) => html! {
+            <div>
+                <p>
+                    { "Your recovery phrase is shown below. Write it down or print it, and keep \
+                       it somewhere safe: it decrypts the whole vault without any of your keys. \
+                       It will not be shown again." }
+                </p>
+                <pre class={css! { font-size: ${"1.5em"}; }}>{ phrase }</pre>
+                <button onclick={on_print}>{ "Print" }</button>
+                <button onclick={on_done}>{ "I have saved it" }</button>
+            </div>
+        },
+        status => html! {
+            <p>
+                <button onclick={on_set_up} disabled={*status == SetupStatus::Working}>
+                    {
+                        if props.config.recovery_key.is_some() {
+                            "Replace recovery phrase"
+                        } else {
+                            "Set up recovery phrase"
+                        }
+                    }
+                </button>
+                {
+                    if let SetupStatus::Failed(msg) = status {
+                        html! { <span>{ " " }{ msg }</span> }
+                    } else {
+                        html! { <></> }
+                    }
+                }
+            </p>
+        },
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub struct RecoverProps {
+    pub config: Rc<VaultConfig>,
+    pub session: UseSessionHandle,
+}
+
+/// Unlock the vault with the recovery phrase, if all keys are lost.
+#[function_component]
+pub fn Recover(props: &RecoverProps) -> Html {
+    let phrase = use_state(|| "".to_string());
+    let error = use_state(|| None::<String>);
+
+    let on_input = {
+        let phrase = phrase.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Some(el) = e
+                .target()
+                .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+            {
+                phrase.set(el.value());
+            }
+        })
+    };
+
+    let on_submit = {
+        let config = Rc::clone(&props.config);
+        let session = props.session.clone();
+        let phrase = phrase.clone();
+        let error = error.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let config = Rc::clone(&config);
+            let session = session.clone();
+            let phrase = phrase.clone();
+            let error = error.clone();
+            spawn_local(async move {
+                let result = match phrase.parse::<RecoveryPhrase>() {
+                    Ok(parsed) => config.recover(&WebCrypto, &parsed).await,
+                    Err(err) => Err(err),
+                };
+                match result {
+                    Ok((unlocked, master_key)) => {
+                        session.open(unlocked, master_key);
+                        phrase.set("".to_string());
+                        error.set(None);
+                    }
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Recovery failed:".into(), &e);
+                        error.set(Some("JavaScript error".to_string()));
+                    }
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+            });
+        })
+    };
+
+    html! {
+        <form onsubmit={on_submit}>
+            { "Lost all your keys? " }
+            <input
+                type="password"
+                autocomplete="off"
+                placeholder="Recovery phrase"
+                value={(*phrase).clone()}
+                oninput={on_input}
+            />
+            <button type="submit" disabled={phrase.is_empty()}>{ "Recover" }</button>
+            {
+                if let Some(msg) = &*error {
+                    html! { <span>{ " " }{ msg }</span> }
+                } else {
+                    html! { <></> }
+                }
+            }
+        </form>
+    }
+}
@@ -0,0 +1,116 @@
+use std::rc::Rc;
+
+use wasm_bindgen_futures::spawn_local;
+use web_sys::console;
+use yew::function_component;
+use yew::html;
+use yew::use_state;
+use yew::Callback;
+use yew::Html;
+use yew::Properties;
+
+use crate::data::vault::VaultConfig;
+use crate::error::Error;
+use crate::hooks::session::UseSessionHandle;
+use crate::sync::SyncClient;
+use crate::sync::SyncResult;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    pub server_url: &'static str,
+    pub config: Rc<VaultConfig>,
+    pub session: UseSessionHandle,
+    pub set_config: Callback<Rc<VaultConfig>, Result<(), Error>>,
+
+    /// Called with the copy on the server when merging it needs to be
+    /// reviewed first.
+    pub on_review: Callback<Rc<VaultConfig>>,
+}
+
+#[derive(Clone, PartialEq)]
+enum Status {
+    Idle,
+    Syncing,
+    Synced,
+    NeedsReview,
+    Failed(String),
+}
+
+/// Merge the vault with the copy on the sync server, and store the result
+/// both there and locally. Changes that need review are handed to
+/// `on_review` instead.
+#[function_component]
+pub fn SyncButton(props: &Props) -> Html {
+    let status = use_state(|| Status::Idle);
+
+    let onclick = {
+        let server_url = props.server_url;
+        let config = Rc::clone(&props.config);
+        let session = props.session.clone();
+        let set_config = props.set_config.clone();
+        let on_review = props.on_review.clone();
+        let status = status.clone();
+        Callback::from(move |_| {
+            let config = Rc::clone(&config);
+            let session = session.clone();
+            let set_config = set_config.clone();
+            let on_review = on_review.clone();
+            let status = status.clone();
+            status.set(Status::Syncing);
+            spawn_local(async move {
+                // The write token is derived from a secret under the master key
+                let session = match session.unlock(&config).await {
+                    Ok(session) => session,
+                    Err(err) => {
+                        status.set(Status::Failed(err.to_string()));
+                        return;
+                    }
+                };
+
+                let result = SyncClient::new(server_url)
+                    .sync(&config, &session.master_key)
+                    .await
+                    .and_then(|result| match result {
+                        SyncResult::Synced(synced) if synced == *config => Ok(Status::Synced),
+                        SyncResult::Synced(synced) => {
+                            set_config.emit(Rc::new(synced)).map(|()| Status::Synced)
+                        }
+                        SyncResult::NeedsReview(remote) => {
+                            on_review.emit(Rc::new(remote));
+                            Ok(Status::NeedsReview)
+                        }
+                    });
+                match result {
+                    Ok(new_status) => {
+                        status.set(new_status);
+                    }
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Failed to sync vault:".into(), &e);
+                        status.set(Status::Failed("JavaScript error".to_string()));
+                    }
+                    Err(err) => {
+                        console::log_2(&"Failed to sync vault:".into(), &err.to_string().into());
+                        status.set(Status::Failed(err.to_string()));
+                    }
+                }
+            });
+        })
+    };
+
+    html! {
+        <p>
+            <button {onclick} disabled={*status == Status::Syncing}>{ "Sync" }</button>
+            {
+                match &*status {
+                    Status::Idle => html! { <></> },
+                    Status::Syncing => html! { { " Syncing..." } },
+                    Status::Synced => html! { { " Synced." } },
+                    Status::NeedsReview => html! {
+                        { " The copy on the server has changes to review below. Sync again after merging them." }
+                    },
+                    Status::Failed(msg) => html! { <>{ " Sync failed: " }{ msg }</> },
+                }
+            }
+        </p>
+    }
+}
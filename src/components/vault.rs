@@ -3,74 +3,286 @@ use stylist::yew::styled_component;
 use wasm_bindgen_futures::spawn_local;
 use web_sys::console;
 use yew::html;
+use yew::use_effect_with_deps;
+use yew::use_state;
 use yew::Callback;
 use yew::Html;
 use yew::Properties;
 
+use crate::components::contacts::Contacts;
 use crate::components::create_button::CreateButton;
 use crate::components::credentials_list::CredentialsList;
 use crate::components::files_list::FilesList;
+use crate::components::files_list::ReencryptStatus;
 use crate::components::insert_content::InsertContent;
+use crate::components::insert_content::RequiredKeys;
+use crate::components::merge_import::MergeImport;
+use crate::components::padding_select::PaddingSelect;
+use crate::components::passphrase::PassphraseSetup;
+use crate::components::passphrase::PassphraseUnlock;
+use crate::components::recovery::Recover;
+use crate::components::recovery::RecoverySetup;
+use crate::components::sync_button::SyncButton;
+use crate::config;
+use crate::crypto::MasterKey;
+use crate::crypto::WebCrypto;
 use crate::crypto::WrappedKeypair;
+use crate::data::entry::Entry;
+use crate::data::vault::NameIndex;
 use crate::data::vault::VaultConfig;
 use crate::data::CredentialId;
-use crate::error::JsOrSerdeError;
+use crate::error::Error;
+use crate::hooks::session::use_session;
+use crate::sync;
 
 #[derive(PartialEq, Properties)]
 pub struct Props {
     pub config: Rc<VaultConfig>,
-    pub set_config: Callback<Rc<VaultConfig>, Result<(), JsOrSerdeError>>,
+    pub set_config: Callback<Rc<VaultConfig>, Result<(), Error>>,
+
+    /// An imported vault config, or the copy on the sync server, waiting to be
+    /// merged into this one.
+    pub imported: Option<Rc<VaultConfig>>,
+    pub on_import: Callback<Rc<VaultConfig>>,
+    pub on_import_done: Callback<()>,
+
+    /// The largest file, in bytes, that can be added as an entry.
+    pub max_file_size: usize,
 }
 
 #[styled_component]
 pub fn Vault(props: &Props) -> Html {
+    let session = use_session(config::session::idle_timeout());
+
+    let names = use_state(|| None::<Rc<NameIndex>>);
+    {
+        let names = names.clone();
+        let session = session.clone();
+        let deps = (Rc::clone(&props.config), session.is_unlocked());
+        use_effect_with_deps(
+            move |(conf, _)| {
+                let conf = Rc::clone(conf);
+                match session.get() {
+                    Some(session) if conf.has_encrypted_names() => spawn_local(async move {
+                        match conf.decrypt_names(&WebCrypto, &session.master_key).await {
+                            Ok(index) => names.set(Some(Rc::new(index))),
+                            Err(err) => {
+                                console::log_2(
+                                    &"Failed to decrypt entry names:".into(),
+                                    &err.to_string().into(),
+                                );
+                                names.set(None);
+                            }
+                        }
+                    }),
+                    _ => names.set(None),
+                }
+            },
+            deps,
+        );
+    }
+
+    // A session keeps the master key it was unlocked with, so it has to be
+    // unlocked again once the master key is replaced
+    {
+        let session = session.clone();
+        use_effect_with_deps(
+            move |_| {
+                if session.is_unlocked() {
+                    session.lock();
+                }
+            },
+            props.config.revisions.master_key,
+        );
+    }
+
+    let on_unlock = {
+        let session = session.clone();
+        let conf: Rc<VaultConfig> = Rc::clone(&props.config);
+
+        Callback::from(move |_| {
+            let session = session.clone();
+            let conf = Rc::clone(&conf);
+            spawn_local(async move {
+                match session.unlock(&conf).await {
+                    Ok(_) => {
+                        console::log_1(&"Vault unlocked.".into());
+                    }
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Failed to unlock vault:".into(), &e);
+                    }
+                    Err(err) => {
+                        console::log_2(&"Failed to unlock vault:".into(), &err.to_string().into());
+                    }
+                }
+            })
+        })
+    };
+
+    let on_lock = {
+        let session = session.clone();
+        Callback::from(move |_| session.lock())
+    };
+
     let on_create = {
         let set_config = props.set_config.clone();
         let conf: Rc<VaultConfig> = Rc::clone(&props.config);
+        let session = session.clone();
 
         Callback::from(move |wrapped_keypair: WrappedKeypair| {
-            let mut conf = Rc::clone(&conf);
-            Rc::make_mut(&mut Rc::make_mut(&mut Rc::make_mut(&mut conf).user).keypairs)
-                .push(Rc::new(wrapped_keypair));
-            match set_config.emit(conf) {
-                Ok(()) => {
-                    console::log_1(&"Successfully registered new credential!".into());
-                }
-                Err(JsOrSerdeError::JsError(e)) => {
-                    console::log_2(&"Failed to register new credential:".into(), &e);
-                }
-                Err(JsOrSerdeError::SerializeError(_)) => {
-                    console::log_1(
-                        &"Failed to register new credential: JSON serialization failed.".into(),
-                    );
+            let set_config = set_config.clone();
+            let conf = Rc::clone(&conf);
+            let session = session.clone();
+            spawn_local(async move {
+                let mut conf = Rc::clone(&conf);
+                let result = if conf.master_key.is_some() {
+                    match session.unlock(&conf).await {
+                        Ok(session) => Rc::make_mut(&mut conf)
+                            .add_credential(&WebCrypto, wrapped_keypair, &session.master_key)
+                            .await
+                            .map(|_| ()),
+                        Err(err) => Err(err),
+                    }
+                } else {
+                    // First credential, or a format version 2 vault: no ceremony is
+                    // needed to wrap a fresh master key to every credential.
+                    let conf = Rc::make_mut(&mut conf);
+                    match conf.push_keypair(wrapped_keypair) {
+                        Ok(conf) => conf.create_master_key(&WebCrypto).await.map(|_| ()),
+                        Err(err) => Err(err),
+                    }
+                };
+
+                match result.and_then(|_| set_config.emit(conf)) {
+                    Ok(()) => {
+                        console::log_1(&"Successfully registered new credential!".into());
+                    }
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Failed to register new credential:".into(), &e);
+                    }
+                    Err(err) => {
+                        console::log_2(
+                            &"Failed to register new credential:".into(),
+                            &err.to_string().into(),
+                        );
+                    }
                 }
-            }
+            })
         })
     };
 
+    let create_error = use_state(|| None::<String>);
+
+    let on_create_begin = {
+        let create_error = create_error.clone();
+        Callback::from(move |()| create_error.set(None))
+    };
+
+    let on_create_fail = {
+        let create_error = create_error.clone();
+        Callback::from(move |msg: String| create_error.set(Some(msg)))
+    };
+
     let on_delete_credential = {
         let set_config = props.set_config.clone();
         let conf: Rc<VaultConfig> = Rc::clone(&props.config);
 
         Callback::from(move |cred_id: CredentialId| {
             let mut conf = Rc::clone(&conf);
-            Rc::make_mut(&mut conf).delete_credential(&cred_id);
-            match set_config.emit(conf) {
+            let result = Rc::make_mut(&mut conf)
+                .delete_credential(&cred_id)
+                .map(|_| ());
+            match result.and_then(|()| set_config.emit(conf)) {
                 Ok(()) => {
                     console::log_1(&"Successfully deleted credential!".into());
                 }
-                Err(JsOrSerdeError::JsError(e)) => {
+                Err(Error::JsError(e)) => {
                     console::log_2(&"Failed to delete credential:".into(), &e);
                 }
-                Err(JsOrSerdeError::SerializeError(_)) => {
-                    console::log_1(
-                        &"Failed to delete credential: JSON serialization failed.".into(),
+                Err(err) => {
+                    console::log_2(
+                        &"Failed to delete credential:".into(),
+                        &err.to_string().into(),
                     );
                 }
             }
         })
     };
 
+    let revoke_result = use_state(|| None::<String>);
+
+    let on_revoke_credential = {
+        let set_config = props.set_config.clone();
+        let conf: Rc<VaultConfig> = Rc::clone(&props.config);
+        let session = session.clone();
+        let names = (*names).clone();
+        let revoke_result = revoke_result.clone();
+
+        Callback::from(move |cred_id: CredentialId| {
+            let set_config = set_config.clone();
+            let conf = Rc::clone(&conf);
+            let session = session.clone();
+            let names = names.clone();
+            let revoke_result = revoke_result.clone();
+            spawn_local(async move {
+                let mut conf = Rc::clone(&conf);
+                let result = match session.unlock(&conf).await {
+                    Ok(session) => {
+                        Rc::make_mut(&mut conf)
+                            .revoke_credential(
+                                &WebCrypto,
+                                &cred_id,
+                                &session.unlocked,
+                                &session.master_key,
+                            )
+                            .await
+                    }
+                    Err(err) => Err(err),
+                };
+                match result.and_then(|(_, report)| set_config.emit(conf).map(|()| report)) {
+                    Ok(report) => {
+                        let name = |key: &String| {
+                            names
+                                .as_ref()
+                                .and_then(|names| names.name(key))
+                                .unwrap_or(key)
+                                .to_string()
+                        };
+                        let mut msg = format!(
+                            "Revoked the key and rotated {} entries: {}",
+                            report.rotated.len(),
+                            report
+                                .rotated
+                                .iter()
+                                .map(name)
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        );
+                        for (key, err) in report.failed {
+                            console::log_2(
+                                &format!("Failed to rotate {key}:").into(),
+                                &err.to_string().into(),
+                            );
+                            msg.push_str(&format!(". Not rotated: {} ({err})", name(&key)));
+                        }
+                        revoke_result.set(Some(msg));
+                    }
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Failed to revoke credential:".into(), &e);
+                        revoke_result.set(Some("Failed to revoke the key".to_string()));
+                    }
+                    Err(err) => {
+                        console::log_2(
+                            &"Failed to revoke credential:".into(),
+                            &err.to_string().into(),
+                        );
+                        revoke_result.set(Some(format!("Failed to revoke the key: {err}")));
+                    }
+                }
+            })
+        })
+    };
+
     let on_rename_credential = {
         let set_config = props.set_config.clone();
         let conf: Rc<VaultConfig> = Rc::clone(&props.config);
@@ -88,12 +300,13 @@ pub fn Vault(props: &Props) -> Html {
                 Ok(()) => {
                     console::log_1(&"Successfully renamed credential!".into());
                 }
-                Err(JsOrSerdeError::JsError(e)) => {
+                Err(Error::JsError(e)) => {
                     console::log_2(&"Failed to rename credential:".into(), &e);
                 }
-                Err(JsOrSerdeError::SerializeError(_)) => {
-                    console::log_1(
-                        &"Failed to rename credential: JSON serialization failed.".into(),
+                Err(err) => {
+                    console::log_2(
+                        &"Failed to rename credential:".into(),
+                        &err.to_string().into(),
                     );
                 }
             }
@@ -103,28 +316,247 @@ pub fn Vault(props: &Props) -> Html {
     let on_insert = {
         let set_config = props.set_config.clone();
         let conf: Rc<VaultConfig> = Rc::clone(&props.config);
+        let session = session.clone();
 
-        Callback::from(move |(name, content): (String, Vec<u8>)| {
+        Callback::from(
+            move |(name, entry, required_keys): (String, Entry, Option<RequiredKeys>)| {
+                let set_config = set_config.clone();
+                let conf = Rc::clone(&conf);
+                let session = session.clone();
+                spawn_local(async move {
+                    let mut conf = Rc::clone(&conf);
+                    let content = match entry.to_bytes() {
+                        Ok(content) => content,
+                        Err(err) => {
+                            console::log_2(
+                                &"Failed to encode entry:".into(),
+                                &err.to_string().into(),
+                            );
+                            return;
+                        }
+                    };
+                    let unlocked_session;
+                    let new_master_key;
+                    let master_key: Result<&MasterKey, Error> = if conf.master_key.is_some() {
+                        match session.unlock(&conf).await {
+                            Ok(session) => {
+                                unlocked_session = session;
+                                Ok(&unlocked_session.master_key)
+                            }
+                            Err(err) => Err(err),
+                        }
+                    } else {
+                        match Rc::make_mut(&mut conf).create_master_key(&WebCrypto).await {
+                            Ok(master_key) => {
+                                new_master_key = master_key;
+                                Ok(&new_master_key)
+                            }
+                            Err(err) => Err(err),
+                        }
+                    };
+                    let result = match master_key {
+                        Ok(master_key) => match required_keys {
+                            Some(required_keys) => {
+                                Rc::make_mut(&mut conf)
+                                    .push_threshold_content(
+                                        &WebCrypto,
+                                        name,
+                                        content,
+                                        required_keys.threshold,
+                                        &required_keys.credential_ids,
+                                        master_key,
+                                    )
+                                    .await
+                            }
+                            None => {
+                                Rc::make_mut(&mut conf)
+                                    .push_content(&WebCrypto, name, content, master_key)
+                                    .await
+                            }
+                        },
+                        Err(err) => Err(err),
+                    };
+                    match result
+                        .map(|_|
+                                 // Throw away the returned reference to conf, but preserve the error if any
+                                 ())
+                        .and_then(|_| match config::sync::server_url() {
+                            Some(_) => sync::check_size(&conf),
+                            None => Ok(()),
+                        })
+                        .and_then(|_| set_config.emit(conf))
+                    {
+                        Ok(()) => {
+                            console::log_1(&"Successfully encrypted content!".into());
+                        }
+                        Err(Error::JsError(e)) => {
+                            console::log_2(&"Failed to encrypt content:".into(), &e);
+                        }
+                        Err(err) => {
+                            console::log_2(
+                                &"Failed to encrypt content:".into(),
+                                &err.to_string().into(),
+                            );
+                        }
+                    }
+                })
+            },
+        )
+    };
+
+    let on_upgrade = {
+        let set_config = props.set_config.clone();
+        let conf: Rc<VaultConfig> = Rc::clone(&props.config);
+        let session = session.clone();
+
+        Callback::from(move |()| {
             let set_config = set_config.clone();
             let conf = Rc::clone(&conf);
+            let session = session.clone();
             spawn_local(async move {
                 let mut conf = Rc::clone(&conf);
-                let result = Rc::make_mut(&mut conf).push_content(name, content).await;
-                match result
-                    .map(|_|
-                                 // Throw away the returned reference to conf, but preserve the error if any
-                                 ())
-                    .and_then(|_| set_config.emit(conf))
-                {
+                let result = match session.unlock(&conf).await {
+                    Ok(session) => {
+                        Rc::make_mut(&mut conf)
+                            .upgrade_content(&WebCrypto, &session.unlocked, &session.master_key)
+                            .await
+                    }
+                    Err(err) => Err(err),
+                };
+                match result.and_then(|upgraded| set_config.emit(conf).map(|_| upgraded)) {
+                    Ok(upgraded) => {
+                        console::log_2(&"Upgraded entries:".into(), &upgraded.join(", ").into());
+                    }
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Failed to upgrade entries:".into(), &e);
+                    }
+                    Err(err) => {
+                        console::log_2(
+                            &"Failed to upgrade entries:".into(),
+                            &err.to_string().into(),
+                        );
+                    }
+                }
+            })
+        })
+    };
+
+    let reencrypt_status = use_state(|| None::<ReencryptStatus>);
+
+    let on_reencrypt_all = {
+        let set_config = props.set_config.clone();
+        let conf: Rc<VaultConfig> = Rc::clone(&props.config);
+        let session = session.clone();
+        let reencrypt_status = reencrypt_status.clone();
+
+        Callback::from(move |()| {
+            let set_config = set_config.clone();
+            let conf = Rc::clone(&conf);
+            let session = session.clone();
+            let reencrypt_status = reencrypt_status.clone();
+            spawn_local(async move {
+                let mut conf = Rc::clone(&conf);
+                let mut status = ReencryptStatus::default();
+                reencrypt_status.set(Some(status.clone()));
+
+                let master_key_created = if conf.master_key.is_none() {
+                    Rc::make_mut(&mut conf)
+                        .create_master_key(&WebCrypto)
+                        .await
+                        .map(|_| ())
+                } else {
+                    Ok(())
+                };
+                let result = match master_key_created {
+                    Ok(()) => match session.unlock(&conf).await {
+                        Ok(session) => match confirm_enrollment(&conf) {
+                            Ok(enroll) => {
+                                Rc::make_mut(&mut conf)
+                                    .reencrypt_all(
+                                        &WebCrypto,
+                                        &session.unlocked,
+                                        &session.master_key,
+                                        &enroll,
+                                        |progress| {
+                                            status.done = progress.done;
+                                            status.total = progress.total;
+                                            if let Err(err) = progress.result {
+                                                status.failed.push((
+                                                    progress.name.to_string(),
+                                                    err.to_string(),
+                                                ));
+                                            }
+                                            reencrypt_status.set(Some(status.clone()));
+                                        },
+                                    )
+                                    .await
+                            }
+                            Err(err) => Err(err),
+                        },
+                        Err(err) => Err(err),
+                    },
+                    Err(err) => Err(err),
+                };
+
+                status.finished = true;
+                reencrypt_status.set(Some(status));
+
+                match result.and_then(|report| set_config.emit(conf).map(|_| report)) {
+                    Ok(report) => {
+                        console::log_2(
+                            &"Re-encrypted entries:".into(),
+                            &report.reencrypted.join(", ").into(),
+                        );
+                        for (name, err) in report.failed {
+                            console::log_2(
+                                &format!("Failed to re-encrypt {name}:").into(),
+                                &err.to_string().into(),
+                            );
+                        }
+                    }
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Failed to re-encrypt entries:".into(), &e);
+                    }
+                    Err(err) => {
+                        console::log_2(
+                            &"Failed to re-encrypt entries:".into(),
+                            &err.to_string().into(),
+                        );
+                    }
+                }
+            })
+        })
+    };
+
+    let on_encrypt_names = {
+        let set_config = props.set_config.clone();
+        let conf: Rc<VaultConfig> = Rc::clone(&props.config);
+        let session = session.clone();
+
+        Callback::from(move |()| {
+            let set_config = set_config.clone();
+            let conf = Rc::clone(&conf);
+            let session = session.clone();
+            spawn_local(async move {
+                let mut conf = Rc::clone(&conf);
+                let result = match session.unlock(&conf).await {
+                    Ok(session) => Rc::make_mut(&mut conf)
+                        .encrypt_names(&WebCrypto, &session.unlocked, &session.master_key)
+                        .await
+                        .map(|_| ()),
+                    Err(err) => Err(err),
+                };
+                match result.and_then(|_| set_config.emit(conf)) {
                     Ok(()) => {
-                        console::log_1(&"Successfully encrypted content!".into());
+                        console::log_1(&"Successfully encrypted entry names!".into());
                     }
-                    Err(JsOrSerdeError::JsError(e)) => {
-                        console::log_2(&"Failed to encrypt content:".into(), &e);
+                    Err(Error::JsError(e)) => {
+                        console::log_2(&"Failed to encrypt entry names:".into(), &e);
                     }
-                    Err(JsOrSerdeError::SerializeError(_)) => {
-                        console::log_1(
-                            &"Failed to encrypt content: JSON serialization failed.".into(),
+                    Err(err) => {
+                        console::log_2(
+                            &"Failed to encrypt entry names:".into(),
+                            &err.to_string().into(),
                         );
                     }
                 }
@@ -139,17 +571,20 @@ pub fn Vault(props: &Props) -> Html {
         Callback::from(move |name: String| {
             let set_config = set_config.clone();
             let mut conf = Rc::clone(&conf);
-            Rc::make_mut(&mut conf).contents.remove(&name);
+            let result = Rc::make_mut(&mut conf).delete_content(&name).map(|_| ());
 
-            match set_config.emit(conf) {
+            match result.and_then(|()| set_config.emit(conf)) {
                 Ok(()) => {
                     console::log_1(&"Successfully encrypted content!".into());
                 }
-                Err(JsOrSerdeError::JsError(e)) => {
+                Err(Error::JsError(e)) => {
                     console::log_2(&"Failed to encrypt content:".into(), &e);
                 }
-                Err(JsOrSerdeError::SerializeError(_)) => {
-                    console::log_1(&"Failed to encrypt content: JSON serialization failed.".into());
+                Err(err) => {
+                    console::log_2(
+                        &"Failed to encrypt content:".into(),
+                        &err.to_string().into(),
+                    );
                 }
             }
         })
@@ -157,33 +592,189 @@ pub fn Vault(props: &Props) -> Html {
 
     html! {
         <>
+            {
+                if session.is_unlocked() {
+                    html! {
+                        <p>
+                            { "Vault is unlocked. " }
+                            <button onclick={on_lock}>{ "Lock" }</button>
+                        </p>
+                    }
+                } else if props.config.master_key.is_some() {
+                    html! {
+                        <>
+                            <p>
+                                { "Vault is locked. " }
+                                <button onclick={on_unlock}>{ "Unlock" }</button>
+                            </p>
+                            {
+                                if props.config.passphrase_key.is_some() {
+                                    html! {
+                                        <PassphraseUnlock
+                                            config={Rc::clone(&props.config)}
+                                            session={session.clone()}
+                                        />
+                                    }
+                                } else {
+                                    html! { <></> }
+                                }
+                            }
+                            {
+                                if props.config.recovery_key.is_some() {
+                                    html! {
+                                        <Recover
+                                            config={Rc::clone(&props.config)}
+                                            session={session.clone()}
+                                        />
+                                    }
+                                } else {
+                                    html! { <></> }
+                                }
+                            }
+                        </>
+                    }
+                } else {
+                    html! { <></> }
+                }
+            }
+            {
+                if let Some(server_url) = config::sync::server_url() {
+                    html! {
+                        <SyncButton
+                            {server_url}
+                            config={Rc::clone(&props.config)}
+                            session={session.clone()}
+                            set_config={props.set_config.clone()}
+                            on_review={props.on_import.clone()}
+                        />
+                    }
+                } else {
+                    html! { <></> }
+                }
+            }
+            {
+                if let Some(imported) = &props.imported {
+                    html! {
+                        <MergeImport
+                            config={Rc::clone(&props.config)}
+                            imported={Rc::clone(imported)}
+                            session={session.clone()}
+                            set_config={props.set_config.clone()}
+                            on_done={props.on_import_done.clone()}
+                        />
+                    }
+                } else {
+                    html! { <></> }
+                }
+            }
             <div>
                 <h2>{ "Keys" }</h2>
                 <CredentialsList
                     keypairs={Rc::clone(&props.config.user.keypairs)}
                     on_delete={on_delete_credential}
+                    on_revoke={on_revoke_credential}
                     on_rename={on_rename_credential}
                 />
                 <CreateButton
                     config={Rc::clone(&props.config)}
                     {on_create}
-                    on_begin={|_| {}}
-                    on_fail={|_| {}}
+                    on_begin={on_create_begin}
+                    on_fail={on_create_fail}
                 />
+                {
+                    if let Some(msg) = &*create_error {
+                        html! { <p>{ msg }</p> }
+                    } else {
+                        html! { <></> }
+                    }
+                }
+                {
+                    if let Some(msg) = &*revoke_result {
+                        html! { <p>{ msg }</p> }
+                    } else {
+                        html! { <></> }
+                    }
+                }
+                {
+                    if props.config.master_key.is_some() {
+                        html! {
+                            <>
+                                <RecoverySetup
+                                    config={Rc::clone(&props.config)}
+                                    session={session.clone()}
+                                    set_config={props.set_config.clone()}
+                                />
+                                <PassphraseSetup
+                                    config={Rc::clone(&props.config)}
+                                    session={session.clone()}
+                                    set_config={props.set_config.clone()}
+                                />
+                            </>
+                        }
+                    } else {
+                        html! { <></> }
+                    }
+                }
             </div>
             <div>
                 <FilesList
                     config={Rc::clone(&props.config)}
-                    on_reencrypt={on_insert.clone()}
+                    session={session.clone()}
+                    names={(*names).clone()}
+                    reencrypt_status={(*reencrypt_status).clone()}
+                    {on_upgrade}
+                    {on_reencrypt_all}
+                    {on_encrypt_names}
                     on_delete={on_delete_content.clone()}
                 />
+                <PaddingSelect
+                    config={Rc::clone(&props.config)}
+                    set_config={props.set_config.clone()}
+                />
             </div>
             <div>
                 <InsertContent
                     config={Rc::clone(&props.config)}
+                    names={(*names).clone()}
+                    max_file_size={props.max_file_size}
                     on_submit={on_insert}
                 />
             </div>
+            <Contacts
+                config={Rc::clone(&props.config)}
+                session={session.clone()}
+                names={(*names).clone()}
+                set_config={props.set_config.clone()}
+            />
         </>
     }
 }
+
+/// The registered credentials without access to the vault that the user
+/// agrees to give access to. A synced config may list keypairs added by
+/// someone else, so they are not enrolled without asking.
+fn confirm_enrollment(config: &VaultConfig) -> Result<Vec<CredentialId>, Error> {
+    let unenrolled = config.unenrolled_credentials()?;
+    if unenrolled.is_empty() {
+        return Ok(unenrolled);
+    }
+    let names: Vec<String> = unenrolled
+        .iter()
+        .map(|cred_id| {
+            config
+                .get_credential_nickname(cred_id)
+                .map(|nickname| nickname.to_string())
+                .unwrap_or_else(|| cred_id.b64url())
+        })
+        .collect();
+    let message = format!(
+        "These keys cannot unlock the vault yet: {}. Only give them access if you registered \
+         them yourself. Give them access?",
+        names.join(", ")
+    );
+    let confirmed = web_sys::window()
+        .map(|window| window.confirm_with_message(&message))
+        .transpose()?
+        .unwrap_or(false);
+    Ok(if confirmed { unenrolled } else { Vec::new() })
+}
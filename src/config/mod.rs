@@ -1 +1,4 @@
+pub mod session;
+pub mod storage;
+pub mod sync;
 pub mod webauthn;
@@ -0,0 +1,14 @@
+use std::time::Duration;
+
+const DEFAULT_IDLE_TIMEOUT_SECONDS: u64 = 300;
+
+/// How long an unlocked session may go unused before it is locked again.
+/// Set the `SESSION_IDLE_TIMEOUT` environment variable at build time to
+/// override, in seconds.
+pub fn idle_timeout() -> Duration {
+    Duration::from_secs(
+        option_env!("SESSION_IDLE_TIMEOUT")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_IDLE_TIMEOUT_SECONDS),
+    )
+}
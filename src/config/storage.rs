@@ -0,0 +1,10 @@
+use crate::storage::BackendKind;
+
+/// Where to keep the vault. Set the `STORAGE_BACKEND` environment variable at
+/// build time to `indexeddb` (the default), `localstorage` or `memory` to
+/// override.
+pub fn backend() -> BackendKind {
+    option_env!("STORAGE_BACKEND")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(BackendKind::IndexedDb)
+}
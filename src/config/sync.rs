@@ -0,0 +1,5 @@
+/// The URL of the sync server to sync the vault with, if any. Set the
+/// `SYNC_SERVER_URL` environment variable at build time to enable syncing.
+pub fn server_url() -> Option<&'static str> {
+    option_env!("SYNC_SERVER_URL").filter(|url| !url.is_empty())
+}
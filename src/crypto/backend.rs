@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+
+use crate::error::Error;
+
+/// The cryptographic primitives the vault format is built from.
+///
+/// Key handles are associated types so that the WebCrypto implementation can
+/// keep them as opaque, non-extractable `CryptoKey`s while the pure-Rust
+/// implementation holds the key material directly. Content keys cross the
+/// trait boundary as raw bytes, since they are the values being wrapped.
+#[async_trait(?Send)]
+pub trait CryptoBackend {
+    /// An AES-256-GCM key.
+    type AesGcmKey;
+
+    /// An AES-256-KW key encryption key.
+    type AesKwKey;
+
+    /// A P-256 ECDH private key.
+    type EcdhPrivateKey;
+
+    fn random_bytes(&self, dest: &mut [u8]) -> Result<(), Error>;
+
+    fn random<const N: usize>(&self) -> Result<[u8; N], Error> {
+        let mut result: [u8; N] = [0; N];
+        self.random_bytes(&mut result)?;
+        Ok(result)
+    }
+
+    async fn import_aes_gcm_key(&self, raw: &[u8]) -> Result<Self::AesGcmKey, Error>;
+
+    /// Derive an AES-256-GCM key from `ikm` using HKDF-SHA256.
+    async fn hkdf_aes_gcm_key(
+        &self,
+        ikm: &[u8],
+        salt: &[u8],
+        info: &[u8],
+    ) -> Result<Self::AesGcmKey, Error>;
+
+    /// Encrypt `plaintext`, returning the ciphertext with the 128-bit tag appended.
+    async fn aes_gcm_encrypt(
+        &self,
+        key: &Self::AesGcmKey,
+        iv: &[u8],
+        additional_data: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, Error>;
+
+    async fn aes_gcm_decrypt(
+        &self,
+        key: &Self::AesGcmKey,
+        iv: &[u8],
+        additional_data: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Error>;
+
+    /// Wrap the raw key `key` using RFC 3394 AES key wrap.
+    async fn aes_kw_wrap(&self, kek: &Self::AesKwKey, key: &[u8]) -> Result<Vec<u8>, Error>;
+
+    async fn aes_kw_unwrap(
+        &self,
+        kek: &Self::AesKwKey,
+        wrapped_key: &[u8],
+    ) -> Result<Vec<u8>, Error>;
+
+    /// Generate a P-256 keypair, returning the private key and the raw
+    /// (uncompressed SEC1) public key. Only private keys that are wrapped with
+    /// [Self::wrap_ecdh_private_key] need to be `extractable`.
+    async fn ecdh_generate_keypair(
+        &self,
+        extractable: bool,
+    ) -> Result<(Self::EcdhPrivateKey, Vec<u8>), Error>;
+
+    /// Import a P-256 private key from its raw scalar, returning it and the
+    /// raw public key as [Self::ecdh_generate_keypair] does.
+    async fn ecdh_import_private_key(
+        &self,
+        scalar: &[u8],
+    ) -> Result<(Self::EcdhPrivateKey, Vec<u8>), Error>;
+
+    /// Derive an AES-256-KW key from the ECDH shared secret, used directly as
+    /// the key bytes like WebCrypto's `deriveKey` does.
+    async fn ecdh_derive_aes_kw_key(
+        &self,
+        private_key: &Self::EcdhPrivateKey,
+        peer_public_key: &[u8],
+    ) -> Result<Self::AesKwKey, Error>;
+
+    /// Encrypt `private_key` in JWK format with AES-GCM.
+    async fn wrap_ecdh_private_key(
+        &self,
+        wrapping_key: &Self::AesGcmKey,
+        iv: &[u8],
+        additional_data: &[u8],
+        private_key: &Self::EcdhPrivateKey,
+    ) -> Result<Vec<u8>, Error>;
+
+    async fn unwrap_ecdh_private_key(
+        &self,
+        wrapping_key: &Self::AesGcmKey,
+        iv: &[u8],
+        additional_data: &[u8],
+        wrapped_private_key: &[u8],
+    ) -> Result<Self::EcdhPrivateKey, Error>;
+}
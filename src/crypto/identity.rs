@@ -0,0 +1,290 @@
+//! Public identities for sharing entries with other users. An identity lists
+//! the public keys of a user's credentials, and is signed with an ECDSA P-256
+//! key derived from the [IdentitySecret] of their vault. The signature proves
+//! that later versions of an identity come from the same vault as the first
+//! one that was imported; whether that first one belongs to the right person
+//! is checked by comparing [PublicIdentity::fingerprint]s some other way.
+
+use ::base64::Engine;
+use hkdf::Hkdf;
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::Signature;
+use p256::ecdsa::SigningKey;
+use p256::ecdsa::VerifyingKey;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::data::vault::UserConfig;
+use crate::data::UserHandle;
+use crate::error::Error;
+
+use super::decrypt_with_master_key;
+use super::encrypt_content_key_to_pubkey;
+use super::encrypt_with_key;
+use super::encrypt_with_master_key;
+use super::ContentKey;
+use super::CryptoBackend;
+use super::EncryptedContent;
+use super::EncryptedContentAdditionalData;
+use super::MasterKey;
+use super::AES_KEY_LENGTH;
+
+const HKDF_INFO: &[u8] = b"passwordless-passwords-prf identity signing key";
+const SYNC_TOKEN_HKDF_INFO: &[u8] = b"passwordless-passwords-prf sync write token";
+
+/// Bytes of the SHA-256 hash of the signing key shown as the fingerprint.
+const FINGERPRINT_LENGTH: usize = 16;
+
+/// The secret a vault's signing key is derived from. It is generated along
+/// with the master key and stored encrypted under it, and kept when the
+/// master key is replaced, so that contacts still recognize the signing key.
+/// Overwritten with zeroes when dropped.
+pub struct IdentitySecret(Vec<u8>);
+
+impl Drop for IdentitySecret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl IdentitySecret {
+    pub fn generate<B: CryptoBackend>(backend: &B) -> Result<Self, Error> {
+        Ok(Self(backend.random::<AES_KEY_LENGTH>()?.to_vec()))
+    }
+
+    /// The secret of a vault from before identity secrets were stored, whose
+    /// signing key was derived from the master key itself.
+    pub fn from_master_key(master_key: &MasterKey) -> Self {
+        Self(master_key.0.clone())
+    }
+}
+
+/// The public key of one of a user's credentials.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct IdentityKey {
+    #[serde(with = "crate::data::base64")]
+    pub credential_id: Vec<u8>,
+    #[serde(with = "crate::data::base64")]
+    pubkey: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PublicIdentity {
+    /// Binds entries shared with this user to their vault. It identifies the
+    /// vault to the sync server too, but writing there takes the vault's
+    /// [sync_token], which is not part of the identity.
+    pub user_handle: UserHandle,
+    pub username: String,
+    #[serde(with = "crate::data::base64")]
+    signing_key: Vec<u8>,
+    pub keys: Vec<IdentityKey>,
+}
+
+impl PublicIdentity {
+    /// Identifies the signing key, for users to compare out of band.
+    pub fn fingerprint(&self) -> String {
+        Sha256::digest(&self.signing_key)[..FINGERPRINT_LENGTH]
+            .chunks(2)
+            .map(|pair| format!("{:02X}{:02X}", pair[0], pair[1]))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Identifies the signing key, and so the vault, in share packages.
+    pub fn signer_id(&self) -> String {
+        signer_id_of(&self.signing_key)
+    }
+
+    /// Whether `other` is signed with the same key as this identity.
+    pub fn same_signer(&self, other: &PublicIdentity) -> bool {
+        self.signing_key == other.signing_key
+    }
+
+    /// Check that `payload` was signed with this identity's signing key.
+    pub fn verify(&self, payload: &[u8], signature: &[u8]) -> Result<(), Error> {
+        let invalid = || Error::MalformedRecord("Invalid signature".to_string());
+        VerifyingKey::from_sec1_bytes(&self.signing_key)
+            .map_err(|_| invalid())?
+            .verify(
+                payload,
+                &Signature::from_slice(signature).map_err(|_| invalid())?,
+            )
+            .map_err(|_| invalid())
+    }
+}
+
+/// A [PublicIdentity] in the canonical JSON form it was signed in.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SignedIdentity {
+    #[serde(with = "crate::data::base64")]
+    identity: Vec<u8>,
+    #[serde(with = "crate::data::base64")]
+    signature: Vec<u8>,
+}
+
+impl SignedIdentity {
+    /// Sign the identity of `user`, listing the public keys of its
+    /// credentials.
+    pub fn create(secret: &IdentitySecret, user: &UserConfig) -> Result<Self, Error> {
+        let signing_key = derive_signing_key(secret)?;
+        let identity = PublicIdentity {
+            user_handle: user.user_handle.clone(),
+            username: user.username.clone(),
+            signing_key: signing_key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes()
+                .to_vec(),
+            keys: user
+                .keypairs
+                .iter()
+                .map(|wkp| {
+                    let ad = wkp.additional_data()?;
+                    Ok(IdentityKey {
+                        credential_id: ad.credential_id,
+                        pubkey: ad.pubkey,
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+        };
+        let identity = serde_json::to_vec(&identity)?;
+        let signature = sign(secret, &identity)?;
+        Ok(Self {
+            identity,
+            signature,
+        })
+    }
+
+    /// The identity without checking the signature, which is done once when
+    /// it is imported.
+    pub fn identity(&self) -> Result<PublicIdentity, Error> {
+        Ok(serde_json::from_slice(&self.identity)?)
+    }
+
+    /// The identity, if it is signed with its own signing key.
+    pub fn verify(&self) -> Result<PublicIdentity, Error> {
+        let identity = self.identity()?;
+        identity.verify(&self.identity, &self.signature)?;
+        Ok(identity)
+    }
+}
+
+/// The signing key of the vault with the identity secret `secret`: the first
+/// output of HKDF-SHA256 that is a valid scalar, as for recovery keys.
+fn derive_signing_key(secret: &IdentitySecret) -> Result<SigningKey, Error> {
+    let hkdf = Hkdf::<Sha256>::new(None, &secret.0);
+    for counter in 0..=u8::MAX {
+        let mut scalar = [0; AES_KEY_LENGTH];
+        hkdf.expand_multi_info(&[HKDF_INFO, &[counter]], &mut scalar)
+            .map_err(|err| Error::CryptoError(err.to_string()))?;
+        let signing_key = SigningKey::from_slice(&scalar);
+        scalar.zeroize();
+        if let Ok(signing_key) = signing_key {
+            return Ok(signing_key);
+        }
+    }
+    Err(Error::CryptoError(
+        "Failed to derive a signing key".to_string(),
+    ))
+}
+
+fn signer_id_of(signing_key: &[u8]) -> String {
+    ::base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(signing_key))
+}
+
+/// The [PublicIdentity::signer_id] of the vault with the identity secret
+/// `secret`.
+pub fn signer_id(secret: &IdentitySecret) -> Result<String, Error> {
+    Ok(signer_id_of(
+        derive_signing_key(secret)?
+            .verifying_key()
+            .to_encoded_point(false)
+            .as_bytes(),
+    ))
+}
+
+/// Sign `payload` with the signing key of the vault with the identity secret
+/// `secret`.
+pub fn sign(secret: &IdentitySecret, payload: &[u8]) -> Result<Vec<u8>, Error> {
+    let signature: Signature = derive_signing_key(secret)?.sign(payload);
+    Ok(signature.to_vec())
+}
+
+/// The token that authorizes writing the vault with the identity secret
+/// `secret` to the sync server. Like the signing key, it outlives master key
+/// replacements, but unlike the user handle it is only known to those who can
+/// unlock the vault.
+pub fn sync_token(secret: &IdentitySecret) -> Result<String, Error> {
+    let mut token = [0; AES_KEY_LENGTH];
+    Hkdf::<Sha256>::new(None, &secret.0)
+        .expand(SYNC_TOKEN_HKDF_INFO, &mut token)
+        .map_err(|err| Error::CryptoError(err.to_string()))?;
+    let encoded = ::base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(token);
+    token.zeroize();
+    Ok(encoded)
+}
+
+/// Encrypt `secret` under `master_key`, for storing in the vault.
+pub async fn encrypt_identity_secret<B: CryptoBackend>(
+    backend: &B,
+    secret: &IdentitySecret,
+    additional_data: &EncryptedContentAdditionalData,
+    master_key: &MasterKey,
+) -> Result<EncryptedContent, Error> {
+    encrypt_with_master_key(backend, &secret.0, additional_data, master_key).await
+}
+
+pub async fn decrypt_identity_secret<B: CryptoBackend>(
+    backend: &B,
+    encrypted: &EncryptedContent,
+    additional_data: &EncryptedContentAdditionalData,
+    master_key: &MasterKey,
+) -> Result<IdentitySecret, Error> {
+    let secret = IdentitySecret(
+        decrypt_with_master_key(backend, encrypted, additional_data, master_key).await?,
+    );
+    if secret.0.len() != AES_KEY_LENGTH {
+        return Err(Error::MalformedRecord(
+            "Identity secret has the wrong length".to_string(),
+        ));
+    }
+    Ok(secret)
+}
+
+/// Encrypt `data` with a content key wrapped to each of the keys of
+/// `identity`, like [super::encrypt_with] does for one's own credentials.
+pub async fn encrypt_to_identity<B: CryptoBackend>(
+    backend: &B,
+    data: &[u8],
+    additional_data: &EncryptedContentAdditionalData,
+    identity: &PublicIdentity,
+) -> Result<EncryptedContent, Error> {
+    if identity.keys.is_empty() {
+        return Err(Error::InvalidInput(format!(
+            "{} has no keys to share with",
+            identity.username
+        )));
+    }
+
+    let content_key: [u8; AES_KEY_LENGTH] = backend.random()?;
+    let mut recipients = Vec::new();
+    for key in &identity.keys {
+        recipients.push(
+            encrypt_content_key_to_pubkey(backend, &content_key, &key.credential_id, &key.pubkey)
+                .await?,
+        );
+    }
+
+    encrypt_with_key(
+        backend,
+        data,
+        additional_data,
+        &backend.import_aes_gcm_key(&content_key).await?,
+        ContentKey::Recipients { recipients },
+    )
+    .await
+}
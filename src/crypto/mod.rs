@@ -2,37 +2,61 @@ use std::collections::HashSet;
 use std::rc::Rc;
 
 use js_sys::Array;
-use js_sys::ArrayBuffer;
-use js_sys::Reflect;
 use js_sys::Uint8Array;
 use serde::Deserialize;
 use serde::Serialize;
 use wasm_bindgen::JsValue;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::console;
-use web_sys::AesGcmParams;
-use web_sys::AesKeyGenParams;
 use web_sys::Crypto;
 use web_sys::CryptoKey;
-use web_sys::EcKeyGenParams;
-use web_sys::EcdhKeyDeriveParams;
-use web_sys::HkdfParams;
 use web_sys::PublicKeyCredential;
 use web_sys::PublicKeyCredentialDescriptor;
 use web_sys::PublicKeyCredentialType;
 use web_sys::SubtleCrypto;
+use zeroize::Zeroize;
 
 use crate::data::vault::UserConfig;
 use crate::data::CredentialId;
-use crate::error::JsOrSerdeError;
+use crate::data::UserHandle;
+use crate::error::Error;
+use crate::webauthn::prf_create_output;
 use crate::webauthn::prf_extension_eval;
 use crate::webauthn::prf_extension_eval_by_credential;
 use crate::webauthn::prf_first_output;
 use crate::webauthn::webauthn_create;
 use crate::webauthn::webauthn_get_with_allow_credentials;
 
+pub mod backend;
+pub mod identity;
+pub mod passphrase;
+pub mod recovery;
+pub mod rustcrypto;
+pub mod shamir;
+pub mod webcrypto;
+
+pub use backend::CryptoBackend;
+pub use webcrypto::WebCrypto;
+
 const AES_SIZE: u16 = 256;
+const AES_KEY_LENGTH: usize = AES_SIZE as usize / 8;
 const AES_IV_LENGTH: usize = 96 / 8;
+const ENCRYPTED_CONTENT_VERSION: u32 = 4;
+
+/// The content format version of padded content. Builds from before padding
+/// would decrypt it to the padded plaintext, so they must reject it.
+const PADDED_CONTENT_VERSION: u32 = 5;
+const AES_TAG_LENGTH: usize = 128 / 8;
+
+/// The last content format version that was encrypted in a single AES-GCM
+/// call, which is still decrypted.
+const SINGLE_SHOT_CONTENT_VERSION: u32 = 3;
+
+/// The plaintext length of each chunk of newly encrypted content.
+const STREAM_CHUNK_SIZE: u32 = 64 * 1024;
+
+/// The random part of each chunk nonce; the rest is the chunk counter.
+const STREAM_NONCE_PREFIX_LENGTH: usize = AES_IV_LENGTH - 4;
 const EC_CURVE: &str = "P-256";
 
 type AesIv = [u8; AES_IV_LENGTH];
@@ -84,6 +108,54 @@ pub struct WrappedContentKey {
     wrapped_content_key: Vec<u8>,
 }
 
+/// One share of a content key split with [shamir::split], wrapped to one
+/// recipient the same way a whole content key is.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct WrappedKeyShare {
+    /// The x coordinate of the share, needed to combine it with others.
+    pub index: u8,
+    #[serde(flatten)]
+    pub key: WrappedContentKey,
+}
+
+/// The vault master key, wrapped to each credential the same way a content
+/// key is.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct WrappedMasterKey {
+    pub recipients: Vec<WrappedContentKey>,
+}
+
+/// An unwrapped vault master key. Overwritten with zeroes when dropped.
+pub struct MasterKey(Vec<u8>);
+
+impl Drop for MasterKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// How the key for an [EncryptedContent] is obtained.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ContentKey {
+    /// Derived from the vault master key using HKDF with this salt.
+    MasterKey {
+        #[serde(with = "crate::data::base64")]
+        master_key_salt: Vec<u8>,
+    },
+
+    /// Generated at random and wrapped to each recipient, as in vault format
+    /// version 2.
+    Recipients { recipients: Vec<WrappedContentKey> },
+
+    /// Generated at random and split into shares, one wrapped to each
+    /// recipient. Any `threshold` of the shares together recover the key.
+    Threshold {
+        threshold: u8,
+        shares: Vec<WrappedKeyShare>,
+    },
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct EncryptedContent {
     #[serde(with = "crate::data::base64")]
@@ -92,147 +164,501 @@ pub struct EncryptedContent {
     iv: Vec<u8>,
     #[serde(with = "crate::data::base64")]
     additional_data: Vec<u8>,
-    pub recipients: Vec<WrappedContentKey>,
+    #[serde(flatten)]
+    pub key: ContentKey,
+}
+
+/// The private key of a [WrappedKeypair], unwrapped with the PRF output of
+/// its credential.
+pub struct UnlockedKey<B: CryptoBackend> {
+    pub credential_id: Vec<u8>,
+    pub private_key: B::EcdhPrivateKey,
+}
+
+/// Identifies the vault entry an [EncryptedContent] belongs to, and how it is
+/// encrypted. Stored in canonical JSON form as the AES-GCM associated data, so
+/// ciphertexts cannot be moved between entries or vaults without being
+/// detected.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct EncryptedContentAdditionalData {
+    #[serde(rename = "v")]
+    version: u32,
+    name: String,
+    user_handle: UserHandle,
+
+    /// Present from format version 4, see [ContentFormat::Stream].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chunk_size: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    padding: Option<Padding>,
 }
 
-pub fn crypto() -> Result<Crypto, JsValue> {
-    web_sys::window().ok_or(JsValue::UNDEFINED)?.crypto()
+impl EncryptedContentAdditionalData {
+    pub fn new(name: &str, user_handle: &UserHandle) -> Self {
+        Self {
+            version: ENCRYPTED_CONTENT_VERSION,
+            name: name.to_string(),
+            user_handle: user_handle.clone(),
+            chunk_size: Some(STREAM_CHUNK_SIZE),
+            padding: None,
+        }
+    }
+
+    /// Pad the plaintext with `padding` before it is encrypted.
+    pub fn with_padding(self, padding: Option<Padding>) -> Self {
+        Self {
+            version: match padding {
+                Some(_) => PADDED_CONTENT_VERSION,
+                None => ENCRYPTED_CONTENT_VERSION,
+            },
+            padding,
+            ..self
+        }
+    }
+
+    fn format(&self) -> Result<ContentFormat, Error> {
+        match (self.version, self.chunk_size, self.padding) {
+            (SINGLE_SHOT_CONTENT_VERSION, None, None) => Ok(ContentFormat::SingleShot),
+            (ENCRYPTED_CONTENT_VERSION, Some(chunk_size), None)
+            | (PADDED_CONTENT_VERSION, Some(chunk_size), Some(_))
+                if chunk_size > 0 =>
+            {
+                Ok(ContentFormat::Stream { chunk_size })
+            }
+            (ENCRYPTED_CONTENT_VERSION | PADDED_CONTENT_VERSION, None | Some(0), _) => Err(
+                Error::MalformedRecord("Content has no valid chunk size".to_string()),
+            ),
+            (version @ (SINGLE_SHOT_CONTENT_VERSION | ENCRYPTED_CONTENT_VERSION), _, Some(_)) => {
+                Err(Error::MalformedRecord(format!(
+                    "Content format version {version} cannot be padded"
+                )))
+            }
+            (PADDED_CONTENT_VERSION, _, None) => Err(Error::MalformedRecord(format!(
+                "Content format version {PADDED_CONTENT_VERSION} must be padded"
+            ))),
+            (version, _, _) => Err(Error::MalformedRecord(format!(
+                "Unknown content format version: {version}"
+            ))),
+        }
+    }
 }
 
-pub fn subtle_crypto() -> Result<SubtleCrypto, JsValue> {
-    Ok(web_sys::window()
-        .ok_or(JsValue::UNDEFINED)?
-        .crypto()?
-        .subtle())
+/// How the plaintext of an [EncryptedContent] is padded before encryption, so
+/// that the ciphertext length reveals less about the plaintext length. The
+/// plaintext is followed by a `0x80` byte and then zeroes up to the padded
+/// length, as in ISO/IEC 7816-4.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Padding {
+    /// The Padmé scheme of Nikitin et al., which adds at most 12% and leaks
+    /// O(log log L) bits of a length L.
+    Padme,
+
+    /// Pad to the next power of two, which adds up to 100% and leaks
+    /// O(log log L) bits too, but in fewer, larger buckets.
+    PowerOfTwo,
 }
 
-pub fn gen_random<const N: usize>() -> Result<[u8; N], JsValue> {
+impl Padding {
+    /// The length `len` bytes are padded to.
+    pub fn padded_length(self, len: usize) -> usize {
+        match self {
+            Self::Padme => {
+                if len < 2 {
+                    return len;
+                }
+                let exponent = len.ilog2();
+                let significant_bits = exponent.ilog2() + 1;
+                let mask = (1 << (exponent - significant_bits)) - 1;
+                (len + mask) & !mask
+            }
+            Self::PowerOfTwo => len.next_power_of_two(),
+        }
+    }
+
+    pub fn pad(self, data: &[u8]) -> Vec<u8> {
+        let mut padded = Vec::with_capacity(self.padded_length(data.len() + 1));
+        padded.extend_from_slice(data);
+        padded.push(0x80);
+        padded.resize(self.padded_length(data.len() + 1), 0);
+        padded
+    }
+
+    /// Strip the padding added by [Self::pad].
+    pub fn unpad(mut padded: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let end = padded.iter().rposition(|b| *b != 0);
+        match end {
+            Some(end) if padded[end] == 0x80 => {
+                padded.truncate(end);
+                Ok(padded)
+            }
+            _ => Err(Error::MalformedRecord(
+                "Content padding is invalid".to_string(),
+            )),
+        }
+    }
+}
+
+/// How the plaintext of an [EncryptedContent] is encrypted with its content
+/// key.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContentFormat {
+    /// In one AES-GCM call, as in content format version 3 and before.
+    SingleShot,
+
+    /// In chunks of `chunk_size` bytes, in the STREAM construction of Hoang,
+    /// Reyhanitabar, Rogaway and Vizár. Each chunk is encrypted with the
+    /// stored IV as nonce prefix and the chunk counter as suffix, and with the
+    /// associated data followed by a byte that is 1 for the last chunk and 0
+    /// for the others. So chunks cannot be reordered, dropped or truncated
+    /// without being detected.
+    Stream { chunk_size: u32 },
+}
+
+impl EncryptedContent {
+    /// Whether this content is bound to its entry name and vault by its
+    /// associated data. Content from before the associated data was
+    /// introduced is not, and could be swapped with another entry until it is
+    /// re-encrypted.
+    pub fn is_bound(&self) -> bool {
+        !self.additional_data.is_empty()
+    }
+
+    /// Check that this content was encrypted for the entry described by
+    /// `expected`. Content from before the associated data was introduced has
+    /// none, and is accepted as-is until it is next re-encrypted; see
+    /// [Self::is_bound].
+    pub fn check_additional_data(
+        &self,
+        expected: &EncryptedContentAdditionalData,
+    ) -> Result<(), Error> {
+        let Some(additional_data) = self.stored_additional_data()? else {
+            return Ok(());
+        };
+        additional_data.format()?;
+        if additional_data.name != expected.name
+            || additional_data.user_handle != expected.user_handle
+        {
+            Err(Error::MalformedRecord(
+                "Entry was moved or renamed without being re-encrypted".to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The associated data this content was encrypted with, unless it is from
+    /// before associated data was introduced.
+    fn stored_additional_data(&self) -> Result<Option<EncryptedContentAdditionalData>, Error> {
+        if self.additional_data.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(serde_json::from_slice(&self.additional_data)?))
+        }
+    }
+
+    /// The entry name this content was encrypted for, unless it is from
+    /// before associated data was introduced.
+    pub fn name(&self) -> Result<Option<String>, Error> {
+        Ok(self
+            .stored_additional_data()?
+            .map(|additional_data| additional_data.name))
+    }
+
+    pub fn format(&self) -> Result<ContentFormat, Error> {
+        match self.stored_additional_data()? {
+            Some(additional_data) => additional_data.format(),
+            None => Ok(ContentFormat::SingleShot),
+        }
+    }
+
+    pub fn padding(&self) -> Result<Option<Padding>, Error> {
+        Ok(self
+            .stored_additional_data()?
+            .and_then(|additional_data| additional_data.padding))
+    }
+}
+
+/// The nonce and associated data of chunk `index` of [ContentFormat::Stream]
+/// content.
+fn stream_chunk_params(
+    nonce_prefix: &[u8],
+    additional_data: &[u8],
+    index: usize,
+    last: bool,
+) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let counter = u32::try_from(index)
+        .map_err(|_| Error::InvalidInput("Content has too many chunks".to_string()))?;
+    let nonce = [nonce_prefix, &counter.to_be_bytes()].concat();
+    let chunk_additional_data = [additional_data, &[u8::from(last)]].concat();
+    Ok((nonce, chunk_additional_data))
+}
+
+async fn stream_encrypt<B: CryptoBackend>(
+    backend: &B,
+    content_key: &B::AesGcmKey,
+    nonce_prefix: &[u8],
+    additional_data: &[u8],
+    chunk_size: u32,
+    data: &[u8],
+) -> Result<Vec<u8>, Error> {
+    // Empty content is one empty last chunk
+    let chunks: Vec<&[u8]> = if data.is_empty() {
+        vec![data]
+    } else {
+        data.chunks(chunk_size as usize).collect()
+    };
+
+    let mut ciphertext = Vec::with_capacity(data.len() + chunks.len() * AES_TAG_LENGTH);
+    for (index, chunk) in chunks.iter().enumerate() {
+        let (nonce, chunk_additional_data) = stream_chunk_params(
+            nonce_prefix,
+            additional_data,
+            index,
+            index + 1 == chunks.len(),
+        )?;
+        ciphertext.extend(
+            backend
+                .aes_gcm_encrypt(content_key, &nonce, &chunk_additional_data, chunk)
+                .await?,
+        );
+    }
+    Ok(ciphertext)
+}
+
+async fn stream_decrypt<B: CryptoBackend>(
+    backend: &B,
+    content_key: &B::AesGcmKey,
+    nonce_prefix: &[u8],
+    additional_data: &[u8],
+    chunk_size: u32,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    if nonce_prefix.len() != STREAM_NONCE_PREFIX_LENGTH {
+        return Err(Error::MalformedRecord(
+            "Content has an IV of the wrong length".to_string(),
+        ));
+    }
+
+    let chunks: Vec<&[u8]> = if ciphertext.is_empty() {
+        vec![ciphertext]
+    } else {
+        ciphertext
+            .chunks(chunk_size as usize + AES_TAG_LENGTH)
+            .collect()
+    };
+
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    for (index, chunk) in chunks.iter().enumerate() {
+        let (nonce, chunk_additional_data) = stream_chunk_params(
+            nonce_prefix,
+            additional_data,
+            index,
+            index + 1 == chunks.len(),
+        )?;
+        plaintext.extend(
+            backend
+                .aes_gcm_decrypt(content_key, &nonce, &chunk_additional_data, chunk)
+                .await?,
+        );
+    }
+    Ok(plaintext)
+}
+
+/// Decrypt the ciphertext of `data` in whichever format it was encrypted, and
+/// strip any padding.
+async fn decrypt_content<B: CryptoBackend>(
+    backend: &B,
+    content_key: &B::AesGcmKey,
+    data: &EncryptedContent,
+) -> Result<Vec<u8>, Error> {
+    let plaintext = match data.format()? {
+        ContentFormat::SingleShot => {
+            backend
+                .aes_gcm_decrypt(
+                    content_key,
+                    &data.iv,
+                    &data.additional_data,
+                    &data.ciphertext,
+                )
+                .await
+        }
+        ContentFormat::Stream { chunk_size } => {
+            stream_decrypt(
+                backend,
+                content_key,
+                &data.iv,
+                &data.additional_data,
+                chunk_size,
+                &data.ciphertext,
+            )
+            .await
+        }
+    }?;
+    match data.padding()? {
+        Some(_) => Padding::unpad(plaintext),
+        None => Ok(plaintext),
+    }
+}
+
+pub fn crypto() -> Result<Crypto, Error> {
+    Ok(web_sys::window().ok_or(JsValue::UNDEFINED)?.crypto()?)
+}
+
+pub fn subtle_crypto() -> Result<SubtleCrypto, Error> {
+    Ok(crypto()?.subtle())
+}
+
+pub fn gen_random<const N: usize>() -> Result<[u8; N], Error> {
     let mut result: [u8; N] = [0; N];
     crypto()?.get_random_values_with_u8_array(&mut result)?;
     Ok(result)
 }
 
-pub async fn create_credential(
-    vault_config: &UserConfig,
-) -> Result<PublicKeyCredential, JsOrSerdeError> {
+/// A newly registered credential with the PRF extension enabled.
+pub struct NewCredential {
+    pub credential: PublicKeyCredential,
+    prf_salt: [u8; 32],
+
+    /// Present if the authenticator evaluated the PRF during registration.
+    prf_output: Option<Vec<u8>>,
+}
+
+impl NewCredential {
+    pub fn credential_id(&self) -> Vec<u8> {
+        Uint8Array::new(&self.credential.raw_id()).to_vec()
+    }
+}
+
+/// Register a new credential, failing with [Error::PrfUnsupported] if the
+/// authenticator did not enable the PRF extension.
+pub async fn create_credential(vault_config: &UserConfig) -> Result<NewCredential, Error> {
     console::log_1(&"add_credential 1".into());
 
     let prf_salt: [u8; 32] = gen_random()?;
     let challenge: [u8; 32] = gen_random()?;
 
-    Ok(JsFuture::from(webauthn_create(
+    let credential: PublicKeyCredential = JsFuture::from(webauthn_create(
         challenge.as_slice(),
-        &vault_config,
+        vault_config,
         Some(&prf_extension_eval(
             &Uint8Array::from(prf_salt.as_slice()).buffer(),
-        )),
+        )?),
     )?)
     .await?
-    .into())
-}
+    .into();
 
-pub async fn create_wrapped_keypair(
-    credential_id: &[u8],
-) -> Result<WrappedKeypair, JsOrSerdeError> {
-    let subtle = subtle_crypto()?;
+    let prf_output: Option<Vec<u8>> = prf_create_output(&credential)?.map(|o| o.to_vec());
 
-    let prf_salt: [u8; 32] = gen_random()?;
-
-    let prf_output: Uint8Array = {
-        let get_challenge: [u8; 32] = gen_random()?;
-        let cred: PublicKeyCredential = JsFuture::from(webauthn_get_with_allow_credentials(
-            get_challenge.as_slice(),
-            Some(PublicKeyCredentialDescriptor::new(
-                &Uint8Array::from(credential_id),
-                PublicKeyCredentialType::PublicKey,
-            ))
-            .into_iter()
-            .collect::<Array>(),
-            Some(&prf_extension_eval(
-                &Uint8Array::from(prf_salt.as_slice()).buffer(),
-            )),
-        )?)
-        .await?
-        .into();
-        prf_first_output(&cred)?
-    };
+    Ok(NewCredential {
+        credential,
+        prf_salt,
+        prf_output,
+    })
+}
 
-    let hkdf_salt: [u8; 32] = gen_random()?;
+/// Generate a new ECDH keypair and wrap its private key with a key derived
+/// from `prf_output`, which must be the PRF output for `prf_salt`.
+pub async fn wrap_keypair<B: CryptoBackend>(
+    backend: &B,
+    credential_id: &[u8],
+    prf_salt: &[u8],
+    prf_output: &[u8],
+) -> Result<WrappedKeypair, Error> {
+    let hkdf_salt: [u8; 32] = backend.random()?;
     let hkdf_info: [u8; 0] = [];
-    let base_key: CryptoKey = JsFuture::from(subtle.import_key_with_str(
-        "raw",
-        &prf_output,
-        "HKDF",
-        false,
-        &Array::of1(&"deriveKey".into()),
-    )?)
-    .await?
-    .into();
+    let wrapping_key = backend
+        .hkdf_aes_gcm_key(prf_output, &hkdf_salt, &hkdf_info)
+        .await?;
 
-    let wrapping_key: CryptoKey = JsFuture::from(subtle.derive_key_with_object_and_object(
-        &HkdfParams::new(
-            "HKDF",
-            &"SHA-256".into(),
-            &Uint8Array::from(hkdf_info.as_slice()),
-            &Uint8Array::from(hkdf_salt.as_slice()),
-        ),
-        &base_key,
-        &AesKeyGenParams::new("AES-GCM", AES_SIZE),
-        false,
-        &Array::of1(&"wrapKey".into()),
-    )?)
-    .await?
-    .into();
+    let (private_key, pubkey) = backend.ecdh_generate_keypair(true).await?;
 
-    let keypair = JsFuture::from(subtle.generate_key_with_object(
-        &EcKeyGenParams::new("ECDH", EC_CURVE),
-        true,
-        &Array::of1(&"deriveKey".into()),
-    )?)
-    .await?;
-    console::log_2(&"keypair".into(), &keypair);
-
-    let pubkey: ArrayBuffer = JsFuture::from(
-        subtle.export_key("raw", &Reflect::get(&keypair, &"publicKey".into())?.into())?,
-    )
-    .await?
-    .into();
-    console::log_2(&"pubkey".into(), &pubkey);
-
-    let iv: AesIv = gen_random()?;
+    let iv: AesIv = backend.random()?;
     let additional_data = WrappedKeypairAdditionalData {
         credential_id: credential_id.into(),
-        pubkey: Uint8Array::new(&pubkey).to_vec(),
+        pubkey,
         prf_salt: prf_salt.into(),
         hkdf_salt: hkdf_salt.into(),
         hkdf_info: hkdf_info.into(),
     };
     let additional_data_bytes: Vec<u8> = serde_json::to_vec(&additional_data)?;
 
-    let wrapped_private_key: ArrayBuffer = JsFuture::from(
-        subtle.wrap_key_with_object(
-            "jwk",
-            &Reflect::get(&keypair, &"privateKey".into())?.into(),
-            &wrapping_key,
-            AesGcmParams::new("AES-GCM", &Uint8Array::from(iv.as_slice()))
-                .additional_data(&Uint8Array::from(additional_data_bytes.as_slice())),
-        )?,
-    )
-    .await?
-    .into();
-    console::log_2(&"wrapped_private_key".into(), &wrapped_private_key);
+    let wrapped_private_key = backend
+        .wrap_ecdh_private_key(&wrapping_key, &iv, &additional_data_bytes, &private_key)
+        .await?;
 
     Ok(WrappedKeypair {
-        wrapped_private_key: Uint8Array::new(&wrapped_private_key).to_vec(),
+        wrapped_private_key,
         iv: iv.into(),
         additional_data: additional_data_bytes,
         nickname: None,
     })
 }
 
+/// Unwrap the private key of `wrapped_keypair` using the PRF output for its
+/// `prf_salt`.
+pub async fn unwrap_keypair<B: CryptoBackend>(
+    backend: &B,
+    wrapped_keypair: &WrappedKeypair,
+    prf_output: &[u8],
+) -> Result<B::EcdhPrivateKey, Error> {
+    let additional_data = wrapped_keypair.additional_data()?;
+
+    let wrapping_key = backend
+        .hkdf_aes_gcm_key(
+            prf_output,
+            &additional_data.hkdf_salt,
+            &additional_data.hkdf_info,
+        )
+        .await?;
+
+    backend
+        .unwrap_ecdh_private_key(
+            &wrapping_key,
+            &wrapped_keypair.iv,
+            &wrapped_keypair.additional_data,
+            &wrapped_keypair.wrapped_private_key,
+        )
+        .await
+}
+
+/// Create a keypair wrapped with the PRF output of `new_credential`. This
+/// needs a second WebAuthn ceremony only if the authenticator did not
+/// evaluate the PRF during registration.
+pub async fn create_wrapped_keypair(
+    new_credential: &NewCredential,
+) -> Result<WrappedKeypair, Error> {
+    let credential_id: Vec<u8> = new_credential.credential_id();
+    let prf_salt: &[u8; 32] = &new_credential.prf_salt;
+
+    let prf_output: Vec<u8> = if let Some(prf_output) = &new_credential.prf_output {
+        prf_output.clone()
+    } else {
+        let get_challenge: [u8; 32] = gen_random()?;
+        let cred: PublicKeyCredential = JsFuture::from(webauthn_get_with_allow_credentials(
+            get_challenge.as_slice(),
+            Some(PublicKeyCredentialDescriptor::new(
+                &Uint8Array::from(credential_id.as_slice()),
+                PublicKeyCredentialType::PublicKey,
+            ))
+            .into_iter()
+            .collect::<Array>(),
+            Some(&prf_extension_eval(
+                &Uint8Array::from(prf_salt.as_slice()).buffer(),
+            )?),
+        )?)
+        .await?
+        .into();
+        prf_first_output(&cred)?.to_vec()
+    };
+
+    wrap_keypair(&WebCrypto, &credential_id, prf_salt, &prf_output).await
+}
+
 pub async fn unwrap_private_key(
     wrapped_keypairs: &[Rc<WrappedKeypair>],
-) -> Result<(Vec<u8>, CryptoKey), JsOrSerdeError> {
+) -> Result<UnlockedKey<WebCrypto>, Error> {
     let get_challenge: [u8; 32] = gen_random()?;
     let cred: PublicKeyCredential = JsFuture::from(webauthn_get_with_allow_credentials(
         get_challenge.as_slice(),
@@ -245,7 +671,7 @@ pub async fn unwrap_private_key(
                     PublicKeyCredentialType::PublicKey,
                 ))
             })
-            .collect::<Result<Array, JsOrSerdeError>>()?,
+            .collect::<Result<Array, Error>>()?,
         Some(&prf_extension_eval_by_credential(wrapped_keypairs)?),
     )?)
     .await?
@@ -261,247 +687,471 @@ pub async fn unwrap_private_key(
         .iter()
         .find(|wkp| {
             wkp.additional_data()
-                .map_or(false, |ead| ead.credential_id == credential_id)
+                .is_ok_and(|ead| ead.credential_id == credential_id)
         })
-        .unwrap();
+        .ok_or(Error::NoMatchingRecipient)?;
+
+    let private_key: CryptoKey =
+        unwrap_keypair(&WebCrypto, wrapped_keypair, &prf_output.to_vec()).await?;
 
+    Ok(UnlockedKey {
+        credential_id,
+        private_key,
+    })
+}
+
+pub async fn encrypt_content_key_to_recipient<B: CryptoBackend>(
+    backend: &B,
+    content_key: &[u8],
+    wrapped_keypair: &WrappedKeypair,
+) -> Result<WrappedContentKey, Error> {
     let additional_data = wrapped_keypair.additional_data()?;
+    encrypt_content_key_to_pubkey(
+        backend,
+        content_key,
+        &additional_data.credential_id,
+        &additional_data.pubkey,
+    )
+    .await
+}
 
-    let subtle: SubtleCrypto = subtle_crypto()?;
-    let base_key: CryptoKey = JsFuture::from(subtle.import_key_with_str(
-        "raw",
-        &prf_output,
-        "HKDF",
-        false,
-        &Array::of1(&"deriveKey".into()),
-    )?)
-    .await?
-    .into();
-    console::log_2(&"base_key".into(), &base_key);
-
-    let wrapping_key: CryptoKey = JsFuture::from(subtle.derive_key_with_object_and_object(
-        &HkdfParams::new(
-            "HKDF",
-            &"SHA-256".into(),
-            &Uint8Array::from(additional_data.hkdf_info.as_slice()),
-            &Uint8Array::from(additional_data.hkdf_salt.as_slice()),
-        ),
-        &base_key,
-        &AesKeyGenParams::new("AES-GCM", AES_SIZE),
-        false,
-        &Array::of1(&"unwrapKey".into()),
-    )?)
-    .await?
-    .into();
-    console::log_2(&"wrapping_key".into(), &wrapping_key);
+/// Wrap `content_key` to the holder of the private key for `pubkey`, who is
+/// identified by `credential_id` among the recipients.
+pub async fn encrypt_content_key_to_pubkey<B: CryptoBackend>(
+    backend: &B,
+    content_key: &[u8],
+    credential_id: &[u8],
+    pubkey: &[u8],
+) -> Result<WrappedContentKey, Error> {
+    let (wrapping_exchange_private_key, wrapping_exchange_pubkey) =
+        backend.ecdh_generate_keypair(false).await?;
 
-    let mut wrapped_private_key: Vec<u8> = wrapped_keypair.wrapped_private_key.to_vec();
+    let wrapping_key = backend
+        .ecdh_derive_aes_kw_key(&wrapping_exchange_private_key, pubkey)
+        .await?;
 
-    let private_key: CryptoKey = JsFuture::from(
-        subtle.unwrap_key_with_u8_array_and_object_and_object(
-            "jwk",
-            wrapped_private_key.as_mut_slice(),
-            &wrapping_key,
-            AesGcmParams::new("AES-GCM", &Uint8Array::from(wrapped_keypair.iv.as_slice()))
-                .additional_data(&Uint8Array::from(
-                    wrapped_keypair.additional_data.as_slice(),
-                )),
-            &EcKeyGenParams::new("ECDH", EC_CURVE),
-            false,
-            &Array::of1(&"deriveKey".into()),
-        )?,
-    )
-    .await?
-    .into();
+    let wrapped_content_key = backend.aes_kw_wrap(&wrapping_key, content_key).await?;
+
+    Ok(WrappedContentKey {
+        credential_id: credential_id.to_vec(),
+        wrapping_exchange_pubkey,
+        wrapped_content_key,
+    })
+}
+
+/// Unwrap the key wrapped to the credential of `unlocked` in `recipients`.
+pub async fn decrypt_content_key<B: CryptoBackend>(
+    backend: &B,
+    recipients: &[WrappedContentKey],
+    unlocked: &UnlockedKey<B>,
+) -> Result<Vec<u8>, Error> {
+    let recipient: &WrappedContentKey = recipients
+        .iter()
+        .find(|wkp| wkp.credential_id == unlocked.credential_id)
+        .ok_or(Error::NoMatchingRecipient)?;
 
-    Ok((credential_id, private_key))
+    let wrapping_key = backend
+        .ecdh_derive_aes_kw_key(&unlocked.private_key, &recipient.wrapping_exchange_pubkey)
+        .await?;
+
+    backend
+        .aes_kw_unwrap(&wrapping_key, &recipient.wrapped_content_key)
+        .await
 }
 
-pub async fn encrypt_content_key_to_recipient(
-    content_key: &CryptoKey,
-    wrapped_keypair: &WrappedKeypair,
-) -> Result<WrappedContentKey, JsOrSerdeError> {
-    let subtle: SubtleCrypto = subtle_crypto()?;
+/// Generate a new vault master key and wrap it to each of `wrapped_keypairs`.
+pub async fn create_master_key<B: CryptoBackend>(
+    backend: &B,
+    wrapped_keypairs: &[Rc<WrappedKeypair>],
+) -> Result<(MasterKey, WrappedMasterKey), Error> {
+    let master_key = MasterKey(backend.random::<AES_KEY_LENGTH>()?.to_vec());
 
-    let wrapping_exchange_keypair = JsFuture::from(subtle.generate_key_with_object(
-        &EcKeyGenParams::new("ECDH", EC_CURVE),
-        false,
-        &Array::of1(&"deriveKey".into()),
-    )?)
-    .await?;
-    console::log_2(
-        &"wrapping_exchange_keypair".into(),
-        &wrapping_exchange_keypair,
-    );
+    let mut recipients: Vec<WrappedContentKey> = Vec::new();
+    for wrapped_keypair in wrapped_keypairs {
+        recipients.push(wrap_master_key_to_recipient(backend, &master_key, wrapped_keypair).await?);
+    }
 
-    let recipient_pubkey: CryptoKey = JsFuture::from(subtle.import_key_with_object(
-        "raw",
-        &Uint8Array::from(wrapped_keypair.additional_data()?.pubkey.as_slice()),
-        &EcKeyGenParams::new("ECDH", EC_CURVE),
-        false,
-        &Array::new(), // CHROME: []   FIREFOX: ["deriveKey"]
-    )?)
-    .await?
-    .into();
-    console::log_2(&"recipient_pubkey".into(), &recipient_pubkey);
-
-    let wrapping_key: CryptoKey = JsFuture::from(subtle.derive_key_with_object_and_object(
-        &EcdhKeyDeriveParams::new("ECDH", &recipient_pubkey),
-        &Reflect::get(&wrapping_exchange_keypair, &"privateKey".into())?.into(),
-        &AesKeyGenParams::new("AES-KW", AES_SIZE),
-        false,
-        &Array::of1(&"wrapKey".into()),
-    )?)
-    .await?
-    .into();
-    console::log_2(&"wrapping_key".into(), &wrapping_key);
+    Ok((master_key, WrappedMasterKey { recipients }))
+}
 
-    let wrapped_content_key: ArrayBuffer =
-        JsFuture::from(subtle.wrap_key_with_str("raw", &content_key, &wrapping_key, "AES-KW")?)
-            .await?
-            .into();
-    console::log_2(&"wrapped_content_key".into(), &wrapped_content_key);
+pub async fn wrap_master_key_to_recipient<B: CryptoBackend>(
+    backend: &B,
+    master_key: &MasterKey,
+    wrapped_keypair: &WrappedKeypair,
+) -> Result<WrappedContentKey, Error> {
+    encrypt_content_key_to_recipient(backend, &master_key.0, wrapped_keypair).await
+}
 
-    let wrapping_exchange_pubkey: ArrayBuffer = JsFuture::from(subtle.export_key(
-        "raw",
-        &Reflect::get(&wrapping_exchange_keypair, &"publicKey".into())?.into(),
-    )?)
-    .await?
-    .into();
-    console::log_2(
-        &"wrapping_exchange_pubkey".into(),
-        &wrapping_exchange_pubkey,
-    );
+pub async fn unwrap_master_key<B: CryptoBackend>(
+    backend: &B,
+    wrapped_master_key: &WrappedMasterKey,
+    unlocked: &UnlockedKey<B>,
+) -> Result<MasterKey, Error> {
+    Ok(MasterKey(
+        decrypt_content_key(backend, &wrapped_master_key.recipients, unlocked).await?,
+    ))
+}
 
-    Ok(WrappedContentKey {
-        credential_id: wrapped_keypair.additional_data()?.credential_id,
-        wrapping_exchange_pubkey: Uint8Array::new(&wrapping_exchange_pubkey).to_vec(),
-        wrapped_content_key: Uint8Array::new(&wrapped_content_key).to_vec(),
-    })
+async fn derive_content_key<B: CryptoBackend>(
+    backend: &B,
+    master_key: &MasterKey,
+    master_key_salt: &[u8],
+) -> Result<B::AesGcmKey, Error> {
+    backend
+        .hkdf_aes_gcm_key(&master_key.0, master_key_salt, b"content")
+        .await
 }
 
-pub async fn encrypt(
-    mut data: Vec<u8>,
+/// Encrypt `data` with a content key wrapped to each of `wrapped_keypairs`.
+pub async fn encrypt_with<B: CryptoBackend>(
+    backend: &B,
+    data: &[u8],
+    additional_data: &EncryptedContentAdditionalData,
     wrapped_keypairs: &[Rc<WrappedKeypair>],
-) -> Result<EncryptedContent, JsOrSerdeError> {
-    let subtle: SubtleCrypto = subtle_crypto()?;
-
-    let content_key: CryptoKey = JsFuture::from(subtle.generate_key_with_object(
-        &AesKeyGenParams::new("AES-GCM", AES_SIZE),
-        true,
-        &Array::of1(&"encrypt".into()),
-    )?)
-    .await?
-    .into();
+) -> Result<EncryptedContent, Error> {
+    let content_key: [u8; AES_KEY_LENGTH] = backend.random()?;
 
     let mut wrapping_keys: Vec<WrappedContentKey> = Vec::new();
     for wrapped_keypair in wrapped_keypairs {
-        wrapping_keys.push(encrypt_content_key_to_recipient(&content_key, wrapped_keypair).await?);
+        wrapping_keys
+            .push(encrypt_content_key_to_recipient(backend, &content_key, wrapped_keypair).await?);
+    }
+
+    encrypt_with_key(
+        backend,
+        data,
+        additional_data,
+        &backend.import_aes_gcm_key(&content_key).await?,
+        ContentKey::Recipients {
+            recipients: wrapping_keys,
+        },
+    )
+    .await
+}
+
+/// Encrypt `data` with a content key split into one share for each of
+/// `wrapped_keypairs`, so that any `threshold` of them are needed to decrypt.
+pub async fn encrypt_with_threshold<B: CryptoBackend>(
+    backend: &B,
+    data: &[u8],
+    additional_data: &EncryptedContentAdditionalData,
+    threshold: u8,
+    wrapped_keypairs: &[Rc<WrappedKeypair>],
+) -> Result<EncryptedContent, Error> {
+    let mut credential_ids: HashSet<Vec<u8>> = HashSet::new();
+    for wrapped_keypair in wrapped_keypairs {
+        if !credential_ids.insert(wrapped_keypair.additional_data()?.credential_id) {
+            return Err(Error::InvalidInput(
+                "Each key can hold only one share".to_string(),
+            ));
+        }
     }
 
-    let iv: AesIv = gen_random()?;
-    let additional_data_bytes: Vec<u8> = vec![];
+    let content_key: [u8; AES_KEY_LENGTH] = backend.random()?;
+    let shares = shamir::split(backend, &content_key, threshold, wrapped_keypairs.len())?;
+
+    let mut wrapped_shares: Vec<WrappedKeyShare> = Vec::new();
+    for (share, wrapped_keypair) in shares.iter().zip(wrapped_keypairs) {
+        wrapped_shares.push(WrappedKeyShare {
+            index: share.index,
+            key: encrypt_content_key_to_recipient(backend, &share.value, wrapped_keypair).await?,
+        });
+    }
 
-    let ciphertext: ArrayBuffer = JsFuture::from(
-        subtle.encrypt_with_object_and_u8_array(
-            AesGcmParams::new("AES-GCM", &Uint8Array::from(iv.as_slice()))
-                .additional_data(&Uint8Array::from(additional_data_bytes.as_slice())),
-            &content_key,
-            &mut data,
-        )?,
+    encrypt_with_key(
+        backend,
+        data,
+        additional_data,
+        &backend.import_aes_gcm_key(&content_key).await?,
+        ContentKey::Threshold {
+            threshold,
+            shares: wrapped_shares,
+        },
     )
-    .await?
-    .into();
+    .await
+}
+
+/// Encrypt `data` with a content key derived from `master_key`.
+pub async fn encrypt_with_master_key<B: CryptoBackend>(
+    backend: &B,
+    data: &[u8],
+    additional_data: &EncryptedContentAdditionalData,
+    master_key: &MasterKey,
+) -> Result<EncryptedContent, Error> {
+    let master_key_salt: [u8; 32] = backend.random()?;
+
+    encrypt_with_key(
+        backend,
+        data,
+        additional_data,
+        &derive_content_key(backend, master_key, &master_key_salt).await?,
+        ContentKey::MasterKey {
+            master_key_salt: master_key_salt.to_vec(),
+        },
+    )
+    .await
+}
+
+async fn encrypt_with_key<B: CryptoBackend>(
+    backend: &B,
+    data: &[u8],
+    additional_data: &EncryptedContentAdditionalData,
+    content_key: &B::AesGcmKey,
+    key: ContentKey,
+) -> Result<EncryptedContent, Error> {
+    let additional_data_bytes: Vec<u8> = serde_json::to_vec(additional_data)?;
+    let padded: Vec<u8>;
+    let data: &[u8] = match additional_data.padding {
+        Some(padding) => {
+            padded = padding.pad(data);
+            &padded
+        }
+        None => data,
+    };
+
+    let (iv, ciphertext) = match additional_data.format()? {
+        ContentFormat::SingleShot => {
+            let iv: AesIv = backend.random()?;
+            let ciphertext = backend
+                .aes_gcm_encrypt(content_key, &iv, &additional_data_bytes, data)
+                .await?;
+            (iv.to_vec(), ciphertext)
+        }
+        ContentFormat::Stream { chunk_size } => {
+            let nonce_prefix: [u8; STREAM_NONCE_PREFIX_LENGTH] = backend.random()?;
+            let ciphertext = stream_encrypt(
+                backend,
+                content_key,
+                &nonce_prefix,
+                &additional_data_bytes,
+                chunk_size,
+                data,
+            )
+            .await?;
+            (nonce_prefix.to_vec(), ciphertext)
+        }
+    };
 
     Ok(EncryptedContent {
-        ciphertext: Uint8Array::new(&ciphertext).to_vec(),
-        iv: iv.to_vec(),
+        ciphertext,
+        iv,
         additional_data: additional_data_bytes,
-        recipients: wrapping_keys,
+        key,
     })
 }
 
+/// Decrypt `data` using the unwrapped private key of one of its recipients,
+/// or the vault master key if `data` was encrypted with it.
+pub async fn decrypt_with<B: CryptoBackend>(
+    backend: &B,
+    data: &EncryptedContent,
+    additional_data: &EncryptedContentAdditionalData,
+    unlocked: &UnlockedKey<B>,
+    master_key: Option<&MasterKey>,
+) -> Result<Vec<u8>, Error> {
+    data.check_additional_data(additional_data)?;
+
+    let content_key: B::AesGcmKey = match (&data.key, master_key) {
+        (ContentKey::MasterKey { master_key_salt }, Some(master_key)) => {
+            derive_content_key(backend, master_key, master_key_salt).await?
+        }
+        (ContentKey::MasterKey { .. }, None) => {
+            return Err(Error::MalformedRecord(
+                "Content is encrypted with the vault master key, but the vault has none"
+                    .to_string(),
+            ));
+        }
+        (ContentKey::Recipients { recipients }, _) => {
+            backend
+                .import_aes_gcm_key(&decrypt_content_key(backend, recipients, unlocked).await?)
+                .await?
+        }
+        (ContentKey::Threshold { threshold, shares }, _) => {
+            decrypt_key_shares(backend, *threshold, shares, std::slice::from_ref(unlocked)).await?
+        }
+    };
+
+    decrypt_content(backend, &content_key, data).await
+}
+
+/// Unwrap the shares held by `unlocked` and combine them into the content
+/// key, if there are at least `threshold` of them.
+async fn decrypt_key_shares<B: CryptoBackend>(
+    backend: &B,
+    threshold: u8,
+    shares: &[WrappedKeyShare],
+    unlocked: &[UnlockedKey<B>],
+) -> Result<B::AesGcmKey, Error> {
+    let mut unwrapped: Vec<shamir::Share> = Vec::new();
+    for share in shares {
+        if unwrapped.len() >= usize::from(threshold) {
+            break;
+        }
+        if let Some(unlocked) = unlocked
+            .iter()
+            .find(|unlocked| unlocked.credential_id == share.key.credential_id)
+        {
+            unwrapped.push(shamir::Share {
+                index: share.index,
+                value: decrypt_content_key(backend, std::slice::from_ref(&share.key), unlocked)
+                    .await?,
+            });
+        }
+    }
+
+    if unwrapped.is_empty() {
+        return Err(Error::NoMatchingRecipient);
+    } else if unwrapped.len() < usize::from(threshold) {
+        return Err(Error::NotEnoughKeys {
+            unlocked: unwrapped.len(),
+            needed: usize::from(threshold),
+        });
+    }
+
+    let mut content_key = shamir::combine(&unwrapped)?;
+    let imported = backend.import_aes_gcm_key(&content_key).await;
+    content_key.zeroize();
+    imported
+}
+
+/// Decrypt `data` whose content key is split into shares, using the
+/// unwrapped private keys of enough of the share holders.
+pub async fn decrypt_with_shares<B: CryptoBackend>(
+    backend: &B,
+    data: &EncryptedContent,
+    additional_data: &EncryptedContentAdditionalData,
+    unlocked: &[UnlockedKey<B>],
+) -> Result<Vec<u8>, Error> {
+    data.check_additional_data(additional_data)?;
+
+    match &data.key {
+        ContentKey::Threshold { threshold, shares } => {
+            decrypt_content(
+                backend,
+                &decrypt_key_shares(backend, *threshold, shares, unlocked).await?,
+                data,
+            )
+            .await
+        }
+        ContentKey::MasterKey { .. } | ContentKey::Recipients { .. } => Err(
+            Error::MalformedRecord("Content key is not split into shares".to_string()),
+        ),
+    }
+}
+
+/// Decrypt `data` that was encrypted with [encrypt_with_master_key].
+pub async fn decrypt_with_master_key<B: CryptoBackend>(
+    backend: &B,
+    data: &EncryptedContent,
+    additional_data: &EncryptedContentAdditionalData,
+    master_key: &MasterKey,
+) -> Result<Vec<u8>, Error> {
+    data.check_additional_data(additional_data)?;
+
+    match &data.key {
+        ContentKey::MasterKey { master_key_salt } => {
+            decrypt_content(
+                backend,
+                &derive_content_key(backend, master_key, master_key_salt).await?,
+                data,
+            )
+            .await
+        }
+        ContentKey::Recipients { .. } | ContentKey::Threshold { .. } => {
+            Err(Error::MalformedRecord(
+                "Content is not encrypted with the vault master key".to_string(),
+            ))
+        }
+    }
+}
+
+/// Perform WebAuthn ceremonies with the holders of `shares`, one at a time,
+/// until `threshold` different credentials have been used.
+async fn unlock_shares(
+    threshold: u8,
+    shares: &[WrappedKeyShare],
+    wrapped_keypairs: &[Rc<WrappedKeypair>],
+) -> Result<Vec<UnlockedKey<WebCrypto>>, Error> {
+    let mut unlocked: Vec<UnlockedKey<WebCrypto>> = Vec::new();
+    while unlocked.len() < usize::from(threshold) {
+        let remaining: Vec<Rc<WrappedKeypair>> = wrapped_keypairs
+            .iter()
+            .filter(|wkp| {
+                wkp.additional_data().is_ok_and(|ad| {
+                    shares
+                        .iter()
+                        .any(|share| share.key.credential_id == ad.credential_id)
+                        && !unlocked
+                            .iter()
+                            .any(|unlocked| unlocked.credential_id == ad.credential_id)
+                })
+            })
+            .cloned()
+            .collect();
+        if remaining.is_empty() {
+            return Err(Error::NotEnoughKeys {
+                unlocked: unlocked.len(),
+                needed: usize::from(threshold),
+            });
+        }
+        unlocked.push(unwrap_private_key(&remaining).await?);
+    }
+    Ok(unlocked)
+}
+
 pub async fn decrypt(
     data: &EncryptedContent,
+    additional_data: &EncryptedContentAdditionalData,
+    master_key: Option<&WrappedMasterKey>,
     wrapped_keypairs: &[Rc<WrappedKeypair>],
-) -> Result<Vec<u8>, JsOrSerdeError> {
-    let subtle: SubtleCrypto = subtle_crypto()?;
+) -> Result<Vec<u8>, Error> {
+    // Fail before asking for a WebAuthn ceremony if the entry has been tampered with
+    data.check_additional_data(additional_data)?;
 
-    let valid_credential_ids: HashSet<&Vec<u8>> = data
-        .recipients
-        .iter()
-        .map(|wck| &wck.credential_id)
-        .collect();
+    let recipients: &[WrappedContentKey] = match (&data.key, master_key) {
+        (ContentKey::MasterKey { .. }, Some(master_key)) => &master_key.recipients,
+        (ContentKey::MasterKey { .. }, None) => {
+            return Err(Error::MalformedRecord(
+                "Vault has no master key".to_string(),
+            ));
+        }
+        (ContentKey::Recipients { recipients }, _) => recipients,
+        (ContentKey::Threshold { threshold, shares }, _) => {
+            let unlocked = unlock_shares(*threshold, shares, wrapped_keypairs).await?;
+            return decrypt_with_shares(&WebCrypto, data, additional_data, &unlocked).await;
+        }
+    };
+    let valid_credential_ids: HashSet<&Vec<u8>> =
+        recipients.iter().map(|wck| &wck.credential_id).collect();
 
     let valid_keypairs: Vec<Rc<WrappedKeypair>> = wrapped_keypairs
         .iter()
-        .cloned()
         .filter(|wkp| {
             wkp.additional_data()
-                .map_or(false, |ad| valid_credential_ids.contains(&ad.credential_id))
+                .is_ok_and(|ad| valid_credential_ids.contains(&ad.credential_id))
         })
+        .cloned()
         .collect();
 
-    let (credential_id, private_key): (Vec<u8>, CryptoKey) =
-        unwrap_private_key(&valid_keypairs).await?;
+    let unlocked: UnlockedKey<WebCrypto> = unwrap_private_key(&valid_keypairs).await?;
     console::log_2(
         &"credential_id".into(),
-        &Uint8Array::from(credential_id.as_slice()),
+        &Uint8Array::from(unlocked.credential_id.as_slice()),
     );
 
-    let recipient: &WrappedContentKey = data
-        .recipients
-        .iter()
-        .find(|wkp| wkp.credential_id == credential_id)
-        .unwrap();
-
-    let pubkey: CryptoKey = JsFuture::from(subtle.import_key_with_object(
-        "raw",
-        &Uint8Array::from(recipient.wrapping_exchange_pubkey.as_slice()),
-        &EcKeyGenParams::new("ECDH", EC_CURVE),
-        false,
-        &Array::new(), // CHROME: []   FIREFOX: ["deriveKey"]
-    )?)
-    .await?
-    .into();
-    console::log_2(&"pubkey".into(), &pubkey);
-
-    let wrapping_key: CryptoKey = JsFuture::from(subtle.derive_key_with_object_and_object(
-        &EcdhKeyDeriveParams::new("ECDH", &pubkey),
-        &private_key,
-        &AesKeyGenParams::new("AES-KW", AES_SIZE),
-        false,
-        &Array::of1(&"unwrapKey".into()),
-    )?)
-    .await?
-    .into();
-    console::log_2(&"wrapping_key".into(), &wrapping_key);
-
-    let mut wrapped_content_key = recipient.wrapped_content_key.clone();
-    let content_key: CryptoKey = JsFuture::from(subtle.unwrap_key_with_u8_array_and_str_and_str(
-        "raw",
-        &mut wrapped_content_key,
-        &wrapping_key,
-        "AES-KW",
-        "AES-GCM",
-        false,
-        &Array::of1(&"decrypt".into()),
-    )?)
-    .await?
-    .into();
-    console::log_2(&"content_key".into(), &content_key);
-
-    let mut ciphertext = data.ciphertext.clone();
-    let content: ArrayBuffer = JsFuture::from(
-        subtle.decrypt_with_object_and_u8_array(
-            AesGcmParams::new("AES-GCM", &Uint8Array::from(data.iv.as_slice()))
-                .additional_data(&Uint8Array::from(data.additional_data.as_slice())),
-            &content_key,
-            &mut ciphertext,
-        )?,
-    )
-    .await?
-    .into();
+    let master_key: Option<MasterKey> = match (&data.key, master_key) {
+        (ContentKey::MasterKey { .. }, Some(master_key)) => {
+            Some(unwrap_master_key(&WebCrypto, master_key, &unlocked).await?)
+        }
+        _ => None,
+    };
 
-    Ok(Uint8Array::new(&content).to_vec())
+    decrypt_with(
+        &WebCrypto,
+        data,
+        additional_data,
+        &unlocked,
+        master_key.as_ref(),
+    )
+    .await
 }
@@ -0,0 +1,201 @@
+//! A recipient for browsers without the PRF extension: a P-256 keypair whose
+//! private key is wrapped with a key derived from a passphrase using Argon2id.
+//! This is weaker than a hardware key, since anyone with a copy of the vault
+//! can guess passphrases offline, slowed down only by the Argon2id cost.
+
+use argon2::Algorithm;
+use argon2::Argon2;
+use argon2::Params;
+use argon2::Version;
+use serde::Deserialize;
+use serde::Serialize;
+use zeroize::Zeroize;
+
+use crate::error::Error;
+
+use super::encrypt_content_key_to_pubkey;
+use super::AesIv;
+use super::CryptoBackend;
+use super::MasterKey;
+use super::UnlockedKey;
+use super::WrappedContentKey;
+use super::AES_KEY_LENGTH;
+
+const SALT_LENGTH: usize = 16;
+const ID_LENGTH: usize = 16;
+
+/// The Argon2id cost, stored with the key so it can be raised for new keys
+/// without breaking existing ones.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Argon2Params {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+/// The second recommended option of RFC 9106, with one lane since browsers
+/// run it on one thread anyway.
+impl Default for Argon2Params {
+    fn default() -> Self {
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+impl Argon2Params {
+    /// The highest cost accepted. Parameters are read from the vault, which
+    /// may come from a sync server or an import, so without a limit a
+    /// tampered copy could make unlocking exhaust memory or never finish.
+    pub const MAX: Self = Self {
+        memory_kib: 1024 * 1024,
+        iterations: 32,
+        parallelism: 16,
+    };
+
+    fn check(&self) -> Result<(), Error> {
+        let max = Self::MAX;
+        if self.memory_kib > max.memory_kib
+            || self.iterations > max.iterations
+            || self.parallelism > max.parallelism
+        {
+            return Err(Error::InvalidInput(format!(
+                "Argon2 parameters exceed the maximum of {} MiB, {} iterations and {} lanes",
+                max.memory_kib / 1024,
+                max.iterations,
+                max.parallelism
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Bound to the wrapped private key as AES-GCM associated data, so that none
+/// of it can be changed without unwrapping failing.
+#[derive(Serialize)]
+struct PassphraseKeyAdditionalData<'a> {
+    id: &'a [u8],
+    pubkey: &'a [u8],
+    params: &'a Argon2Params,
+    salt: &'a [u8],
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PassphraseKey {
+    /// Identifies the passphrase key in [WrappedContentKey::credential_id].
+    #[serde(with = "crate::data::base64")]
+    pub id: Vec<u8>,
+    #[serde(with = "crate::data::base64")]
+    pubkey: Vec<u8>,
+    pub params: Argon2Params,
+    #[serde(with = "crate::data::base64")]
+    salt: Vec<u8>,
+    #[serde(with = "crate::data::base64")]
+    iv: Vec<u8>,
+    #[serde(with = "crate::data::base64")]
+    wrapped_private_key: Vec<u8>,
+}
+
+impl PassphraseKey {
+    fn additional_data(&self) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec(&PassphraseKeyAdditionalData {
+            id: &self.id,
+            pubkey: &self.pubkey,
+            params: &self.params,
+            salt: &self.salt,
+        })?)
+    }
+}
+
+fn derive_wrapping_key(
+    passphrase: &str,
+    salt: &[u8],
+    params: &Argon2Params,
+) -> Result<[u8; AES_KEY_LENGTH], Error> {
+    params.check()?;
+    let params = Params::new(
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        Some(AES_KEY_LENGTH),
+    )
+    .map_err(|err| Error::InvalidInput(format!("Invalid Argon2 parameters: {err}")))?;
+    let mut key = [0; AES_KEY_LENGTH];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| Error::CryptoError(err.to_string()))?;
+    Ok(key)
+}
+
+/// Generate a new keypair and wrap its private key with `passphrase`.
+pub async fn create_passphrase_key<B: CryptoBackend>(
+    backend: &B,
+    passphrase: &str,
+    params: Argon2Params,
+) -> Result<PassphraseKey, Error> {
+    if passphrase.is_empty() {
+        return Err(Error::InvalidInput(
+            "The passphrase must not be empty".to_string(),
+        ));
+    }
+
+    let salt: [u8; SALT_LENGTH] = backend.random()?;
+    let mut raw_key = derive_wrapping_key(passphrase, &salt, &params)?;
+    let wrapping_key = backend.import_aes_gcm_key(&raw_key).await;
+    raw_key.zeroize();
+
+    let (private_key, pubkey) = backend.ecdh_generate_keypair(true).await?;
+    let id: [u8; ID_LENGTH] = backend.random()?;
+    let iv: AesIv = backend.random()?;
+    let mut key = PassphraseKey {
+        id: id.to_vec(),
+        pubkey,
+        params,
+        salt: salt.to_vec(),
+        iv: iv.to_vec(),
+        wrapped_private_key: Vec::new(),
+    };
+    key.wrapped_private_key = backend
+        .wrap_ecdh_private_key(&wrapping_key?, &iv, &key.additional_data()?, &private_key)
+        .await?;
+    Ok(key)
+}
+
+/// Unwrap the private key of `key` with `passphrase`. The returned
+/// [UnlockedKey] decrypts like a credential's would.
+pub async fn unlock_passphrase_key<B: CryptoBackend>(
+    backend: &B,
+    key: &PassphraseKey,
+    passphrase: &str,
+) -> Result<UnlockedKey<B>, Error> {
+    let mut raw_key = derive_wrapping_key(passphrase, &key.salt, &key.params)?;
+    let wrapping_key = backend.import_aes_gcm_key(&raw_key).await;
+    raw_key.zeroize();
+
+    let private_key = backend
+        .unwrap_ecdh_private_key(
+            &wrapping_key?,
+            &key.iv,
+            &key.additional_data()?,
+            &key.wrapped_private_key,
+        )
+        .await
+        .map_err(|err| match err {
+            Error::AeadFailure => Error::InvalidInput("Wrong passphrase".to_string()),
+            err => err,
+        })?;
+    Ok(UnlockedKey {
+        credential_id: key.id.clone(),
+        private_key,
+    })
+}
+
+pub async fn wrap_master_key_to_passphrase_key<B: CryptoBackend>(
+    backend: &B,
+    master_key: &MasterKey,
+    key: &PassphraseKey,
+) -> Result<WrappedContentKey, Error> {
+    encrypt_content_key_to_pubkey(backend, &master_key.0, &key.id, &key.pubkey).await
+}
@@ -0,0 +1,159 @@
+//! Recovery of a vault whose credentials are all lost. The master key is
+//! wrapped to one more recipient: a P-256 keypair derived from a random
+//! recovery phrase, which is shown to the user once to be written down or
+//! printed. Only the public key is stored in the vault.
+
+use std::fmt;
+use std::str::FromStr;
+
+use data_encoding::BASE32_NOPAD;
+use hkdf::Hkdf;
+use p256::SecretKey;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::error::Error;
+
+use super::encrypt_content_key_to_pubkey;
+use super::CryptoBackend;
+use super::MasterKey;
+use super::UnlockedKey;
+use super::WrappedContentKey;
+
+/// 160 bits, which is 32 base32 characters.
+const ENTROPY_LENGTH: usize = 20;
+
+/// Characters per group when the phrase is displayed.
+const GROUP_LENGTH: usize = 4;
+
+const HKDF_SALT: &[u8] = b"passwordless-passwords-prf recovery key";
+
+/// How many bytes of the SHA-256 hash of the public key identify the recovery
+/// key among the recipients of a wrapped key, in place of a credential ID.
+const ID_LENGTH: usize = 16;
+
+/// A recovery phrase: random bytes displayed in groups of base32 characters.
+/// Overwritten with zeroes when dropped.
+pub struct RecoveryPhrase {
+    entropy: [u8; ENTROPY_LENGTH],
+}
+
+impl Drop for RecoveryPhrase {
+    fn drop(&mut self) {
+        self.entropy.zeroize();
+    }
+}
+
+impl RecoveryPhrase {
+    pub fn generate<B: CryptoBackend>(backend: &B) -> Result<Self, Error> {
+        Ok(Self {
+            entropy: backend.random()?,
+        })
+    }
+}
+
+impl fmt::Display for RecoveryPhrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encoded = BASE32_NOPAD.encode(&self.entropy);
+        let groups: Vec<&str> = encoded
+            .as_bytes()
+            .chunks(GROUP_LENGTH)
+            .map(|group| std::str::from_utf8(group).expect("Base32 is ASCII"))
+            .collect();
+        write!(f, "{}", groups.join("-"))
+    }
+}
+
+/// Parses a phrase as displayed, ignoring case, spaces and dashes.
+impl FromStr for RecoveryPhrase {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized: String = s
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        let invalid = || {
+            Error::InvalidInput(format!(
+                "A recovery phrase has {} letters and digits",
+                BASE32_NOPAD.encode_len(ENTROPY_LENGTH)
+            ))
+        };
+        let mut decoded = BASE32_NOPAD
+            .decode(normalized.as_bytes())
+            .map_err(|_| invalid())?;
+        let entropy = decoded.as_slice().try_into().map_err(|_| invalid());
+        decoded.zeroize();
+        Ok(Self { entropy: entropy? })
+    }
+}
+
+/// The public half of the recovery key, stored in the vault.
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct RecoveryKey {
+    /// Identifies the recovery key in [WrappedContentKey::credential_id].
+    #[serde(with = "crate::data::base64")]
+    pub id: Vec<u8>,
+    #[serde(with = "crate::data::base64")]
+    pubkey: Vec<u8>,
+}
+
+/// The P-256 private scalar for `phrase`: the first output of HKDF-SHA256
+/// that is a valid scalar, which is almost always the first one.
+fn derive_scalar(phrase: &RecoveryPhrase) -> Result<[u8; 32], Error> {
+    let hkdf = Hkdf::<Sha256>::new(Some(HKDF_SALT), &phrase.entropy);
+    for counter in 0..=u8::MAX {
+        let mut scalar = [0; 32];
+        hkdf.expand(&[counter], &mut scalar)
+            .map_err(|err| Error::CryptoError(err.to_string()))?;
+        if SecretKey::from_slice(&scalar).is_ok() {
+            return Ok(scalar);
+        }
+        scalar.zeroize();
+    }
+    Err(Error::CryptoError(
+        "Failed to derive a recovery key".to_string(),
+    ))
+}
+
+/// Derive the recovery keypair from `phrase`. The private key is returned as
+/// an [UnlockedKey] that decrypts like a credential's would.
+pub async fn derive_recovery_key<B: CryptoBackend>(
+    backend: &B,
+    phrase: &RecoveryPhrase,
+) -> Result<(RecoveryKey, UnlockedKey<B>), Error> {
+    let mut scalar = derive_scalar(phrase)?;
+    let imported = backend.ecdh_import_private_key(&scalar).await;
+    scalar.zeroize();
+    let (private_key, pubkey) = imported?;
+
+    let id = Sha256::digest(&pubkey)[..ID_LENGTH].to_vec();
+    Ok((
+        RecoveryKey {
+            id: id.clone(),
+            pubkey,
+        },
+        UnlockedKey {
+            credential_id: id,
+            private_key,
+        },
+    ))
+}
+
+pub async fn wrap_master_key_to_recovery_key<B: CryptoBackend>(
+    backend: &B,
+    master_key: &MasterKey,
+    recovery_key: &RecoveryKey,
+) -> Result<WrappedContentKey, Error> {
+    encrypt_content_key_to_pubkey(
+        backend,
+        &master_key.0,
+        &recovery_key.id,
+        &recovery_key.pubkey,
+    )
+    .await
+}
@@ -0,0 +1,235 @@
+use ::base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ::base64::Engine;
+use aes_gcm::aead::Aead;
+use aes_gcm::aead::Payload;
+use aes_gcm::Aes256Gcm;
+use aes_gcm::KeyInit;
+use aes_gcm::Nonce;
+use aes_kw::KekAes256;
+use async_trait::async_trait;
+use hkdf::Hkdf;
+use p256::ecdh::diffie_hellman;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::PublicKey;
+use p256::SecretKey;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::error::Error;
+
+use super::CryptoBackend;
+use super::AES_IV_LENGTH;
+
+/// [CryptoBackend] implemented with the RustCrypto crates.
+///
+/// Produces the same formats as [super::webcrypto::WebCrypto], so values
+/// created by one can be read by the other.
+pub struct RustCrypto;
+
+pub struct AesKey([u8; 32]);
+
+impl AesKey {
+    fn try_from_slice(raw: &[u8]) -> Result<Self, Error> {
+        Ok(Self(raw.try_into().map_err(|_| {
+            Error::MalformedRecord("AES keys must be 256 bits".to_string())
+        })?))
+    }
+
+    fn aes_gcm(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.0.into())
+    }
+}
+
+/// The subset of the JWK format that WebCrypto produces and accepts for ECDH
+/// private keys.
+#[derive(Deserialize, Serialize)]
+pub struct EcPrivateJwk {
+    kty: String,
+    crv: String,
+    x: String,
+    y: String,
+    d: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ext: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_ops: Option<Vec<String>>,
+}
+
+impl From<&SecretKey> for EcPrivateJwk {
+    fn from(key: &SecretKey) -> Self {
+        let point = key.public_key().to_encoded_point(false);
+        Self {
+            kty: "EC".to_string(),
+            crv: "P-256".to_string(),
+            x: URL_SAFE_NO_PAD.encode(point.x().expect("Uncompressed point has x coordinate")),
+            y: URL_SAFE_NO_PAD.encode(point.y().expect("Uncompressed point has y coordinate")),
+            d: URL_SAFE_NO_PAD.encode(key.to_bytes()),
+            ext: Some(true),
+            key_ops: Some(vec!["deriveKey".to_string()]),
+        }
+    }
+}
+
+impl TryFrom<EcPrivateJwk> for SecretKey {
+    type Error = Error;
+    fn try_from(jwk: EcPrivateJwk) -> Result<Self, Self::Error> {
+        let invalid = || Error::MalformedRecord("Invalid EC private key JWK".to_string());
+
+        if jwk.kty != "EC" || jwk.crv != "P-256" {
+            return Err(invalid());
+        }
+        let d = URL_SAFE_NO_PAD.decode(&jwk.d).map_err(|_| invalid())?;
+        let key = SecretKey::from_slice(&d).map_err(|_| invalid())?;
+
+        let point = key.public_key().to_encoded_point(false);
+        if point.x().map(|x| URL_SAFE_NO_PAD.encode(x)).as_ref() != Some(&jwk.x)
+            || point.y().map(|y| URL_SAFE_NO_PAD.encode(y)).as_ref() != Some(&jwk.y)
+        {
+            return Err(invalid());
+        }
+
+        Ok(key)
+    }
+}
+
+fn check_iv(iv: &[u8]) -> Result<&Nonce<aes_gcm::aead::consts::U12>, Error> {
+    if iv.len() == AES_IV_LENGTH {
+        Ok(Nonce::from_slice(iv))
+    } else {
+        Err(Error::MalformedRecord(format!(
+            "AES-GCM IV must be {AES_IV_LENGTH} bytes"
+        )))
+    }
+}
+
+#[async_trait(?Send)]
+impl CryptoBackend for RustCrypto {
+    type AesGcmKey = AesKey;
+    type AesKwKey = KekAes256;
+    type EcdhPrivateKey = SecretKey;
+
+    fn random_bytes(&self, dest: &mut [u8]) -> Result<(), Error> {
+        getrandom::getrandom(dest).map_err(|err| Error::CryptoError(err.to_string()))
+    }
+
+    async fn import_aes_gcm_key(&self, raw: &[u8]) -> Result<AesKey, Error> {
+        AesKey::try_from_slice(raw)
+    }
+
+    async fn hkdf_aes_gcm_key(
+        &self,
+        ikm: &[u8],
+        salt: &[u8],
+        info: &[u8],
+    ) -> Result<AesKey, Error> {
+        let mut okm = [0; 32];
+        Hkdf::<Sha256>::new(Some(salt), ikm)
+            .expand(info, &mut okm)
+            .map_err(|err| Error::CryptoError(err.to_string()))?;
+        Ok(AesKey(okm))
+    }
+
+    async fn aes_gcm_encrypt(
+        &self,
+        key: &AesKey,
+        iv: &[u8],
+        additional_data: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        key.aes_gcm()
+            .encrypt(
+                check_iv(iv)?,
+                Payload {
+                    msg: plaintext,
+                    aad: additional_data,
+                },
+            )
+            .map_err(|_| Error::CryptoError("AES-GCM encryption failed".to_string()))
+    }
+
+    async fn aes_gcm_decrypt(
+        &self,
+        key: &AesKey,
+        iv: &[u8],
+        additional_data: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        key.aes_gcm()
+            .decrypt(
+                check_iv(iv)?,
+                Payload {
+                    msg: ciphertext,
+                    aad: additional_data,
+                },
+            )
+            .map_err(|_| Error::AeadFailure)
+    }
+
+    async fn aes_kw_wrap(&self, kek: &KekAes256, key: &[u8]) -> Result<Vec<u8>, Error> {
+        kek.wrap_vec(key)
+            .map_err(|err| Error::CryptoError(err.to_string()))
+    }
+
+    async fn aes_kw_unwrap(&self, kek: &KekAes256, wrapped_key: &[u8]) -> Result<Vec<u8>, Error> {
+        kek.unwrap_vec(wrapped_key).map_err(|_| Error::AeadFailure)
+    }
+
+    async fn ecdh_generate_keypair(
+        &self,
+        _extractable: bool,
+    ) -> Result<(SecretKey, Vec<u8>), Error> {
+        let private_key = loop {
+            let bytes: [u8; 32] = self.random()?;
+            if let Ok(key) = SecretKey::from_slice(&bytes) {
+                break key;
+            }
+        };
+        let pubkey = private_key.public_key().to_encoded_point(false).to_bytes();
+        Ok((private_key, pubkey.into()))
+    }
+
+    async fn ecdh_import_private_key(&self, scalar: &[u8]) -> Result<(SecretKey, Vec<u8>), Error> {
+        let private_key = SecretKey::from_slice(scalar)
+            .map_err(|_| Error::InvalidInput("Invalid P-256 private key".to_string()))?;
+        let pubkey = private_key.public_key().to_encoded_point(false).to_bytes();
+        Ok((private_key, pubkey.into()))
+    }
+
+    async fn ecdh_derive_aes_kw_key(
+        &self,
+        private_key: &SecretKey,
+        peer_public_key: &[u8],
+    ) -> Result<KekAes256, Error> {
+        let peer_public_key = PublicKey::from_sec1_bytes(peer_public_key)
+            .map_err(|_| Error::MalformedRecord("Invalid P-256 public key".to_string()))?;
+        let shared_secret =
+            diffie_hellman(private_key.to_nonzero_scalar(), peer_public_key.as_affine());
+        Ok(KekAes256::new(shared_secret.raw_secret_bytes()))
+    }
+
+    async fn wrap_ecdh_private_key(
+        &self,
+        wrapping_key: &AesKey,
+        iv: &[u8],
+        additional_data: &[u8],
+        private_key: &SecretKey,
+    ) -> Result<Vec<u8>, Error> {
+        let jwk = serde_json::to_vec(&EcPrivateJwk::from(private_key))?;
+        self.aes_gcm_encrypt(wrapping_key, iv, additional_data, &jwk)
+            .await
+    }
+
+    async fn unwrap_ecdh_private_key(
+        &self,
+        wrapping_key: &AesKey,
+        iv: &[u8],
+        additional_data: &[u8],
+        wrapped_private_key: &[u8],
+    ) -> Result<SecretKey, Error> {
+        let jwk = self
+            .aes_gcm_decrypt(wrapping_key, iv, additional_data, wrapped_private_key)
+            .await?;
+        SecretKey::try_from(serde_json::from_slice::<EcPrivateJwk>(&jwk)?)
+    }
+}
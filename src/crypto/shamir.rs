@@ -0,0 +1,125 @@
+//! Shamir's secret sharing over GF(2^8), with the AES reduction polynomial
+//! x^8 + x^4 + x^3 + x + 1. Each byte of the secret is the constant term of
+//! its own random polynomial of degree `threshold - 1`, and share `x` holds
+//! the value of each polynomial at `x`. Field arithmetic avoids lookup tables
+//! and branches on secret data.
+
+use zeroize::Zeroize;
+
+use crate::error::Error;
+
+use super::CryptoBackend;
+
+/// One share of a secret. Overwritten with zeroes when dropped.
+pub struct Share {
+    /// The x coordinate of the share, from 1 to 255.
+    pub index: u8,
+    pub value: Vec<u8>,
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// The multiplicative inverse of `a`, as `a^254`. Only called with nonzero
+/// share indices.
+fn inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut base = a;
+    let mut exp: u8 = 254;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul(result, base);
+        }
+        base = mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Split `secret` into `count` shares, any `threshold` of which can be
+/// combined to recover it.
+pub fn split<B: CryptoBackend>(
+    backend: &B,
+    secret: &[u8],
+    threshold: u8,
+    count: usize,
+) -> Result<Vec<Share>, Error> {
+    if threshold == 0 || usize::from(threshold) > count {
+        return Err(Error::InvalidInput(format!(
+            "The threshold must be between 1 and the number of keys ({count})"
+        )));
+    }
+    let count = u8::try_from(count).map_err(|_| {
+        Error::InvalidInput("A secret can be split into at most 255 shares".to_string())
+    })?;
+
+    // coefficients[i * secret.len() + j] is the coefficient of x^(i + 1) for
+    // byte j of the secret
+    let mut coefficients = vec![0; (usize::from(threshold) - 1) * secret.len()];
+    backend.random_bytes(&mut coefficients)?;
+
+    // Horner's method, from the highest coefficient down to the secret byte
+    let evaluate = |x: u8, j: usize| -> u8 {
+        (0..usize::from(threshold) - 1)
+            .rev()
+            .map(|i| coefficients[i * secret.len() + j])
+            .chain(std::iter::once(secret[j]))
+            .fold(0, |acc, coefficient| mul(acc, x) ^ coefficient)
+    };
+    let shares = (1..=count)
+        .map(|index| Share {
+            index,
+            value: (0..secret.len()).map(|j| evaluate(index, j)).collect(),
+        })
+        .collect();
+    coefficients.zeroize();
+    Ok(shares)
+}
+
+/// Recover the secret from `shares` by Lagrange interpolation at zero. The
+/// result is only the secret if there are at least as many shares as the
+/// threshold they were split with.
+pub fn combine(shares: &[Share]) -> Result<Vec<u8>, Error> {
+    let len = match shares.first() {
+        Some(share) => share.value.len(),
+        None => return Err(Error::InvalidInput("No shares to combine".to_string())),
+    };
+    for (i, share) in shares.iter().enumerate() {
+        if share.index == 0 || share.value.len() != len {
+            return Err(Error::MalformedRecord("Invalid secret share".to_string()));
+        }
+        if shares[..i].iter().any(|other| other.index == share.index) {
+            return Err(Error::MalformedRecord("Duplicate secret share".to_string()));
+        }
+    }
+
+    let mut secret = vec![0; len];
+    for share in shares {
+        // The Lagrange basis polynomial of this share, evaluated at zero.
+        // Subtraction is XOR in GF(2^8), so 0 - x is x.
+        let basis = shares
+            .iter()
+            .filter(|other| other.index != share.index)
+            .fold(1, |acc, other| {
+                mul(acc, mul(other.index, inv(other.index ^ share.index)))
+            });
+        for (secret_byte, share_byte) in secret.iter_mut().zip(&share.value) {
+            *secret_byte ^= mul(basis, *share_byte);
+        }
+    }
+    Ok(secret)
+}
@@ -0,0 +1,291 @@
+use async_trait::async_trait;
+use js_sys::Array;
+use js_sys::ArrayBuffer;
+use js_sys::Object;
+use js_sys::Reflect;
+use js_sys::Uint8Array;
+use js_sys::JSON;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::SecretKey;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::AesGcmParams;
+use web_sys::AesKeyGenParams;
+use web_sys::CryptoKey;
+use web_sys::DomException;
+use web_sys::EcKeyGenParams;
+use web_sys::EcdhKeyDeriveParams;
+use web_sys::HkdfParams;
+
+use crate::error::Error;
+
+use super::rustcrypto::EcPrivateJwk;
+use super::subtle_crypto;
+use super::CryptoBackend;
+use super::AES_SIZE;
+use super::EC_CURVE;
+
+/// [CryptoBackend] implemented with the browser's `SubtleCrypto`.
+pub struct WebCrypto;
+
+fn aes_gcm_params(iv: &[u8], additional_data: &[u8]) -> AesGcmParams {
+    AesGcmParams::new("AES-GCM", &Uint8Array::from(iv))
+        .additional_data(&Uint8Array::from(additional_data))
+        .to_owned()
+}
+
+/// WebCrypto reports failed authenticated decryption as an `OperationError`.
+fn aead_error(err: JsValue) -> Error {
+    match err
+        .dyn_ref::<DomException>()
+        .map(DomException::name)
+        .as_deref()
+    {
+        Some("OperationError") => Error::AeadFailure,
+        _ => Error::from(err),
+    }
+}
+
+async fn export_raw(key: &CryptoKey) -> Result<Vec<u8>, Error> {
+    let exported: ArrayBuffer = JsFuture::from(subtle_crypto()?.export_key("raw", key)?)
+        .await?
+        .into();
+    Ok(Uint8Array::new(&exported).to_vec())
+}
+
+async fn import_ecdh_public_key(raw: &[u8]) -> Result<CryptoKey, Error> {
+    Ok(JsFuture::from(subtle_crypto()?.import_key_with_object(
+        "raw",
+        &Uint8Array::from(raw),
+        &EcKeyGenParams::new("ECDH", EC_CURVE),
+        false,
+        &Array::new(), // CHROME: []   FIREFOX: ["deriveKey"]
+    )?)
+    .await?
+    .into())
+}
+
+#[async_trait(?Send)]
+impl CryptoBackend for WebCrypto {
+    type AesGcmKey = CryptoKey;
+    type AesKwKey = CryptoKey;
+    type EcdhPrivateKey = CryptoKey;
+
+    fn random_bytes(&self, dest: &mut [u8]) -> Result<(), Error> {
+        super::crypto()?.get_random_values_with_u8_array(dest)?;
+        Ok(())
+    }
+
+    async fn import_aes_gcm_key(&self, raw: &[u8]) -> Result<CryptoKey, Error> {
+        Ok(JsFuture::from(subtle_crypto()?.import_key_with_str(
+            "raw",
+            &Uint8Array::from(raw),
+            "AES-GCM",
+            true,
+            &Array::of2(&"encrypt".into(), &"decrypt".into()),
+        )?)
+        .await?
+        .into())
+    }
+
+    async fn hkdf_aes_gcm_key(
+        &self,
+        ikm: &[u8],
+        salt: &[u8],
+        info: &[u8],
+    ) -> Result<CryptoKey, Error> {
+        let subtle = subtle_crypto()?;
+        let base_key: CryptoKey = JsFuture::from(subtle.import_key_with_str(
+            "raw",
+            &Uint8Array::from(ikm),
+            "HKDF",
+            false,
+            &Array::of1(&"deriveKey".into()),
+        )?)
+        .await?
+        .into();
+
+        Ok(JsFuture::from(subtle.derive_key_with_object_and_object(
+            &HkdfParams::new(
+                "HKDF",
+                &"SHA-256".into(),
+                &Uint8Array::from(info),
+                &Uint8Array::from(salt),
+            ),
+            &base_key,
+            &AesKeyGenParams::new("AES-GCM", AES_SIZE),
+            false,
+            &Array::of4(
+                &"encrypt".into(),
+                &"decrypt".into(),
+                &"wrapKey".into(),
+                &"unwrapKey".into(),
+            ),
+        )?)
+        .await?
+        .into())
+    }
+
+    async fn aes_gcm_encrypt(
+        &self,
+        key: &CryptoKey,
+        iv: &[u8],
+        additional_data: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let ciphertext: ArrayBuffer =
+            JsFuture::from(subtle_crypto()?.encrypt_with_object_and_buffer_source(
+                &aes_gcm_params(iv, additional_data),
+                key,
+                &Uint8Array::from(plaintext),
+            )?)
+            .await?
+            .into();
+        Ok(Uint8Array::new(&ciphertext).to_vec())
+    }
+
+    async fn aes_gcm_decrypt(
+        &self,
+        key: &CryptoKey,
+        iv: &[u8],
+        additional_data: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let plaintext: ArrayBuffer =
+            JsFuture::from(subtle_crypto()?.decrypt_with_object_and_buffer_source(
+                &aes_gcm_params(iv, additional_data),
+                key,
+                &Uint8Array::from(ciphertext),
+            )?)
+            .await
+            .map_err(aead_error)?
+            .into();
+        Ok(Uint8Array::new(&plaintext).to_vec())
+    }
+
+    async fn aes_kw_wrap(&self, kek: &CryptoKey, key: &[u8]) -> Result<Vec<u8>, Error> {
+        let key: CryptoKey = self.import_aes_gcm_key(key).await?;
+        let wrapped_key: ArrayBuffer =
+            JsFuture::from(subtle_crypto()?.wrap_key_with_str("raw", &key, kek, "AES-KW")?)
+                .await?
+                .into();
+        Ok(Uint8Array::new(&wrapped_key).to_vec())
+    }
+
+    async fn aes_kw_unwrap(&self, kek: &CryptoKey, wrapped_key: &[u8]) -> Result<Vec<u8>, Error> {
+        let key: CryptoKey = JsFuture::from(
+            subtle_crypto()?.unwrap_key_with_buffer_source_and_str_and_str(
+                "raw",
+                &Uint8Array::from(wrapped_key),
+                kek,
+                "AES-KW",
+                "AES-GCM",
+                true,
+                &Array::of1(&"decrypt".into()),
+            )?,
+        )
+        .await
+        .map_err(aead_error)?
+        .into();
+        export_raw(&key).await
+    }
+
+    async fn ecdh_generate_keypair(
+        &self,
+        extractable: bool,
+    ) -> Result<(CryptoKey, Vec<u8>), Error> {
+        let keypair: Object = JsFuture::from(subtle_crypto()?.generate_key_with_object(
+            &EcKeyGenParams::new("ECDH", EC_CURVE),
+            extractable,
+            &Array::of1(&"deriveKey".into()),
+        )?)
+        .await?
+        .into();
+
+        let pubkey = export_raw(&Reflect::get(&keypair, &"publicKey".into())?.into()).await?;
+        Ok((Reflect::get(&keypair, &"privateKey".into())?.into(), pubkey))
+    }
+
+    /// `SubtleCrypto` cannot import a bare scalar, so the public key is
+    /// computed here to import the key as a JWK.
+    async fn ecdh_import_private_key(&self, scalar: &[u8]) -> Result<(CryptoKey, Vec<u8>), Error> {
+        let secret_key = SecretKey::from_slice(scalar)
+            .map_err(|_| Error::InvalidInput("Invalid P-256 private key".to_string()))?;
+        let pubkey = secret_key.public_key().to_encoded_point(false).to_bytes();
+        let jwk: Object =
+            JSON::parse(&serde_json::to_string(&EcPrivateJwk::from(&secret_key))?)?.into();
+
+        let private_key: CryptoKey = JsFuture::from(subtle_crypto()?.import_key_with_object(
+            "jwk",
+            &jwk,
+            &EcKeyGenParams::new("ECDH", EC_CURVE),
+            false,
+            &Array::of1(&"deriveKey".into()),
+        )?)
+        .await?
+        .into();
+        Ok((private_key, pubkey.into()))
+    }
+
+    async fn ecdh_derive_aes_kw_key(
+        &self,
+        private_key: &CryptoKey,
+        peer_public_key: &[u8],
+    ) -> Result<CryptoKey, Error> {
+        let peer_public_key: CryptoKey = import_ecdh_public_key(peer_public_key).await?;
+        Ok(
+            JsFuture::from(subtle_crypto()?.derive_key_with_object_and_object(
+                &EcdhKeyDeriveParams::new("ECDH", &peer_public_key),
+                private_key,
+                &AesKeyGenParams::new("AES-KW", AES_SIZE),
+                false,
+                &Array::of2(&"wrapKey".into(), &"unwrapKey".into()),
+            )?)
+            .await?
+            .into(),
+        )
+    }
+
+    async fn wrap_ecdh_private_key(
+        &self,
+        wrapping_key: &CryptoKey,
+        iv: &[u8],
+        additional_data: &[u8],
+        private_key: &CryptoKey,
+    ) -> Result<Vec<u8>, Error> {
+        let wrapped_private_key: ArrayBuffer =
+            JsFuture::from(subtle_crypto()?.wrap_key_with_object(
+                "jwk",
+                private_key,
+                wrapping_key,
+                &aes_gcm_params(iv, additional_data),
+            )?)
+            .await?
+            .into();
+        Ok(Uint8Array::new(&wrapped_private_key).to_vec())
+    }
+
+    async fn unwrap_ecdh_private_key(
+        &self,
+        wrapping_key: &CryptoKey,
+        iv: &[u8],
+        additional_data: &[u8],
+        wrapped_private_key: &[u8],
+    ) -> Result<CryptoKey, Error> {
+        Ok(JsFuture::from(
+            subtle_crypto()?.unwrap_key_with_buffer_source_and_object_and_object(
+                "jwk",
+                &Uint8Array::from(wrapped_private_key),
+                wrapping_key,
+                &aes_gcm_params(iv, additional_data),
+                &EcKeyGenParams::new("ECDH", EC_CURVE),
+                false,
+                &Array::of1(&"deriveKey".into()),
+            )?,
+        )
+        .await
+        .map_err(aead_error)?
+        .into())
+    }
+}
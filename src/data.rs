@@ -2,12 +2,17 @@ use ::base64::Engine;
 use js_sys::Uint8Array;
 use serde::Deserialize;
 use serde::Serialize;
-use wasm_bindgen::JsValue;
 use web_sys::PublicKeyCredentialDescriptor;
 use web_sys::PublicKeyCredentialType;
 
 use crate::crypto::gen_random;
+use crate::error::Error;
 
+pub mod contact;
+pub mod entry;
+pub mod migration;
+pub mod revision;
+pub mod totp;
 pub mod vault;
 
 pub mod base64 {
@@ -34,7 +39,7 @@ pub mod base64 {
     impl TryFrom<Base64Wrapper> for Vec<u8> {
         type Error = base64::DecodeError;
         fn try_from(v: Base64Wrapper) -> Result<Self, Self::Error> {
-            Ok(::base64::engine::general_purpose::STANDARD.decode(v.base64)?)
+            ::base64::engine::general_purpose::STANDARD.decode(v.base64)
         }
     }
 
@@ -45,7 +50,7 @@ pub mod base64 {
         <T as TryFrom<Base64Wrapper>>::Error: std::fmt::Display,
     {
         let b64: Base64Wrapper = Deserialize::deserialize(d)?;
-        T::try_from(b64).map_err(|e| serde::de::Error::custom(e))
+        T::try_from(b64).map_err(serde::de::Error::custom)
     }
 
     pub fn serialize<'t, S, T>(v: &'t T, s: S) -> Result<S::Ok, S::Error>
@@ -63,7 +68,7 @@ pub mod base64 {
 pub struct UserHandle(#[serde(with = "crate::data::base64")] Vec<u8>);
 
 impl UserHandle {
-    pub async fn generate() -> Result<Self, JsValue> {
+    pub async fn generate() -> Result<Self, Error> {
         Ok(Self(Vec::from(gen_random::<64>()?)))
     }
 
@@ -71,6 +76,11 @@ impl UserHandle {
         let Self(v) = self;
         Uint8Array::from(v.as_slice())
     }
+
+    pub fn b64url(&self) -> String {
+        let Self(v) = self;
+        ::base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(v)
+    }
 }
 
 #[derive(Clone, PartialEq)]
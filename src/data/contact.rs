@@ -0,0 +1,71 @@
+//! Other users whose public identities have been imported, and the entries
+//! shared with them.
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::crypto::identity::PublicIdentity;
+use crate::crypto::identity::SignedIdentity;
+use crate::crypto::EncryptedContent;
+use crate::error::Error;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Contact {
+    /// Verified when imported.
+    pub identity: SignedIdentity,
+
+    /// A copy of each entry shared with this contact, encrypted to their keys
+    /// and bound to their vault. Keyed like
+    /// [VaultConfig::contents](super::vault::VaultConfig::contents).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub shared: BTreeMap<String, Rc<EncryptedContent>>,
+}
+
+impl Contact {
+    pub fn identity(&self) -> Result<PublicIdentity, Error> {
+        self.identity.identity()
+    }
+}
+
+/// What a [SharePackage] signs: the entries shared by one user with another.
+/// Both are named by their [PublicIdentity::signer_id] rather than their user
+/// handle, which a package passed around has no need to reveal.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SharePayload {
+    pub sender: String,
+    pub recipient: String,
+    pub entries: Vec<Rc<EncryptedContent>>,
+}
+
+/// The entries shared with a contact, for them to import into their vault.
+/// Signed by the sender, who must be one of the recipient's contacts.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SharePackage {
+    #[serde(with = "crate::data::base64")]
+    pub payload: Vec<u8>,
+    #[serde(with = "crate::data::base64")]
+    pub signature: Vec<u8>,
+}
+
+impl SharePackage {
+    /// The payload, if it is signed by `sender`.
+    pub fn verify(&self, sender: &PublicIdentity) -> Result<SharePayload, Error> {
+        sender.verify(&self.payload, &self.signature)?;
+        let payload: SharePayload = serde_json::from_slice(&self.payload)?;
+        if payload.sender != sender.signer_id() {
+            return Err(Error::MalformedRecord(
+                "The entries are from someone else".to_string(),
+            ));
+        }
+        Ok(payload)
+    }
+
+    /// The signer ID of the sender named in the payload, before the
+    /// signature is checked.
+    pub fn sender(&self) -> Result<String, Error> {
+        Ok(serde_json::from_slice::<SharePayload>(&self.payload)?.sender)
+    }
+}
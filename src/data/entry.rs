@@ -0,0 +1,96 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::data::totp::Totp;
+use crate::error::Error;
+
+/// The plaintext of a vault entry, serialized as JSON inside the encrypted
+/// payload. The `type` field tells the kinds of entry apart.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Entry {
+    Login(Login),
+    Text { text: String },
+    File(Attachment),
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Login {
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub urls: Vec<String>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp: Option<Totp>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct CustomField {
+    pub name: String,
+    pub value: String,
+
+    /// Whether the value should be masked until revealed, like a password.
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+/// An uploaded file. The MIME type and original filename are kept with the
+/// contents, so the file can be downloaded again as it was.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Attachment {
+    pub filename: String,
+    pub mime_type: String,
+
+    #[serde(with = "crate::data::base64")]
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    /// Fails with [Error::InvalidInput] if `data` is larger than `max_size`
+    /// bytes. An empty `mime_type` defaults to `application/octet-stream`.
+    pub fn new(
+        filename: String,
+        mime_type: String,
+        data: Vec<u8>,
+        max_size: usize,
+    ) -> Result<Self, Error> {
+        if data.len() > max_size {
+            return Err(Error::InvalidInput(format!(
+                "{filename} is {} bytes, but at most {max_size} bytes can be stored",
+                data.len(),
+            )));
+        }
+        let mime_type = if mime_type.is_empty() {
+            "application/octet-stream".to_string()
+        } else {
+            mime_type
+        };
+        Ok(Self {
+            filename,
+            mime_type,
+            data,
+        })
+    }
+}
+
+impl Entry {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Decode a decrypted payload. Entries from before the entry types were
+    /// introduced are plain UTF-8 text, and decode as [Entry::Text].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        serde_json::from_slice(bytes).or_else(|_| {
+            String::from_utf8(bytes.to_vec())
+                .map(|text| Self::Text { text })
+                .map_err(|_| Error::MalformedRecord("Content is not valid UTF-8".to_string()))
+        })
+    }
+}
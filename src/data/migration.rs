@@ -0,0 +1,135 @@
+//! Upgrades stored vault configs from older format versions.
+//!
+//! A stored config is first parsed as untyped JSON, so that any known version
+//! can be read regardless of how its layout differs from [VaultConfig]. Each
+//! step in [MIGRATIONS] upgrades the JSON by exactly one version, and the
+//! steps are applied in order until the config is at [CURRENT_VERSION].
+
+use serde_json::Value;
+
+use crate::error::Error;
+
+use super::vault::VaultConfig;
+
+/// The vault format version written by this build.
+pub const CURRENT_VERSION: u32 = 11;
+
+/// The oldest vault format version this build can upgrade from.
+pub const OLDEST_VERSION: u32 = 2;
+
+type Migration = fn(&mut Value) -> Result<(), Error>;
+
+/// Upgrade steps, each one from the version it is listed with to the next.
+const MIGRATIONS: &[(u32, Migration)] = &[
+    (2, v2_to_v3),
+    (3, v3_to_v4),
+    (4, v4_to_v5),
+    (5, v5_to_v6),
+    (6, v6_to_v7),
+    (7, v7_to_v8),
+    (8, v8_to_v9),
+    (9, v9_to_v10),
+    (10, v10_to_v11),
+];
+
+/// Format version 3 adds an optional vault master key. Version 2 entries are
+/// still readable as they are, and are re-encrypted under the master key once
+/// one is created.
+fn v2_to_v3(_config: &mut Value) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Format version 4 adds optional encrypted entry names. Builds that do not
+/// know the name index would drop it, so they must not open such a vault.
+fn v3_to_v4(_config: &mut Value) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Format version 5 adds entry and credential revisions with tombstones.
+/// Builds that do not know them would drop the tombstones, and deleted
+/// entries would come back on the next merge.
+fn v4_to_v5(_config: &mut Value) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Format version 6 adds an optional recovery key with its revision. Builds
+/// that do not know it would drop the key while the master key stays wrapped
+/// to it, so they must not open such a vault.
+fn v5_to_v6(_config: &mut Value) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Format version 7 adds an optional passphrase key with its revision. Builds
+/// that do not know it would drop the key, and a removed passphrase could
+/// come back on the next merge.
+fn v6_to_v7(_config: &mut Value) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Format version 8 adds entries whose content key is split into shares.
+/// Builds that do not know them cannot parse such an entry, and should report
+/// that the vault is newer rather than malformed.
+fn v7_to_v8(_config: &mut Value) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Format version 9 adds contacts and their revisions. Builds that do not
+/// know them would drop the contacts along with what was shared with them.
+fn v8_to_v9(_config: &mut Value) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Format version 10 adds the identity secret that the signing key is
+/// derived from. Builds that do not know it would sign with a key derived
+/// from the master key instead, which contacts reject once the master key
+/// has been replaced.
+fn v9_to_v10(_config: &mut Value) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Format version 11 adds the padding setting with its revision. Builds that
+/// do not know it would drop the setting, and store new entries unpadded.
+fn v10_to_v11(_config: &mut Value) -> Result<(), Error> {
+    Ok(())
+}
+
+fn version(config: &Value) -> Result<u32, Error> {
+    config
+        .get("v")
+        .and_then(Value::as_u64)
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or_else(|| Error::MalformedRecord("Vault config has no format version".to_string()))
+}
+
+/// Upgrade `config` to [CURRENT_VERSION].
+pub fn migrate(mut config: Value) -> Result<Value, Error> {
+    let mut v = version(&config)?;
+
+    if v > CURRENT_VERSION {
+        return Err(Error::NewerVersion(v));
+    }
+    if v < OLDEST_VERSION {
+        return Err(Error::MalformedRecord(format!(
+            "Unknown vault format version: {v}"
+        )));
+    }
+
+    while v < CURRENT_VERSION {
+        let (_, migration) = MIGRATIONS
+            .iter()
+            .find(|(from, _)| *from == v)
+            .ok_or_else(|| {
+                Error::MalformedRecord(format!("No migration from vault format version {v}"))
+            })?;
+        migration(&mut config)?;
+        v += 1;
+        config["v"] = v.into();
+    }
+
+    Ok(config)
+}
+
+/// Parse a stored vault config of any supported format version.
+pub fn parse_vault_config(s: &str) -> Result<VaultConfig, Error> {
+    Ok(serde_json::from_value(migrate(serde_json::from_str(s)?)?)?)
+}
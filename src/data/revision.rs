@@ -0,0 +1,148 @@
+//! Revision metadata for merging copies of a vault from several devices.
+//!
+//! Every entry, credential and contact has a [Revision] that is bumped
+//! whenever it is changed, and deleting one leaves a tombstone revision
+//! behind. Two configs are merged by keeping, for each of them, the state with
+//! the greater revision. This makes the merge commutative, associative and
+//! idempotent, so copies converge no matter in which order they are merged.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::Error;
+
+use super::CredentialId;
+
+/// Ordered by counter first, so that a change supersedes every revision it
+/// was made on top of. Concurrent changes get the same counter, and the
+/// random tag then picks one of them consistently on every device.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
+pub struct Revision {
+    pub counter: u64,
+    pub tag: u64,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deleted: bool,
+}
+
+impl Revision {
+    /// The revision after this one. Also used for entries and credentials
+    /// with no revision yet, from before revisions were recorded.
+    pub fn next(&self, deleted: bool) -> Result<Self, Error> {
+        let mut tag = [0; 8];
+        getrandom::getrandom(&mut tag).map_err(|err| Error::CryptoError(err.to_string()))?;
+        Ok(Self {
+            counter: self.counter + 1,
+            tag: u64::from_le_bytes(tag),
+            deleted,
+        })
+    }
+}
+
+/// The revisions of the entries, credentials and contacts of a vault,
+/// including tombstones for deleted ones. Entries are keyed as in
+/// [VaultConfig::contents](super::vault::VaultConfig::contents), credentials
+/// by their base64url credential ID, and contacts by their base64url user
+/// handle. The master key, the recovery key and the passphrase key have a
+/// revision too, bumped whenever they are replaced or removed, and so does the
+/// padding setting whenever it is changed.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Revisions {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub entries: BTreeMap<String, Revision>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub credentials: BTreeMap<String, Revision>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub contacts: BTreeMap<String, Revision>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub master_key: Option<Revision>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_key: Option<Revision>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase_key: Option<Revision>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub padding: Option<Revision>,
+}
+
+impl Revisions {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+            && self.credentials.is_empty()
+            && self.contacts.is_empty()
+            && self.master_key.is_none()
+            && self.recovery_key.is_none()
+            && self.passphrase_key.is_none()
+            && self.padding.is_none()
+    }
+
+    pub fn entry(&self, key: &str) -> Revision {
+        self.entries.get(key).copied().unwrap_or_default()
+    }
+
+    pub fn credential(&self, cred_id: &CredentialId) -> Revision {
+        self.credentials
+            .get(&cred_id.b64url())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Record a change to, or with `deleted` the deletion of, an entry.
+    pub fn bump_entry(&mut self, key: &str, deleted: bool) -> Result<(), Error> {
+        let next = self.entry(key).next(deleted)?;
+        self.entries.insert(key.to_string(), next);
+        Ok(())
+    }
+
+    /// Record a change to, or with `deleted` the deletion of, a credential.
+    pub fn bump_credential(&mut self, cred_id: &CredentialId, deleted: bool) -> Result<(), Error> {
+        let next = self.credential(cred_id).next(deleted)?;
+        self.credentials.insert(cred_id.b64url(), next);
+        Ok(())
+    }
+
+    pub fn contact(&self, key: &str) -> Revision {
+        self.contacts.get(key).copied().unwrap_or_default()
+    }
+
+    /// Record a change to, or with `deleted` the deletion of, a contact.
+    pub fn bump_contact(&mut self, key: &str, deleted: bool) -> Result<(), Error> {
+        let next = self.contact(key).next(deleted)?;
+        self.contacts.insert(key.to_string(), next);
+        Ok(())
+    }
+
+    /// Record that the master key was replaced with a new one.
+    pub fn bump_master_key(&mut self) -> Result<(), Error> {
+        self.master_key = Some(self.master_key.unwrap_or_default().next(false)?);
+        Ok(())
+    }
+
+    /// Record that a recovery key was set up, replacing any previous one.
+    pub fn bump_recovery_key(&mut self) -> Result<(), Error> {
+        self.recovery_key = Some(self.recovery_key.unwrap_or_default().next(false)?);
+        Ok(())
+    }
+
+    /// Record that a passphrase was set, replacing any previous one, or with
+    /// `deleted` that it was removed.
+    pub fn bump_passphrase_key(&mut self, deleted: bool) -> Result<(), Error> {
+        self.passphrase_key = Some(self.passphrase_key.unwrap_or_default().next(deleted)?);
+        Ok(())
+    }
+
+    /// Record that the padding setting was changed.
+    pub fn bump_padding(&mut self) -> Result<(), Error> {
+        self.padding = Some(self.padding.unwrap_or_default().next(false)?);
+        Ok(())
+    }
+}
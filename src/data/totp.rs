@@ -0,0 +1,272 @@
+//! Time-based one-time passwords as specified in [RFC 6238], with secrets
+//! imported from `otpauth://` URIs.
+//!
+//! [RFC 6238]: https://www.rfc-editor.org/rfc/rfc6238
+
+use std::fmt::Display;
+
+use data_encoding::BASE32_NOPAD;
+use hmac::digest::KeyInit;
+use hmac::Hmac;
+use hmac::Mac;
+use percent_encoding::percent_decode_str;
+use percent_encoding::utf8_percent_encode;
+use percent_encoding::NON_ALPHANUMERIC;
+use serde::Deserialize;
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::Sha256;
+use sha2::Sha512;
+
+use crate::error::Error;
+
+const DEFAULT_DIGITS: u32 = 6;
+const DEFAULT_PERIOD: u64 = 30;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Algorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Sha1 => "SHA1",
+            Self::Sha256 => "SHA256",
+            Self::Sha512 => "SHA512",
+        }
+    }
+
+    fn parse(name: &str) -> Result<Self, Error> {
+        match name.to_ascii_uppercase().as_str() {
+            "SHA1" => Ok(Self::Sha1),
+            "SHA256" => Ok(Self::Sha256),
+            "SHA512" => Ok(Self::Sha512),
+            _ => Err(malformed(format!("Unsupported TOTP algorithm: {name}"))),
+        }
+    }
+
+    fn hmac(&self, key: &[u8], message: &[u8]) -> Vec<u8> {
+        fn hmac<M: Mac + KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+            let mut mac =
+                <M as KeyInit>::new_from_slice(key).expect("HMAC accepts keys of any length");
+            mac.update(message);
+            mac.finalize().into_bytes().to_vec()
+        }
+        match self {
+            Self::Sha1 => hmac::<Hmac<Sha1>>(key, message),
+            Self::Sha256 => hmac::<Hmac<Sha256>>(key, message),
+            Self::Sha512 => hmac::<Hmac<Sha512>>(key, message),
+        }
+    }
+}
+
+/// A TOTP generator. Serializes as its `otpauth://` URI.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Totp {
+    secret: Vec<u8>,
+    pub algorithm: Algorithm,
+    pub digits: u32,
+    pub period: u64,
+    pub issuer: Option<String>,
+    pub account: Option<String>,
+}
+
+fn malformed(msg: impl Into<String>) -> Error {
+    Error::MalformedRecord(msg.into())
+}
+
+fn decode_secret(secret: &str) -> Result<Vec<u8>, Error> {
+    let normalized: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '=')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    match BASE32_NOPAD.decode(normalized.as_bytes()) {
+        Ok(secret) if !secret.is_empty() => Ok(secret),
+        _ => Err(malformed("TOTP secret is not valid base32")),
+    }
+}
+
+fn decode_component(s: &str) -> Result<String, Error> {
+    percent_decode_str(&s.replace('+', " "))
+        .decode_utf8()
+        .map(|s| s.into_owned())
+        .map_err(|_| malformed("otpauth URI is not valid UTF-8"))
+}
+
+impl Totp {
+    pub fn new(
+        secret: Vec<u8>,
+        algorithm: Algorithm,
+        digits: u32,
+        period: u64,
+    ) -> Result<Self, Error> {
+        if secret.is_empty() {
+            return Err(malformed("TOTP secret is empty"));
+        }
+        if digits != 6 && digits != 8 {
+            return Err(malformed(format!(
+                "Unsupported number of TOTP digits: {digits}"
+            )));
+        }
+        if period == 0 {
+            return Err(malformed("TOTP period must be positive"));
+        }
+        Ok(Self {
+            secret,
+            algorithm,
+            digits,
+            period,
+            issuer: None,
+            account: None,
+        })
+    }
+
+    /// Parse either an `otpauth://totp/` URI or a bare base32 secret with the
+    /// default parameters.
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let s = s.trim();
+        if s.to_ascii_lowercase().starts_with("otpauth://") {
+            Self::from_uri(s)
+        } else {
+            Self::new(
+                decode_secret(s)?,
+                Algorithm::default(),
+                DEFAULT_DIGITS,
+                DEFAULT_PERIOD,
+            )
+        }
+    }
+
+    /// Parse an `otpauth://totp/` URI in the [Key Uri Format] used by most
+    /// authenticator apps.
+    ///
+    /// [Key Uri Format]: https://github.com/google/google-authenticator/wiki/Key-Uri-Format
+    pub fn from_uri(uri: &str) -> Result<Self, Error> {
+        let rest = uri
+            .get(..10)
+            .filter(|scheme| scheme.eq_ignore_ascii_case("otpauth://"))
+            .map(|_| &uri[10..])
+            .ok_or_else(|| malformed("Not an otpauth URI"))?;
+        let (kind, rest) = rest
+            .split_once('/')
+            .ok_or_else(|| malformed("otpauth URI has no label"))?;
+        if !kind.eq_ignore_ascii_case("totp") {
+            return Err(malformed(format!("Unsupported OTP type: {kind}")));
+        }
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let label = decode_component(label)?;
+
+        let mut secret = None;
+        let mut algorithm = Algorithm::default();
+        let mut digits = DEFAULT_DIGITS;
+        let mut period = DEFAULT_PERIOD;
+        let mut issuer = None;
+
+        for param in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = decode_component(value)?;
+            match key.to_ascii_lowercase().as_str() {
+                "secret" => secret = Some(decode_secret(&value)?),
+                "algorithm" => algorithm = Algorithm::parse(&value)?,
+                "digits" => {
+                    digits = value
+                        .parse()
+                        .map_err(|_| malformed("TOTP digits is not a number"))?
+                }
+                "period" => {
+                    period = value
+                        .parse()
+                        .map_err(|_| malformed("TOTP period is not a number"))?
+                }
+                "issuer" => issuer = Some(value),
+                _ => {}
+            }
+        }
+
+        let (label_issuer, account) = match label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer.trim().to_string()), account.trim()),
+            None => (None, label.trim()),
+        };
+
+        let mut totp = Self::new(
+            secret.ok_or_else(|| malformed("otpauth URI has no secret"))?,
+            algorithm,
+            digits,
+            period,
+        )?;
+        totp.issuer = issuer.or(label_issuer).filter(|s| !s.is_empty());
+        totp.account = Some(account.to_string()).filter(|s| !s.is_empty());
+        Ok(totp)
+    }
+
+    pub fn to_uri(&self) -> String {
+        let encode = |s: &str| utf8_percent_encode(s, NON_ALPHANUMERIC).to_string();
+        let label = match (&self.issuer, &self.account) {
+            (Some(issuer), Some(account)) => format!("{}:{}", encode(issuer), encode(account)),
+            (Some(issuer), None) => encode(issuer),
+            (None, Some(account)) => encode(account),
+            (None, None) => String::new(),
+        };
+        let mut uri = format!(
+            "otpauth://totp/{}?secret={}&algorithm={}&digits={}&period={}",
+            label,
+            BASE32_NOPAD.encode(&self.secret),
+            self.algorithm.name(),
+            self.digits,
+            self.period,
+        );
+        if let Some(issuer) = &self.issuer {
+            uri.push_str("&issuer=");
+            uri.push_str(&encode(issuer));
+        }
+        uri
+    }
+
+    /// The code valid at `unix_time` seconds since the Unix epoch.
+    pub fn generate(&self, unix_time: u64) -> String {
+        let counter = unix_time / self.period;
+        let hash = self.algorithm.hmac(&self.secret, &counter.to_be_bytes());
+
+        // Dynamic truncation, RFC 4226 section 5.3
+        let offset = usize::from(hash[hash.len() - 1] & 0x0f);
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+
+        let code = binary % 10_u32.pow(self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+
+    /// Seconds until the code generated at `unix_time` expires.
+    pub fn remaining(&self, unix_time: u64) -> u64 {
+        self.period - unix_time % self.period
+    }
+}
+
+impl TryFrom<String> for Totp {
+    type Error = Error;
+    fn try_from(uri: String) -> Result<Self, Self::Error> {
+        Self::from_uri(&uri)
+    }
+}
+
+impl From<Totp> for String {
+    fn from(totp: Totp) -> Self {
+        totp.to_uri()
+    }
+}
+
+impl Display for Totp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_uri())
+    }
+}
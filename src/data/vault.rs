@@ -1,17 +1,62 @@
+use ::base64::Engine;
 use js_sys::Array;
 use serde::Deserialize;
 use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::rc::Rc;
-use wasm_bindgen::JsValue;
 use web_sys::PublicKeyCredentialDescriptor;
 use web_sys::PublicKeyCredentialUserEntity;
 
-use crate::crypto::encrypt;
+use crate::crypto::create_master_key;
+use crate::crypto::decrypt_with;
+use crate::crypto::decrypt_with_master_key;
+use crate::crypto::encrypt_with;
+use crate::crypto::encrypt_with_master_key;
+use crate::crypto::encrypt_with_threshold;
+use crate::crypto::identity::decrypt_identity_secret;
+use crate::crypto::identity::encrypt_identity_secret;
+use crate::crypto::identity::encrypt_to_identity;
+use crate::crypto::identity::sign;
+use crate::crypto::identity::signer_id;
+use crate::crypto::identity::sync_token;
+use crate::crypto::identity::IdentitySecret;
+use crate::crypto::identity::PublicIdentity;
+use crate::crypto::identity::SignedIdentity;
+use crate::crypto::passphrase::create_passphrase_key;
+use crate::crypto::passphrase::unlock_passphrase_key;
+use crate::crypto::passphrase::wrap_master_key_to_passphrase_key;
+use crate::crypto::passphrase::Argon2Params;
+use crate::crypto::passphrase::PassphraseKey;
+use crate::crypto::recovery::derive_recovery_key;
+use crate::crypto::recovery::wrap_master_key_to_recovery_key;
+use crate::crypto::recovery::RecoveryKey;
+use crate::crypto::recovery::RecoveryPhrase;
+use crate::crypto::unwrap_master_key;
+use crate::crypto::unwrap_private_key;
+use crate::crypto::wrap_master_key_to_recipient;
+use crate::crypto::ContentKey;
+use crate::crypto::CryptoBackend;
 use crate::crypto::EncryptedContent;
+use crate::crypto::EncryptedContentAdditionalData;
+use crate::crypto::MasterKey;
+use crate::crypto::Padding;
+use crate::crypto::UnlockedKey;
+use crate::crypto::WebCrypto;
+use crate::crypto::WrappedContentKey;
 use crate::crypto::WrappedKeypair;
-use crate::error::JsOrSerdeError;
+use crate::crypto::WrappedMasterKey;
+use crate::error::Error;
+use crate::storage::StoredValue;
 
+use super::contact::Contact;
+use super::contact::SharePackage;
+use super::contact::SharePayload;
+use super::migration;
+use super::revision::Revision;
+use super::revision::Revisions;
 use super::CredentialId;
 use super::UserHandle;
 
@@ -22,17 +67,118 @@ pub struct VaultConfig {
 
     pub user: Rc<UserConfig>,
     pub contents: HashMap<String, Rc<EncryptedContent>>,
+
+    /// Absent in vault format version 2, where every content key is wrapped
+    /// to each credential individually.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub master_key: Option<WrappedMasterKey>,
+
+    /// Present if entry names are encrypted. The keys of [Self::contents] are
+    /// then random entry IDs, and this [NameIndex] maps them to the names.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_index: Option<EncryptedContent>,
+
+    #[serde(default, skip_serializing_if = "Revisions::is_empty")]
+    pub revisions: Revisions,
+
+    /// How to pad entries encrypted from now on. Entries already encrypted
+    /// keep the padding they were encrypted with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub padding: Option<Padding>,
+
+    /// Present if a recovery phrase is set up. The master key is then also
+    /// wrapped to this key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_key: Option<RecoveryKey>,
+
+    /// Present if a passphrase is set, for browsers without the PRF
+    /// extension. The master key is then also wrapped to this key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase_key: Option<PassphraseKey>,
+
+    /// Other users to share entries with, keyed by their base64url user
+    /// handle.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub contacts: BTreeMap<String, Rc<Contact>>,
+
+    /// The [IdentitySecret] of this vault, encrypted under the master key.
+    /// Absent if the master key is from before identity secrets were stored,
+    /// in which case the master key is the secret until it is replaced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity_secret: Option<EncryptedContent>,
+}
+
+/// The names of the entries in a vault with encrypted names, keyed by entry ID.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct NameIndex {
+    names: BTreeMap<String, String>,
+}
+
+impl NameIndex {
+    pub fn name(&self, id: &str) -> Option<&str> {
+        self.names.get(id).map(String::as_str)
+    }
+
+    pub fn contains_name(&self, name: &str) -> bool {
+        self.names.values().any(|n| n == name)
+    }
+
+    /// The entry ID and name of each entry.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.names
+            .iter()
+            .map(|(id, name)| (id.as_str(), name.as_str()))
+    }
+}
+
+/// The name under which the [NameIndex] is bound to the vault in its
+/// associated data. Not a valid entry ID, so it cannot collide with one.
+const NAME_INDEX_AD_NAME: &str = "$name_index";
+
+/// The name the [VaultConfig::identity_secret] is bound to in its associated
+/// data, like [NAME_INDEX_AD_NAME].
+const IDENTITY_SECRET_AD_NAME: &str = "$identity_secret";
+
+fn generate_entry_id<B: CryptoBackend>(backend: &B) -> Result<String, Error> {
+    let id: [u8; 16] = backend.random()?;
+    Ok(::base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(id))
+}
+
+impl StoredValue for VaultConfig {
+    fn parse(s: &str) -> Result<Self, Error> {
+        migration::parse_vault_config(s)
+    }
 }
 
 impl VaultConfig {
-    pub async fn new(username: String) -> Result<Self, JsValue> {
+    pub async fn new(username: String) -> Result<Self, Error> {
         Ok(Self {
-            version: 2,
+            version: migration::CURRENT_VERSION,
             user: Rc::new(UserConfig::new(username).await?),
             contents: HashMap::new(),
+            master_key: None,
+            name_index: None,
+            revisions: Revisions::default(),
+            padding: None,
+            recovery_key: None,
+            passphrase_key: None,
+            contacts: BTreeMap::new(),
+            identity_secret: None,
         })
     }
 
+    /// The associated data binding an entry's ciphertext to its name in this vault.
+    pub fn content_additional_data(&self, name: &str) -> EncryptedContentAdditionalData {
+        EncryptedContentAdditionalData::new(name, &self.user.user_handle).with_padding(self.padding)
+    }
+
+    /// Set how to pad entries encrypted from now on.
+    pub fn set_padding(&mut self, padding: Option<Padding>) -> Result<&mut Self, Error> {
+        self.padding = padding;
+        self.revisions.bump_padding()?;
+        Ok(self)
+    }
+
     pub fn get_credential_nickname(&self, cred_id: &CredentialId) -> Option<&str> {
         self.user
             .keypairs
@@ -44,47 +190,1742 @@ impl VaultConfig {
                     .map(|ad| ad.credential_id() == *cred_id)
                     .unwrap_or(false)
             })
-            .and_then(|keypair| keypair.nickname.as_ref().map(|s| s.as_str()))
+            .and_then(|keypair| keypair.nickname.as_deref())
     }
 
     pub fn rename_credential(
         &mut self,
         cred_id: &CredentialId,
         name: String,
-    ) -> Result<&mut Self, JsOrSerdeError> {
-        for mut keypair in Rc::make_mut(&mut Rc::make_mut(&mut self.user).keypairs).iter_mut() {
+    ) -> Result<&mut Self, Error> {
+        for keypair in Rc::make_mut(&mut Rc::make_mut(&mut self.user).keypairs).iter_mut() {
             if keypair.additional_data()?.credential_id() == *cred_id {
-                Rc::make_mut(&mut keypair).nickname = Some(name);
+                Rc::make_mut(keypair).nickname = Some(name);
+                self.revisions.bump_credential(cred_id, false)?;
                 return Ok(self);
             }
         }
-        Err(JsOrSerdeError::JsError("Credential not found".into()))
+        Err(Error::InvalidInput("No such credential".to_string()))
     }
 
-    pub fn delete_credential(&mut self, cred_id: &CredentialId) -> &mut Self {
+    /// Delete a credential, along with its keys to the master key and to
+    /// entries. Fails if an entry that needs several keys would be left with
+    /// fewer keys than it needs.
+    pub fn delete_credential(&mut self, cred_id: &CredentialId) -> Result<&mut Self, Error> {
+        let is_other_credential =
+            |wkk: &WrappedContentKey| CredentialId::from(wkk.credential_id.clone()) != *cred_id;
+
+        let too_few_shares = self.contents.values().any(|content| match &content.key {
+            ContentKey::Threshold { threshold, shares } => {
+                shares.iter().any(|share| !is_other_credential(&share.key))
+                    && shares
+                        .iter()
+                        .filter(|share| is_other_credential(&share.key))
+                        .count()
+                        < usize::from(*threshold)
+            }
+            ContentKey::MasterKey { .. } | ContentKey::Recipients { .. } => false,
+        });
+        if too_few_shares {
+            return Err(Error::InvalidInput(
+                "Deleting this key would leave an entry that needs several keys with too few of them"
+                    .to_string(),
+            ));
+        }
+
         Rc::make_mut(&mut Rc::make_mut(&mut self.user).keypairs).retain(|wkp| {
             !wkp.additional_data()
-                .map_or(false, |ad| ad.credential_id() == *cred_id)
+                .is_ok_and(|ad| ad.credential_id() == *cred_id)
         });
 
-        for (_, mut content) in &mut self.contents {
-            Rc::make_mut(&mut content)
+        if let Some(master_key) = &mut self.master_key {
+            master_key.recipients.retain(is_other_credential);
+        }
+
+        let mut changed: Vec<String> = Vec::new();
+        for (key, content) in self.contents.iter_mut() {
+            let affected = match &content.key {
+                ContentKey::MasterKey { .. } => false,
+                ContentKey::Recipients { recipients } => {
+                    !recipients.iter().all(is_other_credential)
+                }
+                ContentKey::Threshold { shares, .. } => {
+                    !shares.iter().all(|share| is_other_credential(&share.key))
+                }
+            };
+            if affected {
+                match &mut Rc::make_mut(content).key {
+                    ContentKey::MasterKey { .. } => {}
+                    ContentKey::Recipients { recipients } => recipients.retain(is_other_credential),
+                    ContentKey::Threshold { shares, .. } => {
+                        shares.retain(|share| is_other_credential(&share.key))
+                    }
+                }
+                changed.push(key.clone());
+            }
+        }
+
+        for key in changed {
+            self.revisions.bump_entry(&key, false)?;
+        }
+        self.revisions.bump_credential(cred_id, true)?;
+        Ok(self)
+    }
+
+    /// Delete a credential like [Self::delete_credential], and make the
+    /// copies of the vault it could decrypt useless to it from now on. The
+    /// master key is replaced with a new one wrapped to its other recipients,
+    /// including the recovery phrase and passphrase, and every entry the
+    /// credential could decrypt is encrypted again with a fresh content key.
+    /// `unlocked` must be another credential. Entries it cannot decrypt on
+    /// its own, such as entries that need several keys, only lose the revoked
+    /// credential's key, and are reported as failed. The vault is left
+    /// unchanged if the credential cannot be deleted or the master key cannot
+    /// be replaced. Returns the new master
+    /// key, if it was replaced.
+    pub async fn revoke_credential<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        cred_id: &CredentialId,
+        unlocked: &UnlockedKey<B>,
+        master_key: &MasterKey,
+    ) -> Result<(Option<MasterKey>, RevokeReport), Error> {
+        if CredentialId::from(unlocked.credential_id.clone()) == *cred_id {
+            return Err(Error::InvalidInput(
+                "Unlock the vault with another key to revoke this one".to_string(),
+            ));
+        }
+        if self.credential_index(cred_id).is_none() {
+            return Err(Error::NoMatchingRecipient);
+        }
+
+        let is_revoked =
+            |wck: &WrappedContentKey| CredentialId::from(wck.credential_id.clone()) == *cred_id;
+        let rotate_master_key = self
+            .master_key
+            .as_ref()
+            .is_some_and(|mk| mk.recipients.iter().any(is_revoked));
+
+        let mut affected: Vec<String> = self
+            .contents
+            .iter()
+            .filter(|(_, content)| match &content.key {
+                ContentKey::MasterKey { .. } => rotate_master_key,
+                ContentKey::Recipients { recipients } => recipients.iter().any(is_revoked),
+                ContentKey::Threshold { shares, .. } => {
+                    shares.iter().any(|share| is_revoked(&share.key))
+                }
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        affected.sort();
+
+        let mut decrypted: Vec<(String, Result<Vec<u8>, Error>)> =
+            Vec::with_capacity(affected.len());
+        for key in affected {
+            let content = decrypt_with(
+                backend,
+                &self.contents[&key],
+                &self.content_additional_data(&key),
+                unlocked,
+                Some(master_key),
+            )
+            .await;
+            decrypted.push((key, content));
+        }
+        let names = if rotate_master_key && self.has_encrypted_names() {
+            Some(self.decrypt_names(backend, master_key).await?)
+        } else {
+            None
+        };
+        let identity_secret = if rotate_master_key {
+            Some(self.identity_secret(backend, master_key).await?)
+        } else {
+            None
+        };
+
+        let mut updated = self.clone();
+        updated.delete_credential(cred_id)?;
+
+        let new_master_key = if rotate_master_key {
+            let keypairs = updated.recipient_keypairs(
+                &updated
+                    .wrapped_master_key()?
+                    .recipients
+                    .iter()
+                    .collect::<Vec<_>>(),
+            );
+            let (new_master_key, mut wrapped_master_key) =
+                create_master_key(backend, &keypairs).await?;
+            if let Some(recovery_key) = &updated.recovery_key {
+                wrapped_master_key.recipients.push(
+                    wrap_master_key_to_recovery_key(backend, &new_master_key, recovery_key).await?,
+                );
+            }
+            if let Some(passphrase_key) = &updated.passphrase_key {
+                wrapped_master_key.recipients.push(
+                    wrap_master_key_to_passphrase_key(backend, &new_master_key, passphrase_key)
+                        .await?,
+                );
+            }
+            updated.master_key = Some(wrapped_master_key);
+            updated.revisions.bump_master_key()?;
+            if let Some(names) = &names {
+                updated.store_names(backend, names, &new_master_key).await?;
+            }
+            // Contacts only accept identities signed with the same key
+            if let Some(identity_secret) = &identity_secret {
+                updated
+                    .store_identity_secret(backend, identity_secret, &new_master_key)
+                    .await?;
+            }
+            Some(new_master_key)
+        } else {
+            None
+        };
+
+        let mut report = RevokeReport::default();
+        for (key, content) in decrypted {
+            let under_master_key =
+                matches!(updated.contents[&key].key, ContentKey::MasterKey { .. });
+            let result = match content {
+                Ok(content) => {
+                    updated
+                        .rotate_content(backend, &key, content, new_master_key.as_ref())
+                        .await
+                }
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok(()) => report.rotated.push(key),
+                // Nothing can decrypt it under the new master key
+                Err(err) if under_master_key => return Err(err),
+                Err(err) => report.failed.push((key, err)),
+            }
+        }
+
+        *self = updated;
+        Ok((new_master_key, report))
+    }
+
+    /// Encrypt `content` again as the entry with the given key in
+    /// [Self::contents], with a fresh content key for the same recipients,
+    /// or under `master_key` if it is encrypted under the master key.
+    async fn rotate_content<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        key: &str,
+        content: Vec<u8>,
+        master_key: Option<&MasterKey>,
+    ) -> Result<(), Error> {
+        let existing = Rc::clone(&self.contents[key]);
+        let keypairs = self.recipient_keypairs(&self.content_recipients(&existing));
+        let additional_data = self.content_additional_data(key);
+        let encrypted = match &existing.key {
+            ContentKey::MasterKey { .. } => {
+                let master_key = master_key
+                    .ok_or_else(|| Error::MalformedRecord("Vault has no master key".to_string()))?;
+                return self
+                    .put_content(backend, key.to_string(), content, master_key)
+                    .await
+                    .map(|_| ());
+            }
+            ContentKey::Recipients { .. } if keypairs.is_empty() => {
+                return Err(Error::NoMatchingRecipient);
+            }
+            ContentKey::Recipients { .. } => {
+                encrypt_with(backend, &content, &additional_data, &keypairs).await?
+            }
+            ContentKey::Threshold { threshold, .. } => {
+                encrypt_with_threshold(backend, &content, &additional_data, *threshold, &keypairs)
+                    .await?
+            }
+        };
+        self.revisions.bump_entry(key, false)?;
+        self.contents.insert(key.to_string(), Rc::new(encrypted));
+        Ok(())
+    }
+
+    /// The keypairs of the credentials among `recipients`.
+    fn recipient_keypairs(&self, recipients: &[&WrappedContentKey]) -> Vec<Rc<WrappedKeypair>> {
+        self.user
+            .keypairs
+            .iter()
+            .filter(|wkp| {
+                wkp.additional_data().is_ok_and(|ad| {
+                    recipients.iter().any(|wck| {
+                        CredentialId::from(wck.credential_id.clone()) == ad.credential_id()
+                    })
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// What to call `recipient` in the UI.
+    pub fn recipient_name(&self, recipient: &WrappedContentKey) -> String {
+        let is = |id: Option<&Vec<u8>>| id == Some(&recipient.credential_id);
+        if is(self.recovery_key.as_ref().map(|key| &key.id)) {
+            "Recovery phrase".to_string()
+        } else if is(self.passphrase_key.as_ref().map(|key| &key.id)) {
+            "Passphrase (weaker than a hardware key)".to_string()
+        } else {
+            let cred_id = CredentialId::from(recipient.credential_id.clone());
+            self.get_credential_nickname(&cred_id)
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| cred_id.b64_abbrev(24))
+        }
+    }
+
+    /// Keys other than credentials' can only be added to the master key, not
+    /// to entries that each have their own recipients.
+    fn check_all_under_master_key(&self) -> Result<(), Error> {
+        if self
+            .contents
+            .values()
+            .any(|content| matches!(content.key, ContentKey::Recipients { .. }))
+        {
+            Err(Error::InvalidInput(
+                "Upgrade all entries to the vault master key first".to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Replace the recipient `old_id` of the master key, if any, with `new`.
+    fn replace_master_key_recipient(
+        &mut self,
+        old_id: Option<&[u8]>,
+        new: Option<WrappedContentKey>,
+    ) -> Result<(), Error> {
+        let wrapped_master_key = self
+            .master_key
+            .as_mut()
+            .ok_or_else(|| Error::InvalidInput("The vault has no master key yet".to_string()))?;
+        if let Some(old_id) = old_id {
+            wrapped_master_key
                 .recipients
-                .retain(|wkk| CredentialId::from(wkk.credential_id.clone()) != *cred_id)
+                .retain(|wck| wck.credential_id != old_id);
         }
+        wrapped_master_key.recipients.extend(new);
+        Ok(())
+    }
+
+    /// Generate a new recovery phrase and wrap the master key to it, replacing
+    /// any previous one. The phrase is not stored, so it must be shown to the
+    /// user now. Entries not yet encrypted with the master key must be
+    /// upgraded first, see [Self::upgrade_content].
+    pub async fn set_up_recovery<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        master_key: &MasterKey,
+    ) -> Result<RecoveryPhrase, Error> {
+        self.check_all_under_master_key()?;
+
+        let phrase = RecoveryPhrase::generate(backend)?;
+        let (recovery_key, _) = derive_recovery_key(backend, &phrase).await?;
+        let wrapped_key =
+            wrap_master_key_to_recovery_key(backend, master_key, &recovery_key).await?;
+
+        let old_id = self.recovery_key.as_ref().map(|key| key.id.clone());
+        self.replace_master_key_recipient(old_id.as_deref(), Some(wrapped_key))?;
+        self.recovery_key = Some(recovery_key);
+        self.revisions.bump_recovery_key()?;
+        Ok(phrase)
+    }
+
+    /// Unwrap the master key with the recovery phrase instead of a WebAuthn
+    /// ceremony. The returned [UnlockedKey] decrypts entries like a
+    /// credential's would.
+    pub async fn recover<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        phrase: &RecoveryPhrase,
+    ) -> Result<(UnlockedKey<B>, MasterKey), Error> {
+        let (recovery_key, unlocked) = derive_recovery_key(backend, phrase).await?;
+        if self.recovery_key.as_ref() != Some(&recovery_key) {
+            return Err(Error::InvalidInput(
+                "This is not the recovery phrase of this vault".to_string(),
+            ));
+        }
+        let master_key = unwrap_master_key(backend, self.wrapped_master_key()?, &unlocked).await?;
+        Ok((unlocked, master_key))
+    }
+
+    /// Set a passphrase that unlocks the vault like a credential would, or
+    /// remove it if `passphrase` is `None`. Replaces any previous passphrase.
+    pub async fn set_passphrase<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        passphrase: Option<&str>,
+        params: Argon2Params,
+        master_key: &MasterKey,
+    ) -> Result<&mut Self, Error> {
+        let (passphrase_key, wrapped_key) = match passphrase {
+            Some(passphrase) => {
+                self.check_all_under_master_key()?;
+                let key = create_passphrase_key(backend, passphrase, params).await?;
+                let wrapped_key =
+                    wrap_master_key_to_passphrase_key(backend, master_key, &key).await?;
+                (Some(key), Some(wrapped_key))
+            }
+            None => (None, None),
+        };
+
+        let old_id = self.passphrase_key.as_ref().map(|key| key.id.clone());
+        self.replace_master_key_recipient(old_id.as_deref(), wrapped_key)?;
+        self.revisions
+            .bump_passphrase_key(passphrase_key.is_none())?;
+        self.passphrase_key = passphrase_key;
+        Ok(self)
+    }
+
+    /// Unwrap the master key with the passphrase instead of a WebAuthn
+    /// ceremony.
+    pub async fn unlock_with_passphrase<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        passphrase: &str,
+    ) -> Result<(UnlockedKey<B>, MasterKey), Error> {
+        let key = self
+            .passphrase_key
+            .as_ref()
+            .ok_or_else(|| Error::InvalidInput("The vault has no passphrase".to_string()))?;
+        let unlocked = unlock_passphrase_key(backend, key, passphrase).await?;
+        let master_key = unwrap_master_key(backend, self.wrapped_master_key()?, &unlocked).await?;
+        Ok((unlocked, master_key))
+    }
+
+    /// Add a credential's keypair without wrapping the master key to it.
+    pub fn push_keypair(&mut self, wrapped_keypair: WrappedKeypair) -> Result<&mut Self, Error> {
+        self.revisions
+            .bump_credential(&wrapped_keypair.additional_data()?.credential_id(), false)?;
+        Rc::make_mut(&mut Rc::make_mut(&mut self.user).keypairs).push(Rc::new(wrapped_keypair));
+        Ok(self)
+    }
+
+    /// Delete the entry with the given key in [Self::contents], leaving a
+    /// tombstone revision so that merging an older copy does not restore it.
+    /// Copies not yet sent to the contacts it is shared with are deleted too.
+    pub fn delete_content(&mut self, key: &str) -> Result<&mut Self, Error> {
+        if self.contents.remove(key).is_some() {
+            self.revisions.bump_entry(key, true)?;
+        }
+        let contacts: Vec<String> = self
+            .contacts
+            .iter()
+            .filter(|(_, contact)| contact.shared.contains_key(key))
+            .map(|(contact, _)| contact.clone())
+            .collect();
+        for contact in contacts {
+            self.unshare_content(key, &contact)?;
+        }
+        Ok(self)
+    }
+
+    /// The secret the signing key of this vault's public identity is derived
+    /// from.
+    pub async fn identity_secret<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        master_key: &MasterKey,
+    ) -> Result<IdentitySecret, Error> {
+        match &self.identity_secret {
+            Some(encrypted) => {
+                decrypt_identity_secret(
+                    backend,
+                    encrypted,
+                    &self.content_additional_data(IDENTITY_SECRET_AD_NAME),
+                    master_key,
+                )
+                .await
+            }
+            None => Ok(IdentitySecret::from_master_key(master_key)),
+        }
+    }
+
+    /// The token that authorizes writing this vault to the sync server.
+    pub async fn sync_token<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        master_key: &MasterKey,
+    ) -> Result<String, Error> {
+        sync_token(&self.identity_secret(backend, master_key).await?)
+    }
+
+    async fn store_identity_secret<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        secret: &IdentitySecret,
+        master_key: &MasterKey,
+    ) -> Result<&mut Self, Error> {
+        self.identity_secret = Some(
+            encrypt_identity_secret(
+                backend,
+                secret,
+                &self.content_additional_data(IDENTITY_SECRET_AD_NAME),
+                master_key,
+            )
+            .await?,
+        );
+        Ok(self)
+    }
+
+    /// Sign the public identity of this vault, for others to import as a
+    /// contact.
+    pub async fn export_identity<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        master_key: &MasterKey,
+    ) -> Result<SignedIdentity, Error> {
+        SignedIdentity::create(
+            &self.identity_secret(backend, master_key).await?,
+            &self.user,
+        )
+    }
+
+    /// Add another user's identity as a contact, or update it. An identity
+    /// already imported can only be replaced by one signed with the same key.
+    pub fn import_contact(&mut self, signed: SignedIdentity) -> Result<PublicIdentity, Error> {
+        let identity = signed.verify()?;
+        if identity.user_handle == self.user.user_handle {
+            return Err(Error::InvalidInput(
+                "This is the identity of this vault".to_string(),
+            ));
+        }
+
+        let key = identity.user_handle.b64url();
+        let shared = match self.contacts.get(&key) {
+            Some(contact) if !contact.identity()?.same_signer(&identity) => {
+                return Err(Error::InvalidInput(format!(
+                    "{} was imported before with a different signing key",
+                    identity.username
+                )));
+            }
+            Some(contact) => contact.shared.clone(),
+            None => BTreeMap::new(),
+        };
+        self.revisions.bump_contact(&key, false)?;
+        self.contacts.insert(
+            key,
+            Rc::new(Contact {
+                identity: signed,
+                shared,
+            }),
+        );
+        Ok(identity)
+    }
+
+    pub fn delete_contact(&mut self, contact: &str) -> Result<&mut Self, Error> {
+        if self.contacts.remove(contact).is_some() {
+            self.revisions.bump_contact(contact, true)?;
+        }
+        Ok(self)
+    }
+
+    fn contact(&self, contact: &str) -> Result<&Rc<Contact>, Error> {
+        self.contacts
+            .get(contact)
+            .ok_or_else(|| Error::InvalidInput("No such contact".to_string()))
+    }
+
+    /// Encrypt the entry with the given key in [Self::contents] to the keys
+    /// of `contact`, under the name `name`, and record it as shared with them.
+    /// Replaces any earlier copy shared with them.
+    pub async fn share_content<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        key: &str,
+        name: &str,
+        contact: &str,
+        unlocked: &UnlockedKey<B>,
+        master_key: &MasterKey,
+    ) -> Result<&mut Self, Error> {
+        let content = self
+            .contents
+            .get(key)
+            .ok_or_else(|| Error::InvalidInput(format!("No entry named \"{name}\"")))?;
+        let mut updated = Rc::clone(self.contact(contact)?);
+        let identity = updated.identity()?;
+
+        let data = decrypt_with(
+            backend,
+            content,
+            &self.content_additional_data(key),
+            unlocked,
+            Some(master_key),
+        )
+        .await?;
+        let shared = encrypt_to_identity(
+            backend,
+            &data,
+            &EncryptedContentAdditionalData::new(name, &identity.user_handle)
+                .with_padding(self.padding),
+            &identity,
+        )
+        .await?;
+
+        Rc::make_mut(&mut updated)
+            .shared
+            .insert(key.to_string(), Rc::new(shared));
+        self.revisions.bump_contact(contact, false)?;
+        self.contacts.insert(contact.to_string(), updated);
+        Ok(self)
+    }
+
+    /// Stop sharing an entry with `contact`. Copies already sent to them
+    /// cannot be taken back.
+    pub fn unshare_content(&mut self, key: &str, contact: &str) -> Result<&mut Self, Error> {
+        if let Some(updated) = self.contacts.get_mut(contact) {
+            if Rc::make_mut(updated).shared.remove(key).is_some() {
+                self.revisions.bump_contact(contact, false)?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// The contacts the entry with the given key is shared with.
+    pub fn shared_with(&self, key: &str) -> Vec<PublicIdentity> {
+        self.contacts
+            .values()
+            .filter(|contact| contact.shared.contains_key(key))
+            .filter_map(|contact| contact.identity().ok())
+            .collect()
+    }
+
+    /// Sign the entries shared with `contact`, for them to import.
+    pub async fn share_package<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        contact: &str,
+        master_key: &MasterKey,
+    ) -> Result<SharePackage, Error> {
+        let contact = self.contact(contact)?;
+        let secret = self.identity_secret(backend, master_key).await?;
+        let payload = serde_json::to_vec(&SharePayload {
+            sender: signer_id(&secret)?,
+            recipient: contact.identity()?.signer_id(),
+            entries: contact.shared.values().cloned().collect(),
+        })?;
+        Ok(SharePackage {
+            signature: sign(&secret, &payload)?,
+            payload,
+        })
+    }
+
+    /// Add the entries in `package`, shared by one of this vault's contacts,
+    /// as new entries encrypted under the master key. `unlocked` must be one
+    /// of the credentials in the identity the sender imported. The vault is
+    /// left unchanged if any entry cannot be added. Returns the names of the
+    /// added entries.
+    pub async fn import_shared<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        package: &SharePackage,
+        unlocked: &UnlockedKey<B>,
+        master_key: &MasterKey,
+    ) -> Result<Vec<String>, Error> {
+        let sender_id = package.sender()?;
+        let sender = self
+            .contacts
+            .values()
+            .filter_map(|contact| contact.identity().ok())
+            .find(|identity| identity.signer_id() == sender_id)
+            .ok_or_else(|| {
+                Error::InvalidInput("The entries are not from one of your contacts".to_string())
+            })?;
+        let payload = package.verify(&sender)?;
+        if payload.recipient != signer_id(&self.identity_secret(backend, master_key).await?)? {
+            return Err(Error::InvalidInput(
+                "The entries are shared with someone else".to_string(),
+            ));
+        }
+
+        let mut updated = self.clone();
+        let mut names: Vec<String> = Vec::new();
+        for content in &payload.entries {
+            let name = content
+                .name()?
+                .ok_or_else(|| Error::MalformedRecord("Shared entry has no name".to_string()))?;
+            let data = decrypt_with(
+                backend,
+                content,
+                &self.content_additional_data(&name),
+                unlocked,
+                None,
+            )
+            .await?;
+            if updated.contents.contains_key(&name) {
+                return Err(Error::InvalidInput(format!(
+                    "An entry named \"{name}\" already exists"
+                )));
+            }
+            updated
+                .push_content(backend, name.clone(), data, master_key)
+                .await?;
+            names.push(name);
+        }
+
+        *self = updated;
+        Ok(names)
+    }
+
+    /// The wrapped keys that can decrypt `content`: its own recipients or
+    /// share holders, or those of the master key.
+    pub fn content_recipients<'a>(
+        &'a self,
+        content: &'a EncryptedContent,
+    ) -> Vec<&'a WrappedContentKey> {
+        match &content.key {
+            ContentKey::MasterKey { .. } => self
+                .master_key
+                .as_ref()
+                .map(|mk| mk.recipients.iter().collect())
+                .unwrap_or_default(),
+            ContentKey::Recipients { recipients } => recipients.iter().collect(),
+            ContentKey::Threshold { shares, .. } => shares.iter().map(|share| &share.key).collect(),
+        }
+    }
+
+    /// Generate a new master key wrapped to every current credential, and a
+    /// new identity secret under it. Existing entries are left as they are;
+    /// see [Self::upgrade_content].
+    pub async fn create_master_key<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+    ) -> Result<MasterKey, Error> {
+        let (master_key, wrapped_master_key) =
+            create_master_key(backend, &self.user.keypairs).await?;
+        self.master_key = Some(wrapped_master_key);
+        self.revisions.bump_master_key()?;
+        self.store_identity_secret(backend, &IdentitySecret::generate(backend)?, &master_key)
+            .await?;
+        Ok(master_key)
+    }
+
+    pub async fn unwrap_master_key<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        unlocked: &UnlockedKey<B>,
+    ) -> Result<MasterKey, Error> {
+        unwrap_master_key(backend, self.wrapped_master_key()?, unlocked).await
+    }
+
+    /// Perform a WebAuthn ceremony with one of the credentials the master key
+    /// is wrapped to, and unwrap the master key.
+    pub async fn unlock(&self) -> Result<(UnlockedKey<WebCrypto>, MasterKey), Error> {
+        let wrapped_master_key = self.wrapped_master_key()?;
+        let keypairs: Vec<Rc<WrappedKeypair>> = self
+            .user
+            .keypairs
+            .iter()
+            .filter(|wkp| {
+                wkp.additional_data().is_ok_and(|ad| {
+                    wrapped_master_key.recipients.iter().any(|wck| {
+                        CredentialId::from(wck.credential_id.clone()) == ad.credential_id()
+                    })
+                })
+            })
+            .cloned()
+            .collect();
 
-        self
+        let unlocked = unwrap_private_key(&keypairs).await?;
+        let master_key = unwrap_master_key(&WebCrypto, wrapped_master_key, &unlocked).await?;
+        Ok((unlocked, master_key))
     }
 
-    pub async fn push_content(
+    fn wrapped_master_key(&self) -> Result<&WrappedMasterKey, Error> {
+        self.master_key
+            .as_ref()
+            .ok_or_else(|| Error::MalformedRecord("Vault has no master key".to_string()))
+    }
+
+    /// Register a new credential by wrapping the master key to it.
+    pub async fn add_credential<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        wrapped_keypair: WrappedKeypair,
+        master_key: &MasterKey,
+    ) -> Result<&mut Self, Error> {
+        let wrapped_key =
+            wrap_master_key_to_recipient(backend, master_key, &wrapped_keypair).await?;
+        self.master_key
+            .as_mut()
+            .ok_or_else(|| Error::MalformedRecord("Vault has no master key".to_string()))?
+            .recipients
+            .push(wrapped_key);
+        self.push_keypair(wrapped_keypair)
+    }
+
+    /// Encrypt `content` under the master key and store it as the entry with
+    /// the given key in [Self::contents], replacing any existing one.
+    async fn put_content<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        key: String,
+        content: Vec<u8>,
+        master_key: &MasterKey,
+    ) -> Result<&mut Self, Error> {
+        let encrypted = encrypt_with_master_key(
+            backend,
+            &content,
+            &self.content_additional_data(&key),
+            master_key,
+        )
+        .await?;
+        self.revisions.bump_entry(&key, false)?;
+        self.contents.insert(key, Rc::new(encrypted));
+        Ok(self)
+    }
+
+    /// Add an entry named `name`. If entry names are encrypted, the entry is
+    /// stored under a new random ID, and it is an error if an entry with the
+    /// same name already exists.
+    pub async fn push_content<B: CryptoBackend>(
         &mut self,
+        backend: &B,
         name: String,
         content: Vec<u8>,
-    ) -> Result<&mut Self, JsOrSerdeError> {
-        let encrypted = encrypt(content, self.user.keypairs.as_slice()).await?;
-        self.contents.insert(name, Rc::new(encrypted));
+        master_key: &MasterKey,
+    ) -> Result<&mut Self, Error> {
+        let (key, names) = self.new_entry_key(backend, name, master_key).await?;
+        self.put_content(backend, key, content, master_key).await?;
+        match names {
+            Some(names) => self.store_names(backend, &names, master_key).await,
+            None => Ok(self),
+        }
+    }
+
+    /// Add an entry named `name` like [Self::push_content], but encrypted
+    /// with a content key split between the credentials `credential_ids` so
+    /// that any `threshold` of them are needed to decrypt it. `master_key` is
+    /// only used for the entry names.
+    pub async fn push_threshold_content<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        name: String,
+        content: Vec<u8>,
+        threshold: u8,
+        credential_ids: &[CredentialId],
+        master_key: &MasterKey,
+    ) -> Result<&mut Self, Error> {
+        let keypairs: Vec<Rc<WrappedKeypair>> = credential_ids
+            .iter()
+            .map(|cred_id| {
+                self.user
+                    .keypairs
+                    .iter()
+                    .find(|wkp| {
+                        wkp.additional_data()
+                            .is_ok_and(|ad| ad.credential_id() == *cred_id)
+                    })
+                    .cloned()
+                    .ok_or_else(|| Error::InvalidInput("Unknown credential".to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let (key, names) = self.new_entry_key(backend, name, master_key).await?;
+        let encrypted = encrypt_with_threshold(
+            backend,
+            &content,
+            &self.content_additional_data(&key),
+            threshold,
+            &keypairs,
+        )
+        .await?;
+        self.revisions.bump_entry(&key, false)?;
+        self.contents.insert(key, Rc::new(encrypted));
+        match names {
+            Some(names) => self.store_names(backend, &names, master_key).await,
+            None => Ok(self),
+        }
+    }
+
+    /// The key in [Self::contents] for a new entry named `name`. If entry
+    /// names are encrypted, this is a new random ID, returned with the name
+    /// index to store once the entry is added.
+    async fn new_entry_key<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        name: String,
+        master_key: &MasterKey,
+    ) -> Result<(String, Option<NameIndex>), Error> {
+        if self.has_encrypted_names() {
+            let mut names = self.decrypt_names(backend, master_key).await?;
+            if names.contains_name(&name) {
+                return Err(Error::InvalidInput(format!(
+                    "An entry named \"{name}\" already exists"
+                )));
+            }
+            let id = generate_entry_id(backend)?;
+            names.names.insert(id.clone(), name);
+            Ok((id, Some(names)))
+        } else {
+            Ok((name, None))
+        }
+    }
+
+    pub fn has_encrypted_names(&self) -> bool {
+        self.name_index.is_some()
+    }
+
+    fn name_index_additional_data(&self) -> EncryptedContentAdditionalData {
+        self.content_additional_data(NAME_INDEX_AD_NAME)
+    }
+
+    /// Decrypt the entry names. If names are not encrypted, each entry is
+    /// named by its key in [Self::contents].
+    pub async fn decrypt_names<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        master_key: &MasterKey,
+    ) -> Result<NameIndex, Error> {
+        match &self.name_index {
+            Some(name_index) => {
+                let mut names: NameIndex = serde_json::from_slice(
+                    &decrypt_with_master_key(
+                        backend,
+                        name_index,
+                        &self.name_index_additional_data(),
+                        master_key,
+                    )
+                    .await?,
+                )?;
+                // Entries are deleted without unlocking, so the index may
+                // still name them
+                names.names.retain(|id, _| self.contents.contains_key(id));
+                Ok(names)
+            }
+            None => Ok(NameIndex {
+                names: self
+                    .contents
+                    .keys()
+                    .map(|name| (name.clone(), name.clone()))
+                    .collect(),
+            }),
+        }
+    }
+
+    async fn store_names<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        names: &NameIndex,
+        master_key: &MasterKey,
+    ) -> Result<&mut Self, Error> {
+        self.name_index = Some(
+            encrypt_with_master_key(
+                backend,
+                &serde_json::to_vec(names)?,
+                &self.name_index_additional_data(),
+                master_key,
+            )
+            .await?,
+        );
         Ok(self)
     }
+
+    /// Move every entry to a random ID and store the entry names in an
+    /// encrypted [NameIndex]. Each entry is re-encrypted, since its
+    /// associated data binds it to its key in [Self::contents]. The vault is
+    /// left unchanged if any entry fails to decrypt, or if any entry needs
+    /// several keys to decrypt.
+    pub async fn encrypt_names<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        unlocked: &UnlockedKey<B>,
+        master_key: &MasterKey,
+    ) -> Result<&mut Self, Error> {
+        if self.has_encrypted_names() {
+            return Ok(self);
+        }
+        if self
+            .contents
+            .values()
+            .any(|content| matches!(content.key, ContentKey::Threshold { .. }))
+        {
+            return Err(Error::InvalidInput(
+                "Entries that need several keys to decrypt cannot be moved".to_string(),
+            ));
+        }
+
+        let mut names: Vec<String> = self.contents.keys().cloned().collect();
+        names.sort();
+
+        let mut decrypted: Vec<(String, Vec<u8>)> = Vec::with_capacity(names.len());
+        for name in names {
+            let content = decrypt_with(
+                backend,
+                &self.contents[&name],
+                &self.content_additional_data(&name),
+                unlocked,
+                Some(master_key),
+            )
+            .await?;
+            decrypted.push((name, content));
+        }
+
+        let mut updated = self.clone();
+        updated.contents.clear();
+        // Revisions are keyed by entry name too. Merging with a vault without
+        // encrypted names is not supported, so there is nothing to supersede.
+        updated.revisions.entries.clear();
+        let mut index = NameIndex::default();
+        for (name, content) in decrypted {
+            let id = generate_entry_id(backend)?;
+            updated
+                .put_content(backend, id.clone(), content, master_key)
+                .await?;
+            index.names.insert(id, name);
+        }
+        updated.store_names(backend, &index, master_key).await?;
+
+        // Entries shared with contacts are keyed by name too
+        for (contact_key, contact) in updated.contacts.iter_mut() {
+            if !contact.shared.is_empty() {
+                let contact = Rc::make_mut(contact);
+                contact.shared = std::mem::take(&mut contact.shared)
+                    .into_iter()
+                    .filter_map(|(name, shared)| {
+                        index
+                            .iter()
+                            .find(|(_, indexed)| *indexed == name)
+                            .map(|(id, _)| (id.to_string(), shared))
+                    })
+                    .collect();
+                updated.revisions.bump_contact(contact_key, false)?;
+            }
+        }
+
+        *self = updated;
+        Ok(self)
+    }
+
+    /// Re-encrypt entries in the format version 2 layout under the master
+    /// key. Entries that `unlocked` is not a recipient of are left unchanged.
+    /// Returns the names of the upgraded entries.
+    pub async fn upgrade_content<B: CryptoBackend>(
+        &mut self,
+        backend: &B,
+        unlocked: &UnlockedKey<B>,
+        master_key: &MasterKey,
+    ) -> Result<Vec<String>, Error> {
+        let mut upgradable: Vec<String> = self
+            .contents
+            .iter()
+            .filter(|(_, content)| match &content.key {
+                ContentKey::MasterKey { .. } | ContentKey::Threshold { .. } => false,
+                ContentKey::Recipients { recipients } => recipients
+                    .iter()
+                    .any(|wck| wck.credential_id == unlocked.credential_id),
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        upgradable.sort();
+
+        for name in &upgradable {
+            let content = decrypt_with(
+                backend,
+                &self.contents[name],
+                &self.content_additional_data(name),
+                unlocked,
+                None,
+            )
+            .await?;
+            self.put_content(backend, name.clone(), content, master_key)
+                .await?;
+        }
+
+        Ok(upgradable)
+    }
+
+    /// The registered credentials that the master key is not wrapped to, such
+    /// as ones added on another device without unlocking the vault.
+    pub fn unenrolled_credentials(&self) -> Result<Vec<CredentialId>, Error> {
+        let Some(wrapped_master_key) = &self.master_key else {
+            return Ok(Vec::new());
+        };
+        let mut unenrolled = Vec::new();
+        for wkp in self.user.keypairs.iter() {
+            let cred_id = wkp.additional_data()?.credential_id();
+            if !wrapped_master_key
+                .recipients
+                .iter()
+                .any(|wck| CredentialId::from(wck.credential_id.clone()) == cred_id)
+            {
+                unenrolled.push(cred_id);
+            }
+        }
+        Ok(unenrolled)
+    }
+
+    /// Wrap the master key to the credentials in `enroll`, which must be ones
+    /// the user confirmed among [Self::unenrolled_credentials], then decrypt
+    /// every entry and encrypt it again under the master key. Other
+    /// unenrolled credentials are left without access, since a synced config
+    /// may list keypairs the user never registered. An entry that fails is
+    /// left unchanged and reported, and the rest are still processed. Entries
+    /// that need several keys to decrypt are skipped. `on_progress` is called
+    /// after each entry.
+    pub async fn reencrypt_all<B, F>(
+        &mut self,
+        backend: &B,
+        unlocked: &UnlockedKey<B>,
+        master_key: &MasterKey,
+        enroll: &[CredentialId],
+        mut on_progress: F,
+    ) -> Result<ReencryptReport, Error>
+    where
+        B: CryptoBackend,
+        F: FnMut(ReencryptProgress),
+    {
+        self.wrapped_master_key()?;
+        let unenrolled = self.unenrolled_credentials()?;
+        let mut missing: Vec<Rc<WrappedKeypair>> = Vec::new();
+        for cred_id in enroll {
+            if !unenrolled.contains(cred_id) {
+                return Err(Error::InvalidInput(
+                    "Only registered keys without access to the vault can be enrolled".to_string(),
+                ));
+            }
+            if let Some(index) = self.credential_index(cred_id) {
+                missing.push(Rc::clone(&self.user.keypairs[index]));
+            }
+        }
+        for wkp in missing {
+            let wrapped_key = wrap_master_key_to_recipient(backend, master_key, &wkp).await?;
+            if let Some(wrapped_master_key) = &mut self.master_key {
+                wrapped_master_key.recipients.push(wrapped_key);
+            }
+        }
+
+        let mut names: Vec<String> = self
+            .contents
+            .iter()
+            .filter(|(_, content)| !matches!(content.key, ContentKey::Threshold { .. }))
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+
+        let mut report = ReencryptReport::default();
+        for (index, name) in names.iter().enumerate() {
+            let result = match decrypt_with(
+                backend,
+                &self.contents[name],
+                &self.content_additional_data(name),
+                unlocked,
+                Some(master_key),
+            )
+            .await
+            {
+                Ok(content) => self
+                    .put_content(backend, name.clone(), content, master_key)
+                    .await
+                    .map(|_| ()),
+                Err(err) => Err(err),
+            };
+
+            on_progress(ReencryptProgress {
+                name,
+                done: index + 1,
+                total: names.len(),
+                result: &result,
+            });
+
+            match result {
+                Ok(()) => report.reencrypted.push(name.clone()),
+                Err(err) => report.failed.push((name.clone(), err)),
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn check_mergeable(&self, other: &VaultConfig) -> Result<(), Error> {
+        if other.user.user_handle != self.user.user_handle {
+            Err(Error::InvalidInput(
+                "The imported config is of a different vault".to_string(),
+            ))
+        } else if other.has_encrypted_names() != self.has_encrypted_names() {
+            Err(Error::InvalidInput(
+                "Entry names must be encrypted in both vaults or in neither".to_string(),
+            ))
+        } else if self.has_concurrent_master_key(other) {
+            Err(Error::InvalidInput(
+                "The master key was created or replaced on both devices at once, so the \
+                 entries under one of them could not be decrypted after merging"
+                    .to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Whether `other` has a different master key than this copy, created or
+    /// replaced independently of it rather than one superseding the other.
+    fn has_concurrent_master_key(&self, other: &VaultConfig) -> bool {
+        let (Some(ours), Some(theirs)) = (&self.master_key, &other.master_key) else {
+            return false;
+        };
+        match (self.revisions.master_key, other.revisions.master_key) {
+            (Some(a), Some(b)) => a.counter == b.counter && a != b,
+            // From before master key revisions were recorded: the same master
+            // key is only wrapped once to each credential
+            (None, None) => ours.recipients.iter().any(|a| {
+                theirs
+                    .recipients
+                    .iter()
+                    .any(|b| a.credential_id == b.credential_id && a != b)
+            }),
+            _ => false,
+        }
+    }
+
+    /// Merge this vault with `other`, a copy of the same vault from another
+    /// device, keeping the newest revision of each entry and credential. The
+    /// result is the same regardless of the order in which copies are merged.
+    /// `master_key` is needed if entry names are encrypted.
+    pub async fn sync_merge<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        other: &VaultConfig,
+        master_key: Option<&MasterKey>,
+    ) -> Result<VaultConfig, Error> {
+        self.check_mergeable(other)?;
+        let mut merged = self.merge_state(other);
+
+        if merged.has_encrypted_names() {
+            let master_key = master_key.ok_or_else(|| {
+                Error::InvalidInput("The vault must be unlocked to merge entry names".to_string())
+            })?;
+            let mut names = NameIndex::default();
+            for config in [self, other] {
+                // The names in a copy made before the master key was
+                // replaced are encrypted under the old one
+                if config.revisions.master_key != merged.revisions.master_key {
+                    continue;
+                }
+                for (id, name) in config.decrypt_names(backend, master_key).await?.names {
+                    if merged.contents.contains_key(&id) {
+                        // An entry keeps its name for life, so both copies agree
+                        names.names.entry(id).or_insert(name);
+                    }
+                }
+            }
+            merged.store_names(backend, &names, master_key).await?;
+        }
+
+        Ok(merged)
+    }
+
+    /// The state-based merge behind [Self::sync_merge], except for the name
+    /// index.
+    fn merge_state(&self, other: &VaultConfig) -> VaultConfig {
+        /// Pick the state with the greater revision. Equal revisions only
+        /// differ in content if neither side has recorded a revision, and
+        /// then the content itself breaks the tie.
+        fn newer<'a, T: Serialize>(
+            a: Option<(Revision, Option<&'a Rc<T>>)>,
+            b: Option<(Revision, Option<&'a Rc<T>>)>,
+        ) -> Option<(Revision, Option<&'a Rc<T>>)> {
+            let order_key = |state: &(Revision, Option<&Rc<T>>)| {
+                (
+                    state.0,
+                    state.1.and_then(|value| serde_json::to_string(value).ok()),
+                )
+            };
+            match (a, b) {
+                (Some(a), Some(b)) => Some(if order_key(&b) > order_key(&a) { b } else { a }),
+                (a, b) => a.or(b),
+            }
+        }
+
+        fn entry_state<'a>(
+            config: &'a VaultConfig,
+            key: &str,
+        ) -> Option<(Revision, Option<&'a Rc<EncryptedContent>>)> {
+            let content = config.contents.get(key);
+            let revision = config.revisions.entries.get(key).copied();
+            if content.is_none() && revision.is_none() {
+                None
+            } else {
+                Some((revision.unwrap_or_default(), content))
+            }
+        }
+
+        /// The state of an entry, unless it is under the master key of a
+        /// `stale` copy.
+        fn current_entry_state<'a>(
+            config: &'a VaultConfig,
+            key: &str,
+            stale: bool,
+        ) -> Option<(Revision, Option<&'a Rc<EncryptedContent>>)> {
+            entry_state(config, key).filter(|(_, content)| {
+                !(stale
+                    && content
+                        .is_some_and(|content| matches!(content.key, ContentKey::MasterKey { .. })))
+            })
+        }
+
+        fn credential_state<'a>(
+            config: &'a VaultConfig,
+            key: &str,
+        ) -> Option<(Revision, Option<&'a Rc<WrappedKeypair>>)> {
+            let keypair = config.user.keypairs.iter().find(|wkp| {
+                wkp.additional_data()
+                    .is_ok_and(|ad| ad.credential_id().b64url() == key)
+            });
+            let revision = config.revisions.credentials.get(key).copied();
+            if keypair.is_none() && revision.is_none() {
+                None
+            } else {
+                Some((revision.unwrap_or_default(), keypair))
+            }
+        }
+
+        fn contact_state<'a>(
+            config: &'a VaultConfig,
+            key: &str,
+        ) -> Option<(Revision, Option<&'a Rc<Contact>>)> {
+            let contact = config.contacts.get(key);
+            let revision = config.revisions.contacts.get(key).copied();
+            if contact.is_none() && revision.is_none() {
+                None
+            } else {
+                Some((revision.unwrap_or_default(), contact))
+            }
+        }
+
+        let credential_keys = |config: &VaultConfig| -> Vec<String> {
+            config
+                .user
+                .keypairs
+                .iter()
+                .filter_map(|wkp| wkp.additional_data().ok())
+                .map(|ad| ad.credential_id().b64url())
+                .chain(config.revisions.credentials.keys().cloned())
+                .collect()
+        };
+
+        // A replaced master key is superseded along with everything under
+        // it, which the new one cannot decrypt. A copy made before the
+        // replacement only contributes entries with their own recipients.
+        let stale = match self.revisions.master_key.cmp(&other.revisions.master_key) {
+            Ordering::Less => Some(self),
+            Ordering::Greater => Some(other),
+            Ordering::Equal => None,
+        };
+        let is_stale =
+            |config: &VaultConfig| stale.is_some_and(|stale| std::ptr::eq(stale, config));
+
+        let mut merged = self.clone();
+        merged.contents.clear();
+        merged.revisions = Revisions::default();
+        merged.revisions.master_key = self.revisions.master_key.max(other.revisions.master_key);
+
+        let entry_keys: BTreeSet<&String> = self
+            .contents
+            .keys()
+            .chain(self.revisions.entries.keys())
+            .chain(other.contents.keys())
+            .chain(other.revisions.entries.keys())
+            .collect();
+        for key in entry_keys {
+            if let Some((revision, content)) = newer(
+                current_entry_state(self, key, is_stale(self)),
+                current_entry_state(other, key, is_stale(other)),
+            ) {
+                if let Some(content) = content {
+                    merged.contents.insert(key.clone(), Rc::clone(content));
+                }
+                if revision != Revision::default() {
+                    merged.revisions.entries.insert(key.clone(), revision);
+                }
+            }
+        }
+
+        merged.contacts.clear();
+        let contact_keys: BTreeSet<&String> = self
+            .contacts
+            .keys()
+            .chain(self.revisions.contacts.keys())
+            .chain(other.contacts.keys())
+            .chain(other.revisions.contacts.keys())
+            .collect();
+        for key in contact_keys {
+            if let Some((revision, contact)) =
+                newer(contact_state(self, key), contact_state(other, key))
+            {
+                if let Some(contact) = contact {
+                    merged.contacts.insert(key.clone(), Rc::clone(contact));
+                }
+                if revision != Revision::default() {
+                    merged.revisions.contacts.insert(key.clone(), revision);
+                }
+            }
+        }
+
+        let cred_keys: BTreeSet<String> = credential_keys(self)
+            .into_iter()
+            .chain(credential_keys(other))
+            .collect();
+        let mut keypairs: Vec<Rc<WrappedKeypair>> = Vec::new();
+        let mut recipients: Vec<WrappedContentKey> = Vec::new();
+        for key in &cred_keys {
+            let ours = credential_state(self, key);
+            let theirs = credential_state(other, key);
+            let ours_won = newer(ours, theirs) == ours;
+            if let Some((revision, keypair)) = newer(ours, theirs) {
+                if let Some(keypair) = keypair {
+                    keypairs.push(Rc::clone(keypair));
+
+                    let recipient = |config: &VaultConfig| {
+                        config
+                            .master_key
+                            .as_ref()
+                            .filter(|_| !is_stale(config))
+                            .and_then(|mk| {
+                                mk.recipients
+                                    .iter()
+                                    .find(|wck| {
+                                        CredentialId::from(wck.credential_id.clone()).b64url()
+                                            == *key
+                                    })
+                                    .cloned()
+                            })
+                    };
+                    let (first, second) = if ours_won {
+                        (self, other)
+                    } else {
+                        (other, self)
+                    };
+                    if let Some(wck) = recipient(first).or_else(|| recipient(second)) {
+                        recipients.push(wck);
+                    }
+                }
+                if revision != Revision::default() {
+                    merged.revisions.credentials.insert(key.clone(), revision);
+                }
+            }
+        }
+
+        let user = Rc::make_mut(&mut merged.user);
+        user.keypairs = Rc::new(keypairs);
+        user.username = self.user.username.clone().max(other.user.username.clone());
+        user.version = self.user.version.max(other.user.version);
+        // The padding set last wins. Equal revisions only differ in the
+        // padding if neither side has recorded a revision, and then the
+        // padding itself breaks the tie.
+        (merged.revisions.padding, merged.padding) = [self, other]
+            .into_iter()
+            .map(|config| (config.revisions.padding, config.padding))
+            .max()
+            .unwrap_or_default();
+
+        // Only one recovery key and one passphrase key can be kept, the ones
+        // set up last, or none if removed last. Equal revisions only differ
+        // in the key if neither side has recorded a revision, and then the
+        // key itself breaks the tie.
+        let current: Vec<&VaultConfig> = [self, other]
+            .into_iter()
+            .filter(|config| !is_stale(config))
+            .collect();
+        let (recovery_revision, recovery_key) = current
+            .iter()
+            .map(|config| (config.revisions.recovery_key, config.recovery_key.as_ref()))
+            .max()
+            .unwrap_or_default();
+        merged.recovery_key = recovery_key.cloned();
+        merged.revisions.recovery_key = recovery_revision;
+        let (passphrase_revision, passphrase_key) = current
+            .iter()
+            .map(|config| {
+                (
+                    config.revisions.passphrase_key,
+                    config.passphrase_key.as_ref(),
+                )
+            })
+            .max_by_key(|(revision, key)| (*revision, key.map(|key| &key.id)))
+            .unwrap_or_default();
+        merged.passphrase_key = passphrase_key.cloned();
+        merged.revisions.passphrase_key = passphrase_revision;
+        // Created along with the master key, so current copies agree
+        merged.identity_secret = current
+            .iter()
+            .find_map(|config| config.identity_secret.clone());
+        let other_ids = [
+            merged.recovery_key.as_ref().map(|key| &key.id),
+            merged.passphrase_key.as_ref().map(|key| &key.id),
+        ];
+        for id in other_ids.into_iter().flatten() {
+            let recipient = current.iter().find_map(|config| {
+                config.master_key.as_ref().and_then(|mk| {
+                    mk.recipients
+                        .iter()
+                        .find(|wck| wck.credential_id == *id)
+                        .cloned()
+                })
+            });
+            recipients.extend(recipient);
+        }
+
+        if self.master_key.is_some() || other.master_key.is_some() {
+            merged.master_key = Some(WrappedMasterKey { recipients });
+        }
+
+        merged
+    }
+
+    /// The name of each entry in [Self::contents], by entry key.
+    async fn entry_names<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        master_key: Option<&MasterKey>,
+    ) -> Result<NameIndex, Error> {
+        match master_key {
+            Some(master_key) => self.decrypt_names(backend, master_key).await,
+            None if !self.has_encrypted_names() => Ok(NameIndex {
+                names: self
+                    .contents
+                    .keys()
+                    .map(|name| (name.clone(), name.clone()))
+                    .collect(),
+            }),
+            None => Err(Error::InvalidInput(
+                "The vault must be unlocked to read entry names".to_string(),
+            )),
+        }
+    }
+
+    /// Compare this vault with the result of [Self::sync_merge] with
+    /// `imported`, an export of the same vault from another device.
+    /// `master_key` is needed if entry names are encrypted.
+    pub async fn merge_preview<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        imported: &VaultConfig,
+        master_key: Option<&MasterKey>,
+    ) -> Result<MergePreview, Error> {
+        let merged = self.sync_merge(backend, imported, master_key).await?;
+        let local_names = self.entry_names(backend, master_key).await?;
+        let merged_names = merged.entry_names(backend, master_key).await?;
+
+        let entry = |key: &String, names: &NameIndex| MergeEntry {
+            key: key.clone(),
+            name: names.name(key).unwrap_or(key).to_string(),
+        };
+        let sorted = |mut entries: Vec<MergeEntry>| {
+            entries.sort_by(|a, b| (&a.name, &a.key).cmp(&(&b.name, &b.key)));
+            entries
+        };
+
+        let credential_labels = |from: &VaultConfig, to: &VaultConfig| -> Vec<String> {
+            from.user
+                .keypairs
+                .iter()
+                .filter_map(|wkp| {
+                    let cred_id = wkp.additional_data().ok()?.credential_id();
+                    if to.credential_index(&cred_id).is_some() {
+                        None
+                    } else {
+                        Some(
+                            wkp.nickname
+                                .clone()
+                                .unwrap_or_else(|| cred_id.b64_abbrev(24)),
+                        )
+                    }
+                })
+                .collect()
+        };
+
+        Ok(MergePreview {
+            added_credentials: credential_labels(&merged, self),
+            removed_credentials: credential_labels(self, &merged),
+            added_entries: sorted(
+                merged
+                    .contents
+                    .keys()
+                    .filter(|key| !self.contents.contains_key(*key))
+                    .map(|key| entry(key, &merged_names))
+                    .collect(),
+            ),
+            removed_entries: sorted(
+                self.contents
+                    .keys()
+                    .filter(|key| !merged.contents.contains_key(*key))
+                    .map(|key| entry(key, &local_names))
+                    .collect(),
+            ),
+            conflicts: sorted(
+                self.contents
+                    .iter()
+                    .filter(|(key, content)| {
+                        merged
+                            .contents
+                            .get(*key)
+                            .is_some_and(|merged| merged != *content)
+                    })
+                    .map(|(key, _)| entry(key, &local_names))
+                    .collect(),
+            ),
+            merged,
+        })
+    }
+
+    /// Whether taking `merged`, the result of [Self::sync_merge], would delete
+    /// entries of this vault, or change its credentials or the recipients of
+    /// its master key. A sync should not apply such changes without showing
+    /// them in a [MergePreview] first.
+    pub fn needs_review(&self, merged: &VaultConfig) -> bool {
+        self.contents
+            .keys()
+            .any(|key| !merged.contents.contains_key(key))
+            || self.user.keypairs != merged.user.keypairs
+            || self.master_key.as_ref().map(|mk| &mk.recipients)
+                != merged.master_key.as_ref().map(|mk| &mk.recipients)
+    }
+
+    fn credential_index(&self, cred_id: &CredentialId) -> Option<usize> {
+        self.user.keypairs.iter().position(|wkp| {
+            wkp.additional_data()
+                .is_ok_and(|ad| ad.credential_id() == *cred_id)
+        })
+    }
+
+    /// The merged vault of `preview`, with the entries resolved as
+    /// [MergeResolution::KeepLocal] in `resolutions`, by entry key, restored
+    /// to their local state. The restored entries get a new revision, so that
+    /// they also win over the imported state when merged again later.
+    pub async fn merge<B: CryptoBackend>(
+        &self,
+        backend: &B,
+        preview: &MergePreview,
+        resolutions: &HashMap<String, MergeResolution>,
+        master_key: Option<&MasterKey>,
+    ) -> Result<VaultConfig, Error> {
+        let mut merged = preview.merged.clone();
+        let mut names = merged.entry_names(backend, master_key).await?;
+        let local_names = self.entry_names(backend, master_key).await?;
+
+        let kept_local = preview
+            .added_entries
+            .iter()
+            .chain(&preview.removed_entries)
+            .chain(&preview.conflicts)
+            .filter(|entry| resolutions.get(&entry.key) == Some(&MergeResolution::KeepLocal));
+        for MergeEntry { key, name } in kept_local {
+            let revision = self.revisions.entry(key).max(merged.revisions.entry(key));
+            match self.contents.get(key) {
+                Some(content)
+                    if self.revisions.master_key != merged.revisions.master_key
+                        && matches!(content.key, ContentKey::MasterKey { .. }) =>
+                {
+                    return Err(Error::InvalidInput(format!(
+                        "\"{name}\" is encrypted under a master key that has since been replaced"
+                    )));
+                }
+                Some(content) => {
+                    merged.contents.insert(key.clone(), Rc::clone(content));
+                    if let Some(name) = local_names.name(key) {
+                        names.names.insert(key.clone(), name.to_string());
+                    }
+                }
+                None => {
+                    merged.contents.remove(key);
+                    names.names.remove(key);
+                }
+            }
+            let deleted = !merged.contents.contains_key(key);
+            merged
+                .revisions
+                .entries
+                .insert(key.clone(), revision.next(deleted)?);
+        }
+
+        if merged.has_encrypted_names() {
+            let master_key = master_key.ok_or_else(|| {
+                Error::InvalidInput("The vault must be unlocked to merge entry names".to_string())
+            })?;
+            merged.store_names(backend, &names, master_key).await?;
+        }
+
+        Ok(merged)
+    }
+}
+
+/// How to resolve an entry listed in a [MergePreview].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MergeResolution {
+    /// Keep the entry as it is in the local vault, or keep it absent.
+    KeepLocal,
+
+    /// Take the entry as merged by revision, which is the default.
+    TakeMerged,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergeEntry {
+    /// The key of the entry in [VaultConfig::contents].
+    pub key: String,
+    pub name: String,
+}
+
+/// The differences between a local vault and its merge with an imported one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergePreview {
+    /// Names of the credentials the merge adds.
+    pub added_credentials: Vec<String>,
+
+    /// Names of the credentials the merge deletes, since they were deleted
+    /// on the other device.
+    pub removed_credentials: Vec<String>,
+
+    /// Entries the merge adds.
+    pub added_entries: Vec<MergeEntry>,
+
+    /// Entries the merge deletes, since they were deleted on the other device.
+    pub removed_entries: Vec<MergeEntry>,
+
+    /// Entries the merge replaces with a different version.
+    pub conflicts: Vec<MergeEntry>,
+
+    merged: VaultConfig,
+}
+
+impl MergePreview {
+    pub fn is_empty(&self) -> bool {
+        self.added_credentials.is_empty()
+            && self.removed_credentials.is_empty()
+            && self.added_entries.is_empty()
+            && self.removed_entries.is_empty()
+            && self.conflicts.is_empty()
+    }
+}
+
+/// Reported by [VaultConfig::reencrypt_all] after each entry.
+pub struct ReencryptProgress<'a> {
+    pub name: &'a str,
+    pub done: usize,
+    pub total: usize,
+    pub result: &'a Result<(), Error>,
+}
+
+#[derive(Debug, Default)]
+pub struct ReencryptReport {
+    pub reencrypted: Vec<String>,
+    pub failed: Vec<(String, Error)>,
+}
+
+/// The entries affected by [VaultConfig::revoke_credential], by key in
+/// [VaultConfig::contents].
+#[derive(Debug, Default)]
+pub struct RevokeReport {
+    /// Entries encrypted again with a fresh content key.
+    pub rotated: Vec<String>,
+
+    /// Entries that only lost the revoked credential's key. Old copies of
+    /// them can still be decrypted with it.
+    pub failed: Vec<(String, Error)>,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -98,7 +1939,7 @@ pub struct UserConfig {
 }
 
 impl UserConfig {
-    pub async fn new(username: String) -> Result<Self, JsValue> {
+    pub async fn new(username: String) -> Result<Self, Error> {
         Ok(Self {
             version: 2,
             username,
@@ -115,7 +1956,7 @@ impl UserConfig {
         )
     }
 
-    pub fn webauthn_credential_descriptors(&self) -> Result<Array, JsOrSerdeError> {
+    pub fn webauthn_credential_descriptors(&self) -> Result<Array, Error> {
         self.keypairs
             .iter()
             .map(|wkp| {
@@ -1,37 +1,110 @@
 use js_sys::Object;
+use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
+use web_sys::DomException;
 
 #[derive(Debug)]
-pub enum JsOrSerdeError {
+pub enum Error {
+    /// The browser or authenticator did not return a WebAuthn PRF output.
+    PrfUnsupported,
+
+    /// None of the keys in question are wrapped to the credential that was used.
+    NoMatchingRecipient,
+
+    /// The WebAuthn ceremony was cancelled or timed out (`NotAllowedError`).
+    UserCancelled,
+
+    /// Authenticated decryption failed: wrong key, or the data was modified.
+    AeadFailure,
+
+    /// A stored record could not be parsed or is not valid.
+    MalformedRecord(String),
+
+    /// The vault was saved in a format version newer than this build supports.
+    NewerVersion(u32),
+
+    /// The browser refused to store the vault (`QuotaExceededError`).
+    QuotaExceeded,
+
+    /// The storage backend is not available in this browser.
+    StorageUnavailable,
+
+    /// An argument or setting is out of its valid range.
+    InvalidInput(String),
+
+    /// A cryptographic operation failed for a reason other than the above.
+    CryptoError(String),
+
+    /// The vault was changed elsewhere since it was read.
+    Conflict,
+
+    /// Fewer keys were unlocked than a threshold-split content key needs.
+    NotEnoughKeys { unlocked: usize, needed: usize },
+
+    /// The sync server rejected a request or could not be reached.
+    SyncServer(String),
+
+    /// Any other JavaScript exception.
     JsError(JsValue),
-    SerializeError(serde_json::Error),
 }
 
-impl std::error::Error for JsOrSerdeError {}
+impl std::error::Error for Error {}
 
-impl std::fmt::Display for JsOrSerdeError {
+impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         match self {
+            Self::PrfUnsupported => write!(
+                f,
+                "The authenticator or browser does not support the WebAuthn PRF extension",
+            ),
+            Self::NoMatchingRecipient => {
+                write!(f, "Not encrypted to the credential that was used",)
+            }
+            Self::UserCancelled => write!(f, "Cancelled by the user",),
+            Self::AeadFailure => write!(f, "Decryption failed: wrong key or modified data",),
+            Self::MalformedRecord(msg) => write!(f, "Malformed record: {msg}",),
+            Self::NewerVersion(v) => write!(
+                f,
+                "The vault is in format version {v}, but this version of the app supports only up to version {}. Please use a newer version of the app.",
+                crate::data::migration::CURRENT_VERSION,
+            ),
+            Self::QuotaExceeded => write!(f, "Storage quota exceeded",),
+            Self::StorageUnavailable => write!(f, "Storage is not available",),
+            Self::InvalidInput(msg) => write!(f, "Invalid input: {msg}",),
+            Self::CryptoError(msg) => write!(f, "Cryptographic operation failed: {msg}",),
+            Self::Conflict => write!(f, "The vault was changed elsewhere, please try again",),
+            Self::NotEnoughKeys { unlocked, needed } => write!(
+                f,
+                "Unlocked {unlocked} of the {needed} keys needed to decrypt",
+            ),
+            Self::SyncServer(msg) => write!(f, "Sync server error: {msg}",),
             Self::JsError(js_value) => write!(f, "JavaScript error: {js_value:?}",),
-            Self::SerializeError(err) => write!(f, "Serialization failed: {err}",),
         }
     }
 }
 
-impl From<JsValue> for JsOrSerdeError {
+impl From<JsValue> for Error {
     fn from(err: JsValue) -> Self {
-        Self::JsError(err)
+        match err
+            .dyn_ref::<DomException>()
+            .map(DomException::name)
+            .as_deref()
+        {
+            Some("NotAllowedError") => Self::UserCancelled,
+            Some("QuotaExceededError") => Self::QuotaExceeded,
+            _ => Self::JsError(err),
+        }
     }
 }
 
-impl From<Object> for JsOrSerdeError {
+impl From<Object> for Error {
     fn from(err: Object) -> Self {
         Self::from(JsValue::from(err))
     }
 }
 
-impl From<serde_json::Error> for JsOrSerdeError {
+impl From<serde_json::Error> for Error {
     fn from(err: serde_json::Error) -> Self {
-        Self::SerializeError(err)
+        Self::MalformedRecord(err.to_string())
     }
 }
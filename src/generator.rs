@@ -0,0 +1,219 @@
+//! Random password and passphrase generation.
+//!
+//! All randomness comes from [CryptoBackend::random_bytes], and indices are
+//! drawn by rejection sampling so that every character or word is equally
+//! likely.
+
+use crate::crypto::CryptoBackend;
+use crate::error::Error;
+
+/// The [BIP-39] English wordlist: 2048 words, each identified by its first
+/// four letters.
+///
+/// [BIP-39]: https://github.com/bitcoin/bips/blob/master/bip-0039/english.txt
+const WORDLIST: &str = include_str!("generator/wordlist.txt");
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// Characters that are easily confused with each other in common fonts.
+const LOOKALIKES: &str = "Il1|O0o`'\"";
+
+pub fn wordlist() -> impl Iterator<Item = &'static str> {
+    WORDLIST.lines()
+}
+
+/// Draw a uniformly random integer in `0..n`.
+pub fn random_below<B: CryptoBackend>(backend: &B, n: u32) -> Result<u32, Error> {
+    if n == 0 {
+        return Err(Error::InvalidInput(
+            "Cannot choose from an empty range".to_string(),
+        ));
+    }
+    // Reject the values in the incomplete last multiple of n to avoid modulo bias
+    let limit = u32::MAX - (u32::MAX % n + 1) % n;
+    loop {
+        let r = u32::from_le_bytes(backend.random()?);
+        if r <= limit {
+            return Ok(r % n);
+        }
+    }
+}
+
+fn choose<'a, B: CryptoBackend, T>(backend: &B, items: &'a [T]) -> Result<&'a T, Error> {
+    let n = u32::try_from(items.len())
+        .map_err(|_| Error::InvalidInput("Too many items to choose from".to_string()))?;
+    Ok(&items[random_below(backend, n)? as usize])
+}
+
+/// A generated secret and the base-2 logarithm of the number of equally
+/// likely secrets the generator could have produced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Generated {
+    pub value: String,
+    pub entropy_bits: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PasswordPolicy {
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+
+    /// Leave out characters in [LOOKALIKES].
+    pub exclude_lookalikes: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            length: 20,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            exclude_lookalikes: false,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// The character set of each enabled class. Each of them must occur at
+    /// least once in a generated password.
+    pub fn classes(&self) -> Vec<Vec<char>> {
+        [
+            (self.lowercase, LOWERCASE),
+            (self.uppercase, UPPERCASE),
+            (self.digits, DIGITS),
+            (self.symbols, SYMBOLS),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, chars)| {
+            chars
+                .chars()
+                .filter(|c| !(self.exclude_lookalikes && LOOKALIKES.contains(*c)))
+                .collect()
+        })
+        .collect()
+    }
+
+    /// The base-2 logarithm of the number of possible passwords, counted by
+    /// inclusion-exclusion over the classes that could be missing. The count
+    /// is normalized by `total^length` to stay within `f64` range.
+    fn entropy_bits(&self, classes: &[Vec<char>]) -> f64 {
+        let total: usize = classes.iter().map(Vec::len).sum();
+        let fraction_valid: f64 = (0..1_u32 << classes.len())
+            .map(|missing| {
+                let excluded: usize = classes
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| missing & (1 << i) != 0)
+                    .map(|(_, class)| class.len())
+                    .sum();
+                let sign = if missing.count_ones() % 2 == 0 {
+                    1.0
+                } else {
+                    -1.0
+                };
+                sign * ((total - excluded) as f64 / total as f64).powi(self.length as i32)
+            })
+            .sum();
+        self.length as f64 * (total as f64).log2() + fraction_valid.log2()
+    }
+}
+
+/// Generate a password of characters drawn uniformly from the classes enabled
+/// in `policy`, containing at least one character of each class.
+pub fn generate_password<B: CryptoBackend>(
+    backend: &B,
+    policy: &PasswordPolicy,
+) -> Result<Generated, Error> {
+    let classes = policy.classes();
+    if classes.is_empty() {
+        return Err(Error::InvalidInput(
+            "At least one character class must be enabled".to_string(),
+        ));
+    }
+    if policy.length < classes.len() || policy.length > 1024 {
+        return Err(Error::InvalidInput(format!(
+            "Length must be between {} and 1024",
+            classes.len()
+        )));
+    }
+
+    let alphabet: Vec<char> = classes.iter().flatten().copied().collect();
+
+    // Reject whole candidates that miss a class, so that all valid passwords
+    // stay equally likely
+    loop {
+        let candidate = (0..policy.length)
+            .map(|_| choose(backend, &alphabet).copied())
+            .collect::<Result<String, Error>>()?;
+        if classes
+            .iter()
+            .all(|class| candidate.chars().any(|c| class.contains(&c)))
+        {
+            return Ok(Generated {
+                value: candidate,
+                entropy_bits: policy.entropy_bits(&classes),
+            });
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassphrasePolicy {
+    pub words: usize,
+    pub separator: String,
+    pub capitalize: bool,
+}
+
+impl Default for PassphrasePolicy {
+    fn default() -> Self {
+        Self {
+            words: 6,
+            separator: "-".to_string(),
+            capitalize: false,
+        }
+    }
+}
+
+/// Generate a diceware-style passphrase of words drawn uniformly from
+/// [wordlist].
+pub fn generate_passphrase<B: CryptoBackend>(
+    backend: &B,
+    policy: &PassphrasePolicy,
+) -> Result<Generated, Error> {
+    if policy.words == 0 || policy.words > 64 {
+        return Err(Error::InvalidInput(
+            "Number of words must be between 1 and 64".to_string(),
+        ));
+    }
+
+    let words: Vec<&str> = wordlist().collect();
+    let value = (0..policy.words)
+        .map(|_| {
+            let word = *choose(backend, &words)?;
+            Ok(if policy.capitalize {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default()
+            } else {
+                word.to_string()
+            })
+        })
+        .collect::<Result<Vec<String>, Error>>()?
+        .join(&policy.separator);
+
+    Ok(Generated {
+        value,
+        entropy_bits: policy.words as f64 * (words.len() as f64).log2(),
+    })
+}
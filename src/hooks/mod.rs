@@ -1 +1,2 @@
-pub mod local_storage;
+pub mod session;
+pub mod storage;
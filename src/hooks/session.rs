@@ -0,0 +1,208 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::console;
+use web_sys::Document;
+use web_sys::Window;
+use yew::hook;
+use yew::use_effect_with_deps;
+use yew::use_mut_ref;
+use yew::use_state;
+use yew::UseStateHandle;
+
+use crate::crypto::ContentKey;
+use crate::crypto::EncryptedContent;
+use crate::crypto::MasterKey;
+use crate::crypto::UnlockedKey;
+use crate::crypto::WebCrypto;
+use crate::data::vault::VaultConfig;
+use crate::error::Error;
+
+/// The keys unwrapped by an unlock ceremony, kept in memory until the session
+/// is locked. The private key is a non-extractable `CryptoKey`.
+pub struct Session {
+    pub unlocked: UnlockedKey<WebCrypto>,
+    pub master_key: MasterKey,
+}
+
+impl Session {
+    /// Whether `content` can be decrypted without another WebAuthn ceremony.
+    pub fn can_decrypt(&self, content: &EncryptedContent) -> bool {
+        match &content.key {
+            ContentKey::MasterKey { .. } => true,
+            ContentKey::Recipients { recipients } => recipients
+                .iter()
+                .any(|wck| wck.credential_id == self.unlocked.credential_id),
+            ContentKey::Threshold { threshold, shares } => {
+                *threshold <= 1
+                    && shares
+                        .iter()
+                        .any(|share| share.key.credential_id == self.unlocked.credential_id)
+            }
+        }
+    }
+}
+
+struct IdleTimer {
+    window: Window,
+    handle: i32,
+    _callback: Closure<dyn FnMut()>,
+}
+
+impl Drop for IdleTimer {
+    fn drop(&mut self) {
+        self.window.clear_timeout_with_handle(self.handle);
+    }
+}
+
+type ListenerCallback = Closure<dyn FnMut()>;
+
+#[derive(Clone)]
+pub struct UseSessionHandle {
+    state: UseStateHandle<Option<Rc<Session>>>,
+    idle_timer: Rc<RefCell<Option<IdleTimer>>>,
+    idle_timeout: Duration,
+}
+
+impl PartialEq for UseSessionHandle {
+    fn eq(&self, other: &Self) -> bool {
+        self.state.as_ref().map(Rc::as_ptr) == other.state.as_ref().map(Rc::as_ptr)
+            && self.idle_timeout == other.idle_timeout
+    }
+}
+
+impl UseSessionHandle {
+    pub fn is_unlocked(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Get the current session, if unlocked, and restart the idle timer.
+    pub fn get(&self) -> Option<Rc<Session>> {
+        let session = self.state.as_ref().map(Rc::clone);
+        if session.is_some() {
+            self.restart_idle_timer();
+        }
+        session
+    }
+
+    /// Get the current session, or run a WebAuthn ceremony to unlock `config`
+    /// if there is none.
+    pub async fn unlock(&self, config: &VaultConfig) -> Result<Rc<Session>, Error> {
+        if let Some(session) = self.get() {
+            return Ok(session);
+        }
+
+        let (unlocked, master_key) = config.unlock().await?;
+        Ok(self.open(unlocked, master_key))
+    }
+
+    /// Start a session with keys unwrapped without a WebAuthn ceremony, such
+    /// as with a recovery phrase.
+    pub fn open(&self, unlocked: UnlockedKey<WebCrypto>, master_key: MasterKey) -> Rc<Session> {
+        let session = Rc::new(Session {
+            unlocked,
+            master_key,
+        });
+        self.state.set(Some(Rc::clone(&session)));
+        self.restart_idle_timer();
+        session
+    }
+
+    pub fn lock(&self) {
+        lock(&self.state, &self.idle_timer);
+    }
+
+    fn restart_idle_timer(&self) {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+
+        let callback: Closure<dyn FnMut()> = {
+            let state = self.state.clone();
+            Closure::new(move || {
+                console::log_1(&"Session idle timeout, locking.".into());
+                // Leave the timer in place: dropping it would drop this closure while it runs
+                state.set(None);
+            })
+        };
+
+        match window.set_timeout_with_callback_and_timeout_and_arguments_0(
+            callback.as_ref().unchecked_ref(),
+            self.idle_timeout.as_millis().try_into().unwrap_or(i32::MAX),
+        ) {
+            Ok(handle) => {
+                *self.idle_timer.borrow_mut() = Some(IdleTimer {
+                    window,
+                    handle,
+                    _callback: callback,
+                });
+            }
+            Err(err) => {
+                // Never leave a session open without a timeout
+                console::error_2(&"Failed to start session idle timer".into(), &err);
+                self.lock();
+            }
+        }
+    }
+}
+
+fn lock(state: &UseStateHandle<Option<Rc<Session>>>, idle_timer: &RefCell<Option<IdleTimer>>) {
+    idle_timer.borrow_mut().take();
+    state.set(None);
+}
+
+/// Keep an unlocked [Session] in memory, locking it after `idle_timeout`
+/// without use and whenever the page is hidden.
+#[hook]
+pub fn use_session(idle_timeout: Duration) -> UseSessionHandle {
+    let state: UseStateHandle<Option<Rc<Session>>> = use_state(|| None);
+    let idle_timer: Rc<RefCell<Option<IdleTimer>>> = use_mut_ref(|| None);
+
+    {
+        let state = state.clone();
+        let idle_timer = Rc::clone(&idle_timer);
+        use_effect_with_deps(
+            move |_| {
+                let document: Option<Document> = web_sys::window().and_then(|w| w.document());
+
+                let listener: Rc<ListenerCallback> = {
+                    let document = document.clone();
+                    let idle_timer = Rc::clone(&idle_timer);
+                    Rc::new(Closure::new(move || {
+                        if document.as_ref().map(Document::hidden).unwrap_or(true) {
+                            lock(&state, &idle_timer);
+                        }
+                    }))
+                };
+
+                if let Some(document) = &document {
+                    if let Err(err) = document.add_event_listener_with_callback(
+                        "visibilitychange",
+                        Closure::as_ref(Rc::as_ref(&listener)).unchecked_ref(),
+                    ) {
+                        console::error_2(&"Failed to add visibilitychange listener".into(), &err);
+                    }
+                }
+
+                move || {
+                    if let Some(document) = &document {
+                        let _ = document.remove_event_listener_with_callback(
+                            "visibilitychange",
+                            Closure::as_ref(Rc::as_ref(&listener)).unchecked_ref(),
+                        );
+                    }
+                    idle_timer.borrow_mut().take();
+                }
+            },
+            (),
+        );
+    }
+
+    UseSessionHandle {
+        state,
+        idle_timer,
+        idle_timeout,
+    }
+}
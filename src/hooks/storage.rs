@@ -0,0 +1,267 @@
+use std::cell::RefCell;
+use std::ops::Deref;
+use std::rc::Rc;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::console;
+use yew::hook;
+use yew::use_effect_with_deps;
+use yew::use_mut_ref;
+use yew::use_state;
+use yew::UseStateHandle;
+
+use crate::error::Error;
+use crate::storage::StorageBackend;
+use crate::storage::StoredValue;
+
+type ParseResult<T> = Result<Rc<T>, (String, Error)>;
+
+struct State<T> {
+    loaded: bool,
+    value: Option<ParseResult<T>>,
+
+    /// The generation of [Self::value] in storage.
+    generation: u64,
+}
+
+pub struct UseStorageHandle<T>
+where
+    T: StoredValue,
+{
+    storage: Rc<dyn StorageBackend>,
+    name: &'static str,
+    state: UseStateHandle<State<T>>,
+
+    /// The generation of the latest value read or written. A handle whose
+    /// state is of an older generation is a stale snapshot.
+    latest_generation: Rc<RefCell<u64>>,
+
+    write_error: UseStateHandle<Option<String>>,
+}
+
+impl<T> Clone for UseStorageHandle<T>
+where
+    T: StoredValue,
+{
+    fn clone(&self) -> Self {
+        Self {
+            storage: Rc::clone(&self.storage),
+            name: self.name,
+            state: self.state.clone(),
+            latest_generation: Rc::clone(&self.latest_generation),
+            write_error: self.write_error.clone(),
+        }
+    }
+}
+
+impl<T> Deref for UseStorageHandle<T>
+where
+    T: StoredValue,
+{
+    type Target = Option<ParseResult<T>>;
+    fn deref(&self) -> &Self::Target {
+        &self.state.value
+    }
+}
+
+impl<T> UseStorageHandle<T>
+where
+    T: StoredValue + 'static,
+{
+    /// Whether the stored value has been read yet. Until then, the value is
+    /// `None` even if one is stored.
+    pub fn is_loaded(&self) -> bool {
+        self.state.loaded
+    }
+
+    pub fn ok(&self) -> Option<Rc<T>> {
+        self.as_ref()
+            .and_then(|res| res.as_ref().ok().map(Rc::clone))
+    }
+
+    /// Why the last rejected write was rejected, such as a conflict with a
+    /// change made in another tab.
+    pub fn write_error(&self) -> Option<&str> {
+        self.write_error.as_deref()
+    }
+
+    pub fn clear_write_error(&self) {
+        self.write_error.set(None);
+    }
+
+    pub fn set(&self, value: Option<T>) -> Result<(), Error> {
+        self.set_with_rc(value.map(Rc::new))
+    }
+
+    /// Set the value, and write it to storage in the background. The new value
+    /// must be based on the value of this handle: if that has been replaced
+    /// since, in this tab or another, the write fails with [Error::Conflict].
+    pub fn set_with_rc(&self, value: Option<Rc<T>>) -> Result<(), Error> {
+        let value_str = value.as_ref().map(serde_json::to_string).transpose()?;
+        self.set_state(value.map(Ok), value_str)
+    }
+
+    pub fn set_from_str(&self, value_str: &str) -> Result<(), Error> {
+        let value = T::parse(value_str)?;
+        self.set_state(Some(Ok(Rc::new(value))), Some(value_str.to_string()))
+    }
+
+    fn set_state(
+        &self,
+        value: Option<ParseResult<T>>,
+        value_str: Option<String>,
+    ) -> Result<(), Error> {
+        let generation = self.state.generation;
+        if *self.latest_generation.borrow() != generation {
+            self.write_error.set(Some(Error::Conflict.to_string()));
+            return Err(Error::Conflict);
+        }
+
+        // Assume the write succeeds, until storage reports the generation
+        // it actually stored
+        let new_generation = if value_str.is_some() {
+            generation + 1
+        } else {
+            0
+        };
+        let written = value
+            .as_ref()
+            .and_then(|value| value.as_ref().ok())
+            .map(Rc::clone);
+        *self.latest_generation.borrow_mut() = new_generation;
+        self.state.set(State {
+            loaded: true,
+            value,
+            generation: new_generation,
+        });
+        self.write(written, value_str, generation, new_generation);
+        Ok(())
+    }
+
+    /// Write `value_str`, the serialization of `value`, to storage if it is
+    /// still at `generation`, or delete the stored value if `None`. The state
+    /// was set to `value` at `expected_generation`; if storage stores another
+    /// generation, the state gets that one. If the write fails, reload the
+    /// stored value so the state is what is stored.
+    fn write(
+        &self,
+        value: Option<Rc<T>>,
+        value_str: Option<String>,
+        generation: u64,
+        expected_generation: u64,
+    ) {
+        let handle = self.clone();
+        spawn_local(async move {
+            let result = match &value_str {
+                Some(value_str) => handle
+                    .storage
+                    .put(handle.name, value_str, generation)
+                    .await
+                    .map(|stored_generation| {
+                        handle.stored(value, expected_generation, stored_generation)
+                    }),
+                None => handle.storage.delete(handle.name).await,
+            };
+            let msg = format!("Failed to write \"{}\" to storage:", handle.name);
+            match result {
+                Ok(()) => return,
+                Err(Error::JsError(e)) => {
+                    console::error_2(&msg.into(), &e);
+                    handle.write_error.set(Some(format!("{e:?}")));
+                }
+                Err(err) => {
+                    console::error_2(&msg.into(), &err.to_string().into());
+                    handle.write_error.set(Some(err.to_string()));
+                }
+            }
+            handle.reload();
+        });
+    }
+
+    /// Record that `value`, set in the state at `expected_generation`, was
+    /// stored at `stored_generation`, unless the state has been replaced since.
+    fn stored(&self, value: Option<Rc<T>>, expected_generation: u64, stored_generation: u64) {
+        let mut latest_generation = self.latest_generation.borrow_mut();
+        if *latest_generation != expected_generation || stored_generation == expected_generation {
+            return;
+        }
+        *latest_generation = stored_generation;
+        self.state.set(State {
+            loaded: true,
+            value: value.map(Ok),
+            generation: stored_generation,
+        });
+    }
+
+    fn reload(&self) {
+        let handle = self.clone();
+        spawn_local(async move {
+            let (value, generation) = match handle.storage.get(handle.name).await {
+                Ok(Some(stored)) => (Self::deserialize(stored.value), stored.generation),
+                Ok(None) => (None, 0),
+                Err(err) => (Some(Err((String::new(), err))), 0),
+            };
+            *handle.latest_generation.borrow_mut() = generation;
+            handle.state.set(State {
+                loaded: true,
+                value,
+                generation,
+            });
+        });
+    }
+
+    fn deserialize(s: String) -> Option<ParseResult<T>> {
+        Some(T::parse(&s).map(Rc::new).map_err(|err| (s, err)))
+    }
+}
+
+/// The value stored under `name` in `storage`, kept up to date with changes
+/// made in other tabs.
+#[hook]
+pub fn use_storage<T>(storage: Rc<dyn StorageBackend>, name: &'static str) -> UseStorageHandle<T>
+where
+    T: StoredValue,
+    T: 'static,
+{
+    let state = use_state(|| State {
+        loaded: false,
+        value: None,
+        generation: 0,
+    });
+    let latest_generation = use_mut_ref(|| 0);
+    let write_error = use_state(|| None);
+    let handle = UseStorageHandle {
+        storage,
+        name,
+        state,
+        latest_generation,
+        write_error,
+    };
+
+    {
+        let handle = handle.clone();
+        use_effect_with_deps(
+            move |_| {
+                handle.reload();
+                let subscription = {
+                    let storage = Rc::clone(&handle.storage);
+                    let handle = handle.clone();
+                    storage.subscribe(Rc::new(move |key: &str| {
+                        if key == handle.name {
+                            handle.reload();
+                        }
+                    }))
+                };
+                if let Err(err) = &subscription {
+                    console::error_2(
+                        &format!("Failed to subscribe to changes of \"{name}\"").into(),
+                        &err.to_string().into(),
+                    );
+                }
+                move || drop(subscription)
+            },
+            (),
+        );
+    }
+
+    handle
+}
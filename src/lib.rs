@@ -3,5 +3,8 @@ pub mod config;
 pub mod crypto;
 pub mod data;
 pub mod error;
+pub mod generator;
 pub mod hooks;
+pub mod storage;
+pub mod sync;
 pub mod webauthn;
@@ -0,0 +1,73 @@
+use std::rc::Rc;
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::error::Error;
+
+/// A stored value and its generation, which every write increments. No stored
+/// value counts as generation 0.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Stored {
+    pub value: String,
+    pub generation: u64,
+}
+
+/// A value that can be kept in storage. Parsing a stored value may do more
+/// than deserialize it, such as upgrade it from an older format.
+pub trait StoredValue: Serialize + Sized {
+    fn parse(s: &str) -> Result<Self, Error>;
+}
+
+/// A persistent key-value store of strings.
+///
+/// Writes are conditional on the generation of the value they replace, so
+/// that a change based on an outdated value is rejected instead of silently
+/// overwriting a change made in another tab.
+///
+/// Implementations notify subscribers of values changed elsewhere, such as in
+/// another tab, so that every tab shows the same vault. Changes made through
+/// the same instance may or may not be notified too.
+#[async_trait(?Send)]
+pub trait StorageBackend {
+    async fn get(&self, key: &str) -> Result<Option<Stored>, Error>;
+
+    /// Store `value` under `key` and return its new generation, if the stored
+    /// generation is still `generation`. Fails with [Error::Conflict] if not.
+    async fn put(&self, key: &str, value: &str, generation: u64) -> Result<u64, Error>;
+
+    /// Delete the value under `key`, whatever its generation.
+    async fn delete(&self, key: &str) -> Result<(), Error>;
+
+    /// The largest file, in bytes, that can be attached to a vault kept in
+    /// this storage. The whole vault is stored as one value, in which a file
+    /// takes up about twice its size once encrypted and base64 encoded. A
+    /// synced vault must also fit [crate::sync::MAX_VAULT_SIZE] as a whole.
+    fn max_file_size(&self) -> usize;
+
+    /// Call `listener` with the key of each changed value, until the returned
+    /// [Subscription] is dropped.
+    fn subscribe(&self, listener: Rc<dyn Fn(&str)>) -> Result<Subscription, Error>;
+}
+
+/// Keeps a [StorageBackend::subscribe] listener registered until dropped.
+#[must_use]
+pub struct Subscription {
+    unsubscribe: Option<Box<dyn FnOnce()>>,
+}
+
+impl Subscription {
+    pub fn new(unsubscribe: impl FnOnce() + 'static) -> Self {
+        Self {
+            unsubscribe: Some(Box::new(unsubscribe)),
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(unsubscribe) = self.unsubscribe.take() {
+            unsubscribe();
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::rc::Rc;
+
+use async_trait::async_trait;
+use js_sys::Object;
+use js_sys::Promise;
+use js_sys::Reflect;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::console;
+use web_sys::BroadcastChannel;
+use web_sys::IdbDatabase;
+use web_sys::IdbObjectStore;
+use web_sys::IdbRequest;
+use web_sys::IdbTransaction;
+use web_sys::IdbTransactionMode;
+use web_sys::MessageEvent;
+
+use crate::error::Error;
+
+use super::StorageBackend;
+use super::Stored;
+use super::Subscription;
+
+const DB_NAME: &str = "passwordless-passwords-prf";
+const DB_VERSION: u32 = 1;
+const STORE_NAME: &str = "values";
+
+/// IndexedDB allows far more, but the whole vault is rewritten on every change
+/// and must also fit in the sync server's size limit.
+const MAX_FILE_SIZE: usize = 8 * 1024 * 1024;
+
+/// Tabs announce the keys they change on this channel, since IndexedDB itself
+/// has no change events.
+const CHANNEL_NAME: &str = "passwordless-passwords-prf-storage";
+
+/// The browser's IndexedDB, which allows much more data than `localStorage`.
+/// Values are stored as records with `value` and `generation` properties.
+pub struct IndexedDb {
+    db: IdbDatabase,
+    channel: BroadcastChannel,
+}
+
+/// Wait for `request` to succeed and return its result.
+async fn await_request(request: &IdbRequest) -> Result<JsValue, Error> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    match JsFuture::from(promise).await {
+        Ok(_) => Ok(request.result()?),
+        Err(err) => Err(request.error()?.map(JsValue::from).unwrap_or(err).into()),
+    }
+}
+
+/// Wait for `transaction` to be committed.
+async fn await_transaction(transaction: &IdbTransaction) -> Result<(), Error> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        transaction.set_oncomplete(Some(&resolve));
+        transaction.set_onerror(Some(&reject));
+        transaction.set_onabort(Some(&reject));
+    });
+    match JsFuture::from(promise).await {
+        Ok(_) => Ok(()),
+        Err(err) => Err(transaction.error().map(JsValue::from).unwrap_or(err).into()),
+    }
+}
+
+fn to_record(value: &str, generation: u64) -> Result<JsValue, Error> {
+    let record = Object::new();
+    Reflect::set(&record, &"value".into(), &value.into())?;
+    Reflect::set(&record, &"generation".into(), &(generation as f64).into())?;
+    Ok(record.into())
+}
+
+fn from_record(record: JsValue) -> Result<Option<Stored>, Error> {
+    if record.is_undefined() {
+        return Ok(None);
+    }
+    let value = Reflect::get(&record, &"value".into())?
+        .as_string()
+        .ok_or_else(|| Error::MalformedRecord("Stored value is not a string".to_string()))?;
+    let generation = Reflect::get(&record, &"generation".into())?
+        .as_f64()
+        .ok_or_else(|| Error::MalformedRecord("Stored generation is not a number".to_string()))?;
+    Ok(Some(Stored {
+        value,
+        generation: generation as u64,
+    }))
+}
+
+impl IndexedDb {
+    pub async fn open() -> Result<Self, Error> {
+        let factory = web_sys::window()
+            .ok_or(Error::StorageUnavailable)?
+            .indexed_db()?
+            .ok_or(Error::StorageUnavailable)?;
+        let request = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+
+        let on_upgrade_needed: Closure<dyn FnMut()> = {
+            let request = request.clone();
+            Closure::new(move || {
+                let result = request
+                    .result()
+                    .and_then(|db| db.dyn_into::<IdbDatabase>())
+                    .and_then(|db| {
+                        if db.object_store_names().contains(STORE_NAME) {
+                            Ok(())
+                        } else {
+                            db.create_object_store(STORE_NAME).map(|_| ())
+                        }
+                    });
+                if let Err(err) = result {
+                    console::error_2(&"Failed to upgrade IndexedDB database:".into(), &err);
+                }
+            })
+        };
+        request.set_onupgradeneeded(Some(on_upgrade_needed.as_ref().unchecked_ref()));
+        let db = await_request(&request).await;
+        request.set_onupgradeneeded(None);
+
+        Ok(Self {
+            db: db?.dyn_into()?,
+            channel: BroadcastChannel::new(CHANNEL_NAME)?,
+        })
+    }
+
+    fn store(&self, mode: IdbTransactionMode) -> Result<(IdbTransaction, IdbObjectStore), Error> {
+        let transaction = self.db.transaction_with_str_and_mode(STORE_NAME, mode)?;
+        let store = transaction.object_store(STORE_NAME)?;
+        Ok((transaction, store))
+    }
+}
+
+#[async_trait(?Send)]
+impl StorageBackend for IndexedDb {
+    async fn get(&self, key: &str) -> Result<Option<Stored>, Error> {
+        let (_, store) = self.store(IdbTransactionMode::Readonly)?;
+        from_record(await_request(&store.get(&JsValue::from_str(key))?).await?)
+    }
+
+    async fn put(&self, key: &str, value: &str, generation: u64) -> Result<u64, Error> {
+        // Check and write in one transaction, so no other tab can write between
+        let (transaction, store) = self.store(IdbTransactionMode::Readwrite)?;
+        let stored = from_record(await_request(&store.get(&JsValue::from_str(key))?).await?)?;
+        if stored.map_or(0, |stored| stored.generation) != generation {
+            transaction.abort()?;
+            return Err(Error::Conflict);
+        }
+        store.put_with_key(&to_record(value, generation + 1)?, &JsValue::from_str(key))?;
+        await_transaction(&transaction).await?;
+        self.channel.post_message(&JsValue::from_str(key))?;
+        Ok(generation + 1)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        let (transaction, store) = self.store(IdbTransactionMode::Readwrite)?;
+        store.delete(&JsValue::from_str(key))?;
+        await_transaction(&transaction).await?;
+        self.channel.post_message(&JsValue::from_str(key))?;
+        Ok(())
+    }
+
+    fn max_file_size(&self) -> usize {
+        MAX_FILE_SIZE
+    }
+
+    /// Only changes made through other instances, usually in other tabs, are
+    /// notified.
+    fn subscribe(&self, listener: Rc<dyn Fn(&str)>) -> Result<Subscription, Error> {
+        let callback: Closure<dyn FnMut(MessageEvent)> = Closure::new(move |e: MessageEvent| {
+            if let Some(key) = e.data().as_string() {
+                listener(&key);
+            }
+        });
+        self.channel
+            .add_event_listener_with_callback("message", callback.as_ref().unchecked_ref())?;
+
+        let channel = self.channel.clone();
+        Ok(Subscription::new(move || {
+            let _ = channel
+                .remove_event_listener_with_callback("message", callback.as_ref().unchecked_ref());
+        }))
+    }
+}
@@ -0,0 +1,112 @@
+use std::rc::Rc;
+
+use async_trait::async_trait;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::Storage;
+use web_sys::StorageEvent;
+use web_sys::Window;
+
+use crate::error::Error;
+
+use super::StorageBackend;
+use super::Stored;
+use super::Subscription;
+
+/// Browsers allow about 5 million characters per origin, shared by the whole
+/// vault.
+const MAX_FILE_SIZE: usize = 1024 * 1024;
+
+/// The browser's `localStorage`. Simple and synchronous, but limited to a few
+/// megabytes per origin.
+///
+/// The generation of each value is kept under the key with a `.generation`
+/// suffix. `localStorage` has no transactions, so a write in another tab can
+/// in principle slip in between checking the generation and writing.
+pub struct LocalStorage {
+    window: Window,
+    storage: Storage,
+}
+
+fn generation_key(key: &str) -> String {
+    format!("{key}.generation")
+}
+
+impl LocalStorage {
+    pub fn new() -> Result<Self, Error> {
+        let window = web_sys::window().ok_or(Error::StorageUnavailable)?;
+        let storage = window.local_storage()?.ok_or(Error::StorageUnavailable)?;
+        Ok(Self { window, storage })
+    }
+
+    fn generation(&self, key: &str) -> Result<u64, Error> {
+        if self.storage.get_item(key)?.is_none() {
+            return Ok(0);
+        }
+        // Values written before generations were recorded are generation 1
+        Ok(self
+            .storage
+            .get_item(&generation_key(key))?
+            .and_then(|generation| generation.parse().ok())
+            .unwrap_or(1))
+    }
+}
+
+#[async_trait(?Send)]
+impl StorageBackend for LocalStorage {
+    async fn get(&self, key: &str) -> Result<Option<Stored>, Error> {
+        let generation = self.generation(key)?;
+        Ok(self
+            .storage
+            .get_item(key)?
+            .map(|value| Stored { value, generation }))
+    }
+
+    async fn put(&self, key: &str, value: &str, generation: u64) -> Result<u64, Error> {
+        if self.generation(key)? != generation {
+            return Err(Error::Conflict);
+        }
+        // The generation goes first, so that other tabs notified of the new
+        // value read its generation too
+        let generation_key = generation_key(key);
+        let previous = self.storage.get_item(&generation_key)?;
+        self.storage
+            .set_item(&generation_key, &(generation + 1).to_string())?;
+        if let Err(err) = self.storage.set_item(key, value) {
+            // Such as when the value exceeds the quota: keep the generation of
+            // the value that is still stored
+            let _ = match previous {
+                Some(previous) => self.storage.set_item(&generation_key, &previous),
+                None => self.storage.remove_item(&generation_key),
+            };
+            return Err(err.into());
+        }
+        Ok(generation + 1)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.storage.remove_item(key)?;
+        Ok(self.storage.remove_item(&generation_key(key))?)
+    }
+
+    fn max_file_size(&self) -> usize {
+        MAX_FILE_SIZE
+    }
+
+    /// Only changes made in other tabs are notified.
+    fn subscribe(&self, listener: Rc<dyn Fn(&str)>) -> Result<Subscription, Error> {
+        let callback: Closure<dyn FnMut(StorageEvent)> = Closure::new(move |e: StorageEvent| {
+            if let Some(key) = e.key().filter(|key| !key.ends_with(".generation")) {
+                listener(&key);
+            }
+        });
+        self.window
+            .add_event_listener_with_callback("storage", callback.as_ref().unchecked_ref())?;
+
+        let window = self.window.clone();
+        Ok(Subscription::new(move || {
+            let _ = window
+                .remove_event_listener_with_callback("storage", callback.as_ref().unchecked_ref());
+        }))
+    }
+}
@@ -0,0 +1,98 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use async_trait::async_trait;
+
+use crate::error::Error;
+
+use super::StorageBackend;
+use super::Stored;
+use super::Subscription;
+
+type Listener = Rc<dyn Fn(&str)>;
+
+/// The same as for IndexedDB, so tests see the same limit as most users.
+const MAX_FILE_SIZE: usize = 8 * 1024 * 1024;
+
+#[derive(Default)]
+struct Inner {
+    values: HashMap<String, Stored>,
+    listeners: Vec<(u64, Listener)>,
+    next_listener_id: u64,
+}
+
+/// Storage that is lost when the page is closed, for tests. Clones share the
+/// same values, and every change is notified to every subscriber.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl MemoryStorage {
+    fn notify(&self, key: &str) {
+        // Release the borrow before calling out, since listeners may read
+        let listeners: Vec<Listener> = self
+            .inner
+            .borrow()
+            .listeners
+            .iter()
+            .map(|(_, listener)| Rc::clone(listener))
+            .collect();
+        for listener in listeners {
+            listener(key);
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl StorageBackend for MemoryStorage {
+    async fn get(&self, key: &str) -> Result<Option<Stored>, Error> {
+        Ok(self.inner.borrow().values.get(key).cloned())
+    }
+
+    async fn put(&self, key: &str, value: &str, generation: u64) -> Result<u64, Error> {
+        {
+            let mut inner = self.inner.borrow_mut();
+            let stored_generation = inner.values.get(key).map_or(0, |stored| stored.generation);
+            if stored_generation != generation {
+                return Err(Error::Conflict);
+            }
+            inner.values.insert(
+                key.to_string(),
+                Stored {
+                    value: value.to_string(),
+                    generation: generation + 1,
+                },
+            );
+        }
+        self.notify(key);
+        Ok(generation + 1)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        let removed = self.inner.borrow_mut().values.remove(key);
+        if removed.is_some() {
+            self.notify(key);
+        }
+        Ok(())
+    }
+
+    fn max_file_size(&self) -> usize {
+        MAX_FILE_SIZE
+    }
+
+    fn subscribe(&self, listener: Rc<dyn Fn(&str)>) -> Result<Subscription, Error> {
+        let mut inner = self.inner.borrow_mut();
+        let id = inner.next_listener_id;
+        inner.next_listener_id += 1;
+        inner.listeners.push((id, listener));
+
+        let inner = Rc::downgrade(&self.inner);
+        Ok(Subscription::new(move || {
+            if let Some(inner) = inner.upgrade() {
+                inner.borrow_mut().listeners.retain(|(i, _)| *i != id);
+            }
+        }))
+    }
+}
@@ -0,0 +1,75 @@
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::error::Error;
+
+pub mod backend;
+pub mod indexed_db;
+pub mod local_storage;
+pub mod memory;
+
+pub use backend::StorageBackend;
+pub use backend::Stored;
+pub use backend::StoredValue;
+pub use backend::Subscription;
+pub use indexed_db::IndexedDb;
+pub use local_storage::LocalStorage;
+pub use memory::MemoryStorage;
+
+/// The storage backends the app can keep the vault in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BackendKind {
+    IndexedDb,
+    LocalStorage,
+    Memory,
+}
+
+impl FromStr for BackendKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "indexeddb" => Ok(Self::IndexedDb),
+            "localstorage" => Ok(Self::LocalStorage),
+            "memory" => Ok(Self::Memory),
+            _ => Err(Error::InvalidInput(format!("Unknown storage backend: {s}"))),
+        }
+    }
+}
+
+/// Open a storage backend of the given kind. If it is not `localStorage`, move
+/// `keys` into it from `localStorage`, where earlier versions kept them.
+pub async fn open(kind: BackendKind, keys: &[&str]) -> Result<Rc<dyn StorageBackend>, Error> {
+    let storage: Rc<dyn StorageBackend> = match kind {
+        BackendKind::IndexedDb => Rc::new(IndexedDb::open().await?),
+        BackendKind::LocalStorage => return Ok(Rc::new(LocalStorage::new()?)),
+        BackendKind::Memory => return Ok(Rc::new(MemoryStorage::default())),
+    };
+
+    if let Ok(local_storage) = LocalStorage::new() {
+        for key in keys {
+            migrate(&local_storage, storage.as_ref(), key).await?;
+        }
+    }
+    Ok(storage)
+}
+
+/// Move the value of `key` from `from` to `to`, unless `to` already has one.
+/// Return whether a value was moved.
+pub async fn migrate(
+    from: &dyn StorageBackend,
+    to: &dyn StorageBackend,
+    key: &str,
+) -> Result<bool, Error> {
+    if to.get(key).await?.is_some() {
+        return Ok(false);
+    }
+    match from.get(key).await? {
+        Some(stored) => {
+            to.put(key, &stored.value, 0).await?;
+            from.delete(key).await?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
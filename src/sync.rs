@@ -0,0 +1,189 @@
+//! Client for the sync server in `sync-server/`, which stores a copy of the
+//! vault config per user handle. Every write is conditional on the `ETag` of
+//! the copy it was merged with, so concurrent syncs from several devices never
+//! overwrite each other's changes, and carries the vault's
+//! [VaultConfig::sync_token], so that only those who can unlock the vault can
+//! write it.
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::Headers;
+use web_sys::Request;
+use web_sys::RequestInit;
+use web_sys::Response;
+
+use crate::crypto::MasterKey;
+use crate::crypto::WebCrypto;
+use crate::data::migration::parse_vault_config;
+use crate::data::vault::VaultConfig;
+use crate::data::UserHandle;
+use crate::error::Error;
+
+/// How many times to retry a sync when another device syncs at the same time.
+const MAX_ATTEMPTS: usize = 3;
+
+/// The largest vault config the sync server stores, its `MAX_BODY_SIZE`.
+pub const MAX_VAULT_SIZE: usize = 16 * 1024 * 1024;
+
+/// Fail with [Error::InvalidInput] if `config` is too large for the sync
+/// server, such as after adding a large file, which the limits of the storage
+/// backends alone allow.
+pub fn check_size(config: &VaultConfig) -> Result<(), Error> {
+    let size = serde_json::to_vec(config)?.len();
+    if size > MAX_VAULT_SIZE {
+        return Err(Error::InvalidInput(format!(
+            "The vault would be {size} bytes, but the sync server stores at most {MAX_VAULT_SIZE} bytes"
+        )));
+    }
+    Ok(())
+}
+
+/// A vault config stored on the sync server.
+pub struct RemoteVault {
+    pub config: String,
+    pub etag: String,
+}
+
+/// The result of [SyncClient::sync].
+pub enum SyncResult {
+    /// The vault was merged with the copy on the server, if any, and the
+    /// result stored there.
+    Synced(VaultConfig),
+
+    /// Merging the copy on the server would delete entries, or change the
+    /// credentials or the recipients of the master key, as told by
+    /// [VaultConfig::needs_review]. Nothing was merged or stored; the copy
+    /// should be merged through a [crate::data::vault::MergePreview] first.
+    NeedsReview(VaultConfig),
+}
+
+pub struct SyncClient {
+    base_url: String,
+}
+
+impl SyncClient {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn url(&self, user_handle: &UserHandle) -> String {
+        format!("{}/vaults/{}", self.base_url, user_handle.b64url())
+    }
+
+    async fn fetch(&self, request: &Request) -> Result<Response, Error> {
+        let window = web_sys::window()
+            .ok_or_else(|| Error::SyncServer("No window to fetch from".to_string()))?;
+        let response = JsFuture::from(window.fetch_with_request(request))
+            .await
+            .map_err(|err| Error::SyncServer(format!("{err:?}")))?;
+        Ok(response.dyn_into::<Response>()?)
+    }
+
+    /// Get the vault config stored for `user_handle`, if any.
+    pub async fn get(&self, user_handle: &UserHandle) -> Result<Option<RemoteVault>, Error> {
+        let request = Request::new_with_str_and_init(
+            &self.url(user_handle),
+            RequestInit::new().method("GET"),
+        )?;
+        let response = self.fetch(&request).await?;
+        match response.status() {
+            200 => {}
+            404 => return Ok(None),
+            status => return Err(Error::SyncServer(format!("GET returned status {status}"))),
+        }
+
+        let etag = response
+            .headers()
+            .get("ETag")?
+            .ok_or_else(|| Error::SyncServer("Response has no ETag".to_string()))?;
+        let config = JsFuture::from(response.text()?)
+            .await?
+            .as_string()
+            .ok_or_else(|| Error::SyncServer("Response body is not text".to_string()))?;
+        Ok(Some(RemoteVault { config, etag }))
+    }
+
+    /// Store `config` for `user_handle` with the write token `token`, and
+    /// return its new `ETag`. Fails with [Error::Conflict] unless the stored
+    /// config still has the given `etag`, or with `None`, unless none is
+    /// stored yet.
+    pub async fn put(
+        &self,
+        user_handle: &UserHandle,
+        config: &str,
+        token: &str,
+        etag: Option<&str>,
+    ) -> Result<String, Error> {
+        let headers = Headers::new()?;
+        headers.set("Content-Type", "application/json")?;
+        headers.set("Authorization", &format!("Bearer {token}"))?;
+        match etag {
+            Some(etag) => headers.set("If-Match", etag)?,
+            None => headers.set("If-None-Match", "*")?,
+        }
+        let request = Request::new_with_str_and_init(
+            &self.url(user_handle),
+            RequestInit::new()
+                .method("PUT")
+                .headers(&headers)
+                .body(Some(&JsValue::from_str(config))),
+        )?;
+
+        let response = self.fetch(&request).await?;
+        match response.status() {
+            200 | 201 => response
+                .headers()
+                .get("ETag")?
+                .ok_or_else(|| Error::SyncServer("Response has no ETag".to_string())),
+            412 => Err(Error::Conflict),
+            403 => Err(Error::SyncServer(
+                "The server holds a different vault for this user".to_string(),
+            )),
+            status => Err(Error::SyncServer(format!("PUT returned status {status}"))),
+        }
+    }
+
+    /// Merge `config` with the copy on the server, store the result there and
+    /// return it, unless the merge needs to be reviewed first.
+    pub async fn sync(
+        &self,
+        config: &VaultConfig,
+        master_key: &MasterKey,
+    ) -> Result<SyncResult, Error> {
+        let user_handle = &config.user.user_handle;
+        let token = config.sync_token(&WebCrypto, master_key).await?;
+        for _ in 0..MAX_ATTEMPTS {
+            let remote = self.get(user_handle).await?;
+            let (merged, etag) = match &remote {
+                Some(remote) => {
+                    let remote_config = parse_vault_config(&remote.config)?;
+                    let merged = config
+                        .sync_merge(&WebCrypto, &remote_config, Some(master_key))
+                        .await?;
+                    if config.needs_review(&merged) {
+                        return Ok(SyncResult::NeedsReview(remote_config));
+                    }
+                    if merged == remote_config {
+                        return Ok(SyncResult::Synced(merged));
+                    }
+                    (merged, Some(remote.etag.as_str()))
+                }
+                None => (config.clone(), None),
+            };
+            check_size(&merged)?;
+
+            match self
+                .put(user_handle, &serde_json::to_string(&merged)?, &token, etag)
+                .await
+            {
+                Ok(_) => return Ok(SyncResult::Synced(merged)),
+                Err(Error::Conflict) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Err(Error::Conflict)
+    }
+}
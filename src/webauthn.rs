@@ -11,6 +11,7 @@ use wasm_bindgen::JsValue;
 use web_sys::AuthenticationExtensionsClientInputs;
 use web_sys::CredentialCreationOptions;
 use web_sys::CredentialRequestOptions;
+use web_sys::CredentialsContainer;
 use web_sys::PublicKeyCredential;
 use web_sys::PublicKeyCredentialCreationOptions;
 use web_sys::PublicKeyCredentialParameters;
@@ -20,52 +21,55 @@ use web_sys::PublicKeyCredentialType;
 use crate::crypto::WrappedKeypair;
 use crate::crypto::WrappedKeypairAdditionalData;
 use crate::data::vault::UserConfig;
-use crate::error::JsOrSerdeError;
+use crate::error::Error;
+
+fn credentials() -> Result<CredentialsContainer, Error> {
+    Ok(web_sys::window()
+        .ok_or(JsValue::UNDEFINED)?
+        .navigator()
+        .credentials())
+}
 
 pub fn webauthn_create(
     challenge: &[u8],
     vault_config: &UserConfig,
     extensions: Option<&AuthenticationExtensionsClientInputs>,
-) -> Result<Promise, JsOrSerdeError> {
-    Ok(web_sys::window()
-        .unwrap()
-        .navigator()
-        .credentials()
-        .create_with_options({
-            let mut opt = PublicKeyCredentialCreationOptions::new(
-                &Uint8Array::from(challenge),
-                &Array::of1(&PublicKeyCredentialParameters::new(
-                    -7,
-                    PublicKeyCredentialType::PublicKey,
-                )),
-                &crate::config::webauthn::rp_entity(),
-                &vault_config.webauthn_user(),
-            );
-            opt.exclude_credentials(&vault_config.webauthn_credential_descriptors()?.into());
-            if let Some(ext) = extensions {
-                opt.extensions(ext);
-            }
-            CredentialCreationOptions::new().public_key(&opt)
-        })?)
+) -> Result<Promise, Error> {
+    Ok(credentials()?.create_with_options({
+        let mut opt = PublicKeyCredentialCreationOptions::new(
+            &Uint8Array::from(challenge),
+            &Array::of1(&PublicKeyCredentialParameters::new(
+                -7,
+                PublicKeyCredentialType::PublicKey,
+            )),
+            &crate::config::webauthn::rp_entity(),
+            &vault_config.webauthn_user(),
+        );
+        opt.exclude_credentials(&vault_config.webauthn_credential_descriptors()?.into());
+        if let Some(ext) = extensions {
+            opt.extensions(ext);
+        }
+        CredentialCreationOptions::new().public_key(&opt)
+    })?)
 }
 
 pub fn webauthn_get(
     challenge: &[u8],
     vault_config: &UserConfig,
     extensions: Option<&AuthenticationExtensionsClientInputs>,
-) -> Result<Promise, JsOrSerdeError> {
-    Ok(webauthn_get_with_allow_credentials(
+) -> Result<Promise, Error> {
+    webauthn_get_with_allow_credentials(
         challenge,
         vault_config.webauthn_credential_descriptors()?,
         extensions,
-    )?)
+    )
 }
 
 pub fn webauthn_get_with_allow_credentials(
     challenge: &[u8],
     allow_credentials: Array,
     extensions: Option<&AuthenticationExtensionsClientInputs>,
-) -> Result<Promise, JsValue> {
+) -> Result<Promise, Error> {
     let mut options = PublicKeyCredentialRequestOptions::new(&Uint8Array::from(challenge))
         .rp_id(crate::config::webauthn::rp_id())
         .allow_credentials(&allow_credentials)
@@ -75,32 +79,28 @@ pub fn webauthn_get_with_allow_credentials(
         options.extensions(extensions);
     }
 
-    web_sys::window()
-        .unwrap()
-        .navigator()
-        .credentials()
-        .get_with_options(CredentialRequestOptions::new().public_key(&options))
+    Ok(credentials()?.get_with_options(CredentialRequestOptions::new().public_key(&options))?)
 }
 
-pub fn prf_extension_eval(salt: &ArrayBuffer) -> AuthenticationExtensionsClientInputs {
-    AuthenticationExtensionsClientInputs::from(
+pub fn prf_extension_eval(
+    salt: &ArrayBuffer,
+) -> Result<AuthenticationExtensionsClientInputs, Error> {
+    Ok(AuthenticationExtensionsClientInputs::from(
         Object::from_entries(&Array::of1(&Array::of2(
             &"prf".into(),
             &Object::from_entries(&Array::of1(&Array::of2(
                 &"eval".into(),
-                &Object::from_entries(&Array::of1(&Array::of2(&"first".into(), salt))).unwrap(),
-            )))
-            .unwrap(),
-        )))
-        .unwrap()
-        .dyn_into::<JsValue>()
-        .unwrap(),
-    )
+                &Object::from_entries(&Array::of1(&Array::of2(&"first".into(), salt)))?.into(),
+            )))?
+            .into(),
+        )))?
+        .dyn_into::<JsValue>()?,
+    ))
 }
 
 pub fn prf_extension_eval_by_credential(
     recipients: &[Rc<WrappedKeypair>],
-) -> Result<AuthenticationExtensionsClientInputs, JsOrSerdeError> {
+) -> Result<AuthenticationExtensionsClientInputs, Error> {
     Ok(AuthenticationExtensionsClientInputs::from(
         Object::from_entries(&Array::of1(&Array::of2(
             &"prf".into(),
@@ -109,8 +109,8 @@ pub fn prf_extension_eval_by_credential(
                     &"evalByCredential".into(),
                     &Object::from_entries(
                         &recipients
-                            .into_iter()
-                            .map(|wkp| -> Result<Array, JsOrSerdeError> {
+                            .iter()
+                            .map(|wkp| -> Result<Array, Error> {
                                 let ead: WrappedKeypairAdditionalData = wkp.additional_data()?;
                                 Ok(Array::of2(
                                     &ead.credential_id().b64url().into(),
@@ -121,7 +121,7 @@ pub fn prf_extension_eval_by_credential(
                                     .into(),
                                 ))
                             })
-                            .collect::<Result<Array, JsOrSerdeError>>()?
+                            .collect::<Result<Array, Error>>()?
                             .into(),
                     )?
                     .into(),
@@ -134,11 +134,47 @@ pub fn prf_extension_eval_by_credential(
     ))
 }
 
-pub fn prf_first_output(cred: &PublicKeyCredential) -> Result<Uint8Array, JsValue> {
-    let extensions: Object = cred.get_client_extension_results().dyn_into()?;
-    let prf_output: Result<Uint8Array, JsValue> = Reflect::get(&extensions, &"prf".into())
-        .and_then(|prf| Reflect::get(&prf, &"results".into()))
+fn prf_extension_results(cred: &PublicKeyCredential) -> Result<JsValue, Error> {
+    let extensions: Object = cred
+        .get_client_extension_results()
+        .dyn_into()
+        .map_err(JsValue::from)?;
+    Reflect::get(&extensions, &"prf".into()).map_err(|_| Error::PrfUnsupported)
+}
+
+/// Get the `results.first` output from the `prf` extension results, if present
+/// and non-empty.
+fn prf_results_first(prf: &JsValue) -> Option<Uint8Array> {
+    let first: JsValue = Reflect::get(prf, &"results".into())
         .and_then(|prf_results| Reflect::get(&prf_results, &"first".into()))
-        .map(|first| Uint8Array::new(&first));
-    prf_output
+        .ok()?;
+
+    if first.is_instance_of::<ArrayBuffer>() || ArrayBuffer::is_view(&first) {
+        Some(Uint8Array::new(&first)).filter(|output| output.length() > 0)
+    } else {
+        None
+    }
+}
+
+/// Get the first PRF output of `cred`, or [Error::PrfUnsupported] if the
+/// browser or authenticator did not return one.
+pub fn prf_first_output(cred: &PublicKeyCredential) -> Result<Uint8Array, Error> {
+    prf_results_first(&prf_extension_results(cred)?).ok_or(Error::PrfUnsupported)
+}
+
+/// Check that the PRF extension was enabled when `cred` was created, and get
+/// the first PRF output if the authenticator already returned one. Fails with
+/// [Error::PrfUnsupported] if `prf.enabled` is not `true`.
+pub fn prf_create_output(cred: &PublicKeyCredential) -> Result<Option<Uint8Array>, Error> {
+    let prf: JsValue = prf_extension_results(cred)?;
+    let enabled: bool = Reflect::get(&prf, &"enabled".into())
+        .ok()
+        .and_then(|enabled| enabled.as_bool())
+        .unwrap_or(false);
+
+    if enabled {
+        Ok(prf_results_first(&prf))
+    } else {
+        Err(Error::PrfUnsupported)
+    }
 }
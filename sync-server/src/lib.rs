@@ -0,0 +1,484 @@
+//! A minimal HTTP server that stores one vault config per user handle, for
+//! syncing a vault between devices. Vault configs are opaque to the server,
+//! which never sees a plaintext entry or key.
+//!
+//! - `GET /vaults/{user_handle}` returns the stored config and its `ETag`.
+//! - `PUT /vaults/{user_handle}` stores a config. The request must have either
+//!   `If-Match` with the `ETag` of the config the update is based on, or
+//!   `If-None-Match: *` to create one, and fails with `412 Precondition
+//!   Failed` if the stored config has changed since.
+//!
+//! `{user_handle}` is the base64url encoded WebAuthn user handle. Since the
+//! user handle is no secret, every `PUT` must also have an `Authorization:
+//! Bearer` write token, which the client derives from a secret in the vault.
+//! The server remembers the token a vault was created with, only as a hash,
+//! and rejects updates with any other token with `403 Forbidden`. A vault
+//! stored before write tokens were required takes the token of its next
+//! update.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::thread;
+use std::time::Duration;
+
+use base64::Engine;
+use sha2::Digest;
+use sha2::Sha256;
+
+/// Vault configs larger than this are rejected. The app checks vaults against
+/// the same limit, as `sync::MAX_VAULT_SIZE`, when adding a file.
+pub const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+const MAX_HEAD_SIZE: u64 = 16 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The strong `ETag` of a stored vault config.
+pub fn etag(body: &[u8]) -> String {
+    format!(
+        "\"{}\"",
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(body))
+    )
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// The condition a `PUT` request puts on the currently stored config.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Precondition {
+    /// `If-Match: "..."`: the stored config must have this `ETag`.
+    Matches(String),
+
+    /// `If-Match: *`: a config must be stored.
+    Exists,
+
+    /// `If-None-Match: *`: no config may be stored yet.
+    Absent,
+}
+
+/// The hash a write token is stored as.
+fn token_hash(token: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+#[derive(Debug)]
+pub enum PutError {
+    PreconditionFailed,
+
+    /// The vault was created with a different write token.
+    Forbidden,
+
+    Io(io::Error),
+}
+
+impl From<io::Error> for PutError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+struct StoredVault {
+    body: Arc<Vec<u8>>,
+
+    /// The hash of the write token, if one was ever given.
+    token_hash: Option<String>,
+}
+
+/// The stored vault configs, by user handle.
+pub struct Store {
+    dir: Option<PathBuf>,
+    vaults: Mutex<HashMap<String, StoredVault>>,
+}
+
+impl Store {
+    /// A store that forgets everything when the server stops.
+    pub fn in_memory() -> Self {
+        Self {
+            dir: None,
+            vaults: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A store that keeps each vault config in a file in `dir`, and the hash
+    /// of its write token next to it, starting with the ones already there.
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut vaults = HashMap::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            if let Some(id) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .filter(|id| is_valid_id(id))
+            {
+                let token_hash = match fs::read_to_string(dir.join(format!("{id}.token"))) {
+                    Ok(hash) => Some(hash.trim().to_string()),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                    Err(err) => return Err(err),
+                };
+                vaults.insert(
+                    id.to_string(),
+                    StoredVault {
+                        body: Arc::new(fs::read(&path)?),
+                        token_hash,
+                    },
+                );
+            }
+        }
+
+        Ok(Self {
+            dir: Some(dir),
+            vaults: Mutex::new(vaults),
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Vec<u8>>> {
+        self.vaults
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(id)
+            .map(|vault| Arc::clone(&vault.body))
+    }
+
+    /// Store `body` as the vault config of `id` if `token` is the write token
+    /// it was created with and `precondition` holds, and return its new
+    /// `ETag`.
+    pub fn put(
+        &self,
+        id: &str,
+        body: Vec<u8>,
+        token: &str,
+        precondition: &Precondition,
+    ) -> Result<String, PutError> {
+        let mut vaults = self.vaults.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let token_hash = token_hash(token);
+        if let Some(StoredVault {
+            token_hash: Some(stored),
+            ..
+        }) = vaults.get(id)
+        {
+            if *stored != token_hash {
+                return Err(PutError::Forbidden);
+            }
+        }
+
+        let holds = match (precondition, vaults.get(id)) {
+            (Precondition::Matches(tag), Some(current)) => *tag == etag(&current.body),
+            (Precondition::Exists, Some(_)) => true,
+            (Precondition::Absent, None) => true,
+            _ => false,
+        };
+        if !holds {
+            return Err(PutError::PreconditionFailed);
+        }
+
+        if let Some(dir) = &self.dir {
+            // Write to temporary files first so a crash never leaves a
+            // truncated config or token behind. The token goes first, since
+            // it is only read along with a config.
+            for (name, contents) in [("token", token_hash.as_bytes()), ("json", &body)] {
+                let tmp_path = dir.join(format!("{id}.{name}.tmp"));
+                fs::write(&tmp_path, contents)?;
+                fs::rename(&tmp_path, dir.join(format!("{id}.{name}")))?;
+            }
+        }
+
+        let tag = etag(&body);
+        vaults.insert(
+            id.to_string(),
+            StoredVault {
+                body: Arc::new(body),
+                token_hash: Some(token_hash),
+            },
+        );
+        Ok(tag)
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+struct Response {
+    status: u16,
+    reason: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn new(status: u16, reason: &'static str) -> Self {
+        Self {
+            status,
+            reason,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    fn text(status: u16, reason: &'static str, msg: &str) -> Self {
+        Self::new(status, reason)
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(format!("{msg}\n").into_bytes())
+    }
+
+    fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+
+    fn write_to(&self, w: &mut impl Write, allow_origin: &str) -> io::Result<()> {
+        write!(w, "HTTP/1.1 {} {}\r\n", self.status, self.reason)?;
+        write!(w, "Content-Length: {}\r\n", self.body.len())?;
+        write!(w, "Connection: close\r\n")?;
+        write!(w, "Access-Control-Allow-Origin: {allow_origin}\r\n")?;
+        write!(w, "Access-Control-Expose-Headers: ETag\r\n")?;
+        for (name, value) in &self.headers {
+            write!(w, "{name}: {value}\r\n")?;
+        }
+        write!(w, "\r\n")?;
+        w.write_all(&self.body)?;
+        w.flush()
+    }
+}
+
+fn bad_request(msg: &str) -> Response {
+    Response::text(400, "Bad Request", msg)
+}
+
+/// Read one request, or the error response to send instead.
+fn read_request(reader: &mut impl BufRead) -> Result<Request, Response> {
+    let mut head = reader.by_ref().take(MAX_HEAD_SIZE);
+    let mut read_line = || -> Result<String, Response> {
+        let mut line = String::new();
+        match head.read_line(&mut line) {
+            Ok(_) if line.ends_with("\r\n") => Ok(line.trim_end().to_string()),
+            Ok(_) | Err(_) => Err(bad_request("Incomplete request")),
+        }
+    };
+
+    let request_line = read_line()?;
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(path), Some(_version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(bad_request("Malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut headers = HashMap::new();
+    loop {
+        let line = read_line()?;
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| bad_request("Malformed header"))?;
+        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+
+    let body = match headers.get("content-length") {
+        Some(len) => {
+            let len: usize = len
+                .parse()
+                .map_err(|_| bad_request("Malformed Content-Length"))?;
+            if len > MAX_BODY_SIZE {
+                return Err(Response::text(
+                    413,
+                    "Payload Too Large",
+                    "The vault config is too large",
+                ));
+            }
+            let mut body = vec![0; len];
+            reader
+                .read_exact(&mut body)
+                .map_err(|_| bad_request("Incomplete request body"))?;
+            body
+        }
+        None if headers.contains_key("transfer-encoding") => {
+            return Err(Response::text(
+                411,
+                "Length Required",
+                "Content-Length is required",
+            ));
+        }
+        None => Vec::new(),
+    };
+
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+pub struct Server {
+    store: Store,
+    allow_origin: String,
+}
+
+impl Server {
+    /// A server for the app at `allow_origin`, which is sent as the
+    /// `Access-Control-Allow-Origin` of responses, so that browsers only let
+    /// that app use the server.
+    pub fn new(store: Store, allow_origin: impl Into<String>) -> Self {
+        Self {
+            store,
+            allow_origin: allow_origin.into(),
+        }
+    }
+
+    /// Serve connections from `listener`, each in its own thread, until
+    /// accepting a connection fails.
+    pub fn serve(self, listener: TcpListener) -> io::Result<()> {
+        let server = Arc::new(self);
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = Arc::clone(&server);
+            thread::spawn(move || {
+                if let Err(err) = server.handle(stream) {
+                    eprintln!("Connection failed: {err}");
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let response = match read_request(&mut reader) {
+            Ok(request) => self.respond(request),
+            Err(response) => response,
+        };
+        response.write_to(&mut stream, &self.allow_origin)
+    }
+
+    fn respond(&self, mut request: Request) -> Response {
+        let path = std::mem::take(&mut request.path);
+        let Some(id) = path.strip_prefix("/vaults/").filter(|id| is_valid_id(id)) else {
+            return Response::text(404, "Not Found", "Not found");
+        };
+
+        match request.method.as_str() {
+            "OPTIONS" => Response::new(204, "No Content")
+                .header("Access-Control-Allow-Methods", "GET, PUT, OPTIONS")
+                .header(
+                    "Access-Control-Allow-Headers",
+                    "Authorization, Content-Type, If-Match, If-None-Match",
+                )
+                .header("Access-Control-Max-Age", "600"),
+
+            "GET" => match self.store.get(id) {
+                Some(body) => Response::new(200, "OK")
+                    .header("Content-Type", "application/json")
+                    .header("Cache-Control", "no-store")
+                    .header("ETag", etag(&body))
+                    .body(body.to_vec()),
+                None => Response::text(404, "Not Found", "No vault is stored for this user"),
+            },
+
+            "PUT" => self.put(id, request),
+
+            _ => Response::text(405, "Method Not Allowed", "Method not allowed")
+                .header("Allow", "GET, PUT, OPTIONS"),
+        }
+    }
+
+    fn put(&self, id: &str, request: Request) -> Response {
+        let Some(token) = request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(str::to_string)
+        else {
+            return Response::text(401, "Unauthorized", "Send the write token of the vault")
+                .header("WWW-Authenticate", "Bearer");
+        };
+
+        let precondition = match (request.header("if-match"), request.header("if-none-match")) {
+            (Some("*"), None) => Precondition::Exists,
+            (Some(tag), None) => Precondition::Matches(tag.to_string()),
+            (None, Some("*")) => Precondition::Absent,
+            _ => {
+                return Response::text(
+                    428,
+                    "Precondition Required",
+                    "Send If-Match with the ETag the update is based on, or If-None-Match: * to create a vault",
+                )
+            }
+        };
+
+        if !serde_json::from_slice::<serde_json::Value>(&request.body)
+            .is_ok_and(|value| value.is_object())
+        {
+            return bad_request("The vault config must be a JSON object");
+        }
+
+        match self.store.put(id, request.body, &token, &precondition) {
+            Ok(tag) if precondition == Precondition::Absent => {
+                Response::new(201, "Created").header("ETag", tag)
+            }
+            Ok(tag) => Response::new(200, "OK").header("ETag", tag),
+            Err(PutError::PreconditionFailed) => {
+                let response = Response::text(
+                    412,
+                    "Precondition Failed",
+                    "The vault was changed since it was read",
+                );
+                match self.store.get(id) {
+                    Some(current) => response.header("ETag", etag(&current)),
+                    None => response,
+                }
+            }
+            Err(PutError::Forbidden) => Response::text(
+                403,
+                "Forbidden",
+                "The vault was created with a different write token",
+            ),
+            Err(PutError::Io(err)) => {
+                eprintln!("Failed to store vault {id}: {err}");
+                Response::text(500, "Internal Server Error", "Failed to store the vault")
+            }
+        }
+    }
+}
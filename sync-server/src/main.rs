@@ -0,0 +1,63 @@
+use std::net::TcpListener;
+use std::process::ExitCode;
+
+use passwordless_passwords_prf_sync_server::Server;
+use passwordless_passwords_prf_sync_server::Store;
+
+const USAGE: &str = "Usage: passwordless-passwords-prf-sync-server [--listen ADDR] [--data-dir DIR] --allow-origin ORIGIN
+
+    --listen ADDR          Address to listen on (default: 127.0.0.1:8787)
+    --data-dir DIR         Directory to store vaults in (default: keep them in memory only)
+    --allow-origin ORIGIN  Origin of the app allowed to use the server, such as https://example.org";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(msg) => {
+            eprintln!("{msg}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut listen = "127.0.0.1:8787".to_string();
+    let mut data_dir = None;
+    let mut allow_origin = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {arg}\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--listen" => listen = value()?,
+            "--data-dir" => data_dir = Some(value()?),
+            "--allow-origin" => allow_origin = Some(value()?),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ => return Err(format!("Unknown argument: {arg}\n\n{USAGE}")),
+        }
+    }
+
+    let allow_origin = allow_origin.ok_or_else(|| format!("Missing --allow-origin\n\n{USAGE}"))?;
+
+    let store = match data_dir {
+        Some(dir) => Store::open(&dir)
+            .map_err(|err| format!("Failed to open data directory {dir}: {err}"))?,
+        None => Store::in_memory(),
+    };
+    let listener =
+        TcpListener::bind(&listen).map_err(|err| format!("Failed to listen on {listen}: {err}"))?;
+    println!(
+        "Listening on http://{}",
+        listener.local_addr().map_err(|err| err.to_string())?
+    );
+
+    Server::new(store, allow_origin)
+        .serve(listener)
+        .map_err(|err| format!("Server failed: {err}"))
+}
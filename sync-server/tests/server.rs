@@ -0,0 +1,214 @@
+use std::io::Read;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::thread;
+
+use passwordless_passwords_prf_sync_server::etag;
+use passwordless_passwords_prf_sync_server::Server;
+use passwordless_passwords_prf_sync_server::Store;
+
+const VAULT: &str = "/vaults/dXNlcg";
+const ORIGIN: &str = "https://app.example";
+const AUTH: (&str, &str) = ("Authorization", "Bearer write-token");
+
+struct Response {
+    status: u16,
+    etag: Option<String>,
+    allow_origin: Option<String>,
+    body: String,
+}
+
+/// Start a server on a free port on localhost.
+fn start(store: Store) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || Server::new(store, ORIGIN).serve(listener));
+    addr
+}
+
+fn request(
+    addr: SocketAddr,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> Response {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "{method} {path} HTTP/1.1\r\nHost: {addr}\r\n").unwrap();
+    for (name, value) in headers {
+        write!(stream, "{name}: {value}\r\n").unwrap();
+    }
+    write!(stream, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap();
+    let header = |name: &str| {
+        head.split("\r\n")
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(": "))
+            .map(str::to_string)
+    };
+    Response {
+        status: status.parse().unwrap(),
+        etag: header("ETag"),
+        allow_origin: header("Access-Control-Allow-Origin"),
+        body: body.to_string(),
+    }
+}
+
+fn create(addr: SocketAddr, body: &str) -> String {
+    let response = request(addr, "PUT", VAULT, &[AUTH, ("If-None-Match", "*")], body);
+    assert_eq!(response.status, 201);
+    response.etag.unwrap()
+}
+
+#[test]
+fn stores_and_returns_vault() {
+    let addr = start(Store::in_memory());
+    assert_eq!(request(addr, "GET", VAULT, &[], "").status, 404);
+
+    let tag = create(addr, r#"{"v":5}"#);
+    assert_eq!(tag, etag(br#"{"v":5}"#));
+
+    let response = request(addr, "GET", VAULT, &[], "");
+    assert_eq!(response.status, 200);
+    assert_eq!(response.etag, Some(tag));
+    assert_eq!(response.body, r#"{"v":5}"#);
+}
+
+#[test]
+fn rejects_stale_etag() {
+    let addr = start(Store::in_memory());
+    let first = create(addr, r#"{"v":1}"#);
+
+    let response = request(
+        addr,
+        "PUT",
+        VAULT,
+        &[AUTH, ("If-Match", &first)],
+        r#"{"v":2}"#,
+    );
+    assert_eq!(response.status, 200);
+    let second = response.etag.unwrap();
+
+    let response = request(
+        addr,
+        "PUT",
+        VAULT,
+        &[AUTH, ("If-Match", &first)],
+        r#"{"v":3}"#,
+    );
+    assert_eq!(response.status, 412);
+    assert_eq!(response.etag, Some(second));
+    assert_eq!(request(addr, "GET", VAULT, &[], "").body, r#"{"v":2}"#);
+}
+
+#[test]
+fn requires_precondition() {
+    let addr = start(Store::in_memory());
+    assert_eq!(request(addr, "PUT", VAULT, &[AUTH], "{}").status, 428);
+
+    create(addr, "{}");
+    let response = request(addr, "PUT", VAULT, &[AUTH, ("If-None-Match", "*")], "{}");
+    assert_eq!(response.status, 412);
+}
+
+#[test]
+fn rejects_invalid_requests() {
+    let addr = start(Store::in_memory());
+    let create = [AUTH, ("If-None-Match", "*")];
+    assert_eq!(request(addr, "PUT", VAULT, &create, "not json").status, 400);
+    assert_eq!(request(addr, "PUT", VAULT, &create, "[]").status, 400);
+    assert_eq!(
+        request(addr, "PUT", "/vaults/..%2Fetc", &create, "{}").status,
+        404
+    );
+    assert_eq!(request(addr, "DELETE", VAULT, &[], "").status, 405);
+    assert_eq!(request(addr, "OPTIONS", VAULT, &[], "").status, 204);
+}
+
+#[test]
+fn requires_write_token() {
+    let addr = start(Store::in_memory());
+    let response = request(addr, "PUT", VAULT, &[("If-None-Match", "*")], "{}");
+    assert_eq!(response.status, 401);
+
+    let tag = create(addr, r#"{"v":1}"#);
+    let other = ("Authorization", "Bearer other-token");
+    for headers in [[other, ("If-Match", &tag)], [other, ("If-Match", "*")]] {
+        let response = request(addr, "PUT", VAULT, &headers, r#"{"v":2}"#);
+        assert_eq!(response.status, 403);
+    }
+    let response = request(addr, "PUT", VAULT, &[("If-Match", &tag)], r#"{"v":2}"#);
+    assert_eq!(response.status, 401);
+    assert_eq!(request(addr, "GET", VAULT, &[], "").body, r#"{"v":1}"#);
+
+    let response = request(
+        addr,
+        "PUT",
+        VAULT,
+        &[AUTH, ("If-Match", &tag)],
+        r#"{"v":2}"#,
+    );
+    assert_eq!(response.status, 200);
+}
+
+#[test]
+fn allows_configured_origin() {
+    let addr = start(Store::in_memory());
+    let response = request(addr, "GET", VAULT, &[], "");
+    assert_eq!(response.allow_origin.as_deref(), Some(ORIGIN));
+}
+
+#[test]
+fn persists_vaults_in_data_dir() {
+    let dir = std::env::temp_dir().join(format!("sync-server-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let addr = start(Store::open(&dir).unwrap());
+    let tag = create(addr, r#"{"v":5}"#);
+
+    let addr = start(Store::open(&dir).unwrap());
+    let response = request(addr, "GET", VAULT, &[], "");
+    assert_eq!(response.status, 200);
+    assert_eq!(response.etag, Some(tag));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn adopts_write_token_of_vault_stored_without_one() {
+    let dir = std::env::temp_dir().join(format!("sync-server-token-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("dXNlcg.json"), r#"{"v":1}"#).unwrap();
+
+    let addr = start(Store::open(&dir).unwrap());
+    let tag = etag(br#"{"v":1}"#);
+    let response = request(
+        addr,
+        "PUT",
+        VAULT,
+        &[AUTH, ("If-Match", &tag)],
+        r#"{"v":2}"#,
+    );
+    assert_eq!(response.status, 200);
+    let tag = response.etag.unwrap();
+
+    // The token is kept across restarts
+    let addr = start(Store::open(&dir).unwrap());
+    let other = ("Authorization", "Bearer other-token");
+    let response = request(
+        addr,
+        "PUT",
+        VAULT,
+        &[other, ("If-Match", &tag)],
+        r#"{"v":3}"#,
+    );
+    assert_eq!(response.status, 403);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
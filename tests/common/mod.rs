@@ -0,0 +1,101 @@
+#![allow(dead_code)]
+
+use std::rc::Rc;
+
+use futures::executor::block_on;
+
+use passwordless_passwords_prf::crypto::encrypt_with;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::unwrap_keypair;
+use passwordless_passwords_prf::crypto::wrap_keypair;
+use passwordless_passwords_prf::crypto::CryptoBackend;
+use passwordless_passwords_prf::crypto::EncryptedContentAdditionalData;
+use passwordless_passwords_prf::crypto::UnlockedKey;
+use passwordless_passwords_prf::crypto::WrappedKeypair;
+use passwordless_passwords_prf::data::migration::parse_vault_config;
+use passwordless_passwords_prf::data::vault::VaultConfig;
+use passwordless_passwords_prf::data::UserHandle;
+
+/// A keypair together with the PRF output that unwraps it, standing in for
+/// an authenticator.
+pub struct TestKey {
+    pub credential_id: Vec<u8>,
+    pub prf_output: [u8; 32],
+    pub keypair: Rc<WrappedKeypair>,
+}
+
+impl TestKey {
+    pub fn new(id: u8) -> Self {
+        let prf_salt: [u8; 32] = RustCrypto.random().unwrap();
+        let prf_output: [u8; 32] = RustCrypto.random().unwrap();
+        let credential_id = vec![id; 16];
+        let keypair = Rc::new(
+            block_on(wrap_keypair(
+                &RustCrypto,
+                &credential_id,
+                &prf_salt,
+                &prf_output,
+            ))
+            .unwrap(),
+        );
+        Self {
+            credential_id,
+            prf_output,
+            keypair,
+        }
+    }
+
+    /// Unwrap the private key, as a WebAuthn ceremony with this credential would.
+    pub fn unlock(&self) -> UnlockedKey<RustCrypto> {
+        UnlockedKey {
+            credential_id: self.credential_id.clone(),
+            private_key: block_on(unwrap_keypair(&RustCrypto, &self.keypair, &self.prf_output))
+                .unwrap(),
+        }
+    }
+}
+
+pub fn user_handle(id: u8) -> UserHandle {
+    serde_json::from_value(serde_json::json!({
+        "$base64": ::base64::Engine::encode(
+            &::base64::engine::general_purpose::STANDARD,
+            [id; 64],
+        ),
+    }))
+    .unwrap()
+}
+
+/// A vault stored in format version 2, with each entry's content key wrapped
+/// to every one of `keys`, as loaded by the current version.
+pub fn legacy_vault(keys: &[&TestKey], entries: &[(&str, &[u8])]) -> VaultConfig {
+    let user_handle = user_handle(0);
+    let keypairs: Vec<Rc<WrappedKeypair>> = keys.iter().map(|k| Rc::clone(&k.keypair)).collect();
+    let contents: serde_json::Map<String, serde_json::Value> = entries
+        .iter()
+        .map(|(name, content)| {
+            let encrypted = block_on(encrypt_with(
+                &RustCrypto,
+                content,
+                &EncryptedContentAdditionalData::new(name, &user_handle),
+                &keypairs,
+            ))
+            .unwrap();
+            (name.to_string(), serde_json::to_value(encrypted).unwrap())
+        })
+        .collect();
+
+    parse_vault_config(
+        &serde_json::json!({
+            "v": 2,
+            "user": {
+                "v": 2,
+                "username": "test",
+                "user_handle": user_handle,
+                "keypairs": keypairs,
+            },
+            "contents": contents,
+        })
+        .to_string(),
+    )
+    .unwrap()
+}
@@ -0,0 +1,379 @@
+mod common;
+
+use std::rc::Rc;
+
+use futures::executor::block_on;
+
+use common::legacy_vault;
+use common::user_handle;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::identity::sign;
+use passwordless_passwords_prf::crypto::identity::SignedIdentity;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::MasterKey;
+use passwordless_passwords_prf::data::contact::SharePackage;
+use passwordless_passwords_prf::data::vault::VaultConfig;
+use passwordless_passwords_prf::data::CredentialId;
+use passwordless_passwords_prf::error::Error;
+
+struct User {
+    config: VaultConfig,
+    key: TestKey,
+    master_key: MasterKey,
+}
+
+impl User {
+    /// A vault with a master key, for the user with handle `id`.
+    fn new(id: u8, entries: &[(&str, &[u8])]) -> Self {
+        let key = TestKey::new(id);
+        let mut config = legacy_vault(&[&key], &[]);
+        Rc::make_mut(&mut config.user).user_handle = user_handle(id);
+        let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+        for (name, content) in entries {
+            block_on(config.push_content(
+                &RustCrypto,
+                name.to_string(),
+                content.to_vec(),
+                &master_key,
+            ))
+            .unwrap();
+        }
+        Self {
+            config,
+            key,
+            master_key,
+        }
+    }
+
+    fn export(&self) -> SignedIdentity {
+        block_on(self.config.export_identity(&RustCrypto, &self.master_key)).unwrap()
+    }
+
+    /// Import `other` as a contact, returning the key of the contact.
+    fn import(&mut self, other: &User) -> String {
+        self.config.import_contact(other.export()).unwrap();
+        other.config.user.user_handle.b64url()
+    }
+
+    /// Add another key and revoke it, which replaces the master key.
+    fn rotate_master_key(&mut self) {
+        let other = TestKey::new(9);
+        block_on(self.config.add_credential(
+            &RustCrypto,
+            (*other.keypair).clone(),
+            &self.master_key,
+        ))
+        .unwrap();
+        let (master_key, _) = block_on(self.config.revoke_credential(
+            &RustCrypto,
+            &CredentialId::from(other.credential_id),
+            &self.key.unlock(),
+            &self.master_key,
+        ))
+        .unwrap();
+        self.master_key = master_key.unwrap();
+    }
+
+    fn share(&mut self, key: &str, contact: &str) {
+        block_on(self.config.share_content(
+            &RustCrypto,
+            key,
+            key,
+            contact,
+            &self.key.unlock(),
+            &self.master_key,
+        ))
+        .unwrap();
+    }
+
+    fn share_package(&self, contact: &str) -> SharePackage {
+        block_on(
+            self.config
+                .share_package(&RustCrypto, contact, &self.master_key),
+        )
+        .unwrap()
+    }
+
+    fn import_shared(&mut self, package: &SharePackage) -> Result<Vec<String>, Error> {
+        block_on(self.config.import_shared(
+            &RustCrypto,
+            package,
+            &self.key.unlock(),
+            &self.master_key,
+        ))
+    }
+
+    fn decrypt(&self, key: &str) -> Vec<u8> {
+        block_on(decrypt_with(
+            &RustCrypto,
+            &self.config.contents[key],
+            &self.config.content_additional_data(key),
+            &self.key.unlock(),
+            Some(&self.master_key),
+        ))
+        .unwrap()
+    }
+}
+
+#[test]
+fn exported_identity_verifies() {
+    let alice = User::new(1, &[]);
+    let signed = alice.export();
+    let identity = signed.verify().unwrap();
+
+    assert_eq!(identity.user_handle, alice.config.user.user_handle);
+    assert_eq!(identity.keys.len(), 1);
+    assert_eq!(identity.keys[0].credential_id, alice.key.credential_id);
+
+    // The signing key, and so the fingerprint, stays the same
+    let again = alice.export();
+    assert_eq!(
+        again.verify().unwrap().fingerprint(),
+        identity.fingerprint()
+    );
+    let bob = User::new(2, &[]);
+    let other = bob.export();
+    assert_ne!(
+        other.identity().unwrap().fingerprint(),
+        identity.fingerprint()
+    );
+}
+
+#[test]
+fn rejects_tampered_identity() {
+    let alice = User::new(1, &[]);
+    let mut bob = User::new(2, &[]);
+    let signed = alice.export();
+
+    let mut json = serde_json::to_value(&signed).unwrap();
+    let mut identity: serde_json::Value = serde_json::to_value(signed.identity().unwrap()).unwrap();
+    identity["username"] = "mallory".into();
+    json["identity"] = serde_json::json!({
+        "$base64": ::base64::Engine::encode(
+            &::base64::engine::general_purpose::STANDARD,
+            serde_json::to_vec(&identity).unwrap(),
+        ),
+    });
+    let tampered = serde_json::from_value(json).unwrap();
+
+    assert!(matches!(
+        bob.config.import_contact(tampered),
+        Err(Error::MalformedRecord(_))
+    ));
+    assert!(bob.config.contacts.is_empty());
+}
+
+#[test]
+fn rejects_own_identity_and_different_signer() {
+    let mut alice = User::new(1, &[]);
+    let own = alice.export();
+    assert!(matches!(
+        alice.config.import_contact(own),
+        Err(Error::InvalidInput(_))
+    ));
+
+    let bob = User::new(2, &[]);
+    alice.import(&bob);
+
+    // Someone else claiming to be Bob
+    let impostor = User::new(2, &[]);
+    let signed = impostor.export();
+    assert!(matches!(
+        alice.config.import_contact(signed),
+        Err(Error::InvalidInput(_))
+    ));
+
+    // Bob's own identity can be imported again, e.g. after adding a key
+    let signed = bob.export();
+    assert!(alice.config.import_contact(signed).is_ok());
+    assert_eq!(alice.config.contacts.len(), 1);
+}
+
+#[test]
+fn shares_entries_with_contact() {
+    let mut alice = User::new(1, &[("bank", b"1234"), ("email", b"5678")]);
+    let mut bob = User::new(2, &[]);
+    let bob_key = alice.import(&bob);
+    let alice_key = bob.import(&alice);
+
+    alice.share("bank", &bob_key);
+    let shared_with = alice.config.shared_with("bank");
+    assert_eq!(shared_with.len(), 1);
+    assert_eq!(shared_with[0].user_handle, bob.config.user.user_handle);
+    assert!(alice.config.shared_with("email").is_empty());
+
+    let package = alice.share_package(&bob_key);
+    assert_eq!(bob.import_shared(&package).unwrap(), vec!["bank"]);
+    assert_eq!(bob.decrypt("bank"), b"1234");
+
+    // Importing again does not overwrite the entry
+    assert!(matches!(
+        bob.import_shared(&package),
+        Err(Error::InvalidInput(_))
+    ));
+
+    // Alice is not one of her own contacts
+    assert!(matches!(
+        alice.import_shared(&package),
+        Err(Error::InvalidInput(_))
+    ));
+
+    // Bob deletes Alice, so her entries are no longer accepted
+    bob.config.delete_content("bank").unwrap();
+    bob.config.delete_contact(&alice_key).unwrap();
+    assert!(matches!(
+        bob.import_shared(&package),
+        Err(Error::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn rejects_tampered_package_and_other_recipient() {
+    let mut alice = User::new(1, &[("bank", b"1234")]);
+    let mut bob = User::new(2, &[]);
+    let mut carol = User::new(3, &[]);
+    let bob_key = alice.import(&bob);
+    bob.import(&alice);
+    carol.import(&alice);
+
+    alice.share("bank", &bob_key);
+    let package = alice.share_package(&bob_key);
+
+    // Only Bob can import it
+    assert!(matches!(
+        carol.import_shared(&package),
+        Err(Error::InvalidInput(_))
+    ));
+
+    let mut tampered = package.clone();
+    let last = tampered.signature.len() - 1;
+    tampered.signature[last] ^= 1;
+    assert!(matches!(
+        bob.import_shared(&tampered),
+        Err(Error::MalformedRecord(_))
+    ));
+    assert!(bob.config.contents.is_empty());
+
+    // Signed by Carol, but claiming to be from Alice
+    let forged = SharePackage {
+        signature: sign(
+            &block_on(carol.config.identity_secret(&RustCrypto, &carol.master_key)).unwrap(),
+            &package.payload,
+        )
+        .unwrap(),
+        payload: package.payload,
+    };
+    assert!(matches!(
+        bob.import_shared(&forged),
+        Err(Error::MalformedRecord(_))
+    ));
+    assert!(bob.config.contents.is_empty());
+}
+
+#[test]
+fn share_package_names_sender_and_recipient_by_signing_key() {
+    let mut alice = User::new(1, &[("bank", b"1234")]);
+    let bob = User::new(2, &[]);
+    let bob_key = alice.import(&bob);
+    alice.share("bank", &bob_key);
+
+    let package = alice.share_package(&bob_key);
+    let payload: serde_json::Value = serde_json::from_slice(&package.payload).unwrap();
+    assert_eq!(
+        payload["sender"],
+        alice.export().identity().unwrap().signer_id()
+    );
+    assert_eq!(
+        payload["recipient"],
+        bob.export().identity().unwrap().signer_id()
+    );
+}
+
+#[test]
+fn shares_after_revoking_a_key() {
+    let mut alice = User::new(1, &[("bank", b"1234")]);
+    let mut bob = User::new(2, &[]);
+    let bob_key = alice.import(&bob);
+    bob.import(&alice);
+    let fingerprint = alice.export().identity().unwrap().fingerprint();
+
+    alice.rotate_master_key();
+
+    // Bob still recognizes Alice's identity, and accepts what she shares
+    let signed = alice.export();
+    assert_eq!(signed.identity().unwrap().fingerprint(), fingerprint);
+    bob.config.import_contact(signed).unwrap();
+    alice.share("bank", &bob_key);
+    let package = alice.share_package(&bob_key);
+    assert_eq!(bob.import_shared(&package).unwrap(), vec!["bank"]);
+    assert_eq!(bob.decrypt("bank"), b"1234");
+}
+
+#[test]
+fn keeps_identity_of_vault_without_identity_secret() {
+    let mut alice = User::new(1, &[]);
+    let mut bob = User::new(2, &[]);
+    // As if the master key was created before identity secrets were stored
+    alice.config.identity_secret = None;
+    bob.import(&alice);
+
+    alice.rotate_master_key();
+    assert!(alice.config.identity_secret.is_some());
+    assert!(bob.config.import_contact(alice.export()).is_ok());
+}
+
+#[test]
+fn sync_token_outlives_master_key_rotation() {
+    let mut alice = User::new(1, &[]);
+    let bob = User::new(2, &[]);
+    let token = block_on(alice.config.sync_token(&RustCrypto, &alice.master_key)).unwrap();
+
+    alice.rotate_master_key();
+    assert_eq!(
+        block_on(alice.config.sync_token(&RustCrypto, &alice.master_key)).unwrap(),
+        token
+    );
+    assert_ne!(
+        block_on(bob.config.sync_token(&RustCrypto, &bob.master_key)).unwrap(),
+        token
+    );
+}
+
+#[test]
+fn unshares_deleted_entries() {
+    let mut alice = User::new(1, &[("bank", b"1234"), ("email", b"5678")]);
+    let bob = User::new(2, &[]);
+    let bob_key = alice.import(&bob);
+
+    alice.share("bank", &bob_key);
+    alice.share("email", &bob_key);
+    alice.config.unshare_content("email", &bob_key).unwrap();
+    assert!(alice.config.shared_with("email").is_empty());
+    assert_eq!(alice.config.contacts[&bob_key].shared.len(), 1);
+
+    alice.config.delete_content("bank").unwrap();
+    assert!(alice.config.shared_with("bank").is_empty());
+    assert!(alice.config.contacts[&bob_key].shared.is_empty());
+}
+
+#[test]
+fn contacts_survive_merge() {
+    let mut alice = User::new(1, &[("bank", b"1234")]);
+    let bob = User::new(2, &[]);
+    let carol = User::new(3, &[]);
+    let bob_key = alice.import(&bob);
+    let carol_key = alice.import(&carol);
+
+    let mut other = alice.config.clone();
+    alice.share("bank", &bob_key);
+    other.delete_contact(&carol_key).unwrap();
+
+    let merged = block_on(alice.config.sync_merge(&RustCrypto, &other, None)).unwrap();
+    assert_eq!(merged.contacts.keys().collect::<Vec<_>>(), vec![&bob_key]);
+    assert_eq!(merged.shared_with("bank").len(), 1);
+    assert_eq!(
+        merged,
+        block_on(other.sync_merge(&RustCrypto, &alice.config, None)).unwrap()
+    );
+}
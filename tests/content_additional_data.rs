@@ -0,0 +1,91 @@
+mod common;
+
+use futures::executor::block_on;
+
+use common::user_handle;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::encrypt_with;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::EncryptedContent;
+use passwordless_passwords_prf::crypto::EncryptedContentAdditionalData;
+
+fn encrypt_entry(key: &TestKey, name: &str, user: u8) -> EncryptedContent {
+    block_on(encrypt_with(
+        &RustCrypto,
+        name.as_bytes(),
+        &EncryptedContentAdditionalData::new(name, &user_handle(user)),
+        std::slice::from_ref(&key.keypair),
+    ))
+    .unwrap()
+}
+
+fn decrypt_entry(
+    key: &TestKey,
+    content: &EncryptedContent,
+    name: &str,
+    user: u8,
+) -> Result<Vec<u8>, String> {
+    block_on(decrypt_with(
+        &RustCrypto,
+        content,
+        &EncryptedContentAdditionalData::new(name, &user_handle(user)),
+        &key.unlock(),
+        None,
+    ))
+    .map_err(|err| err.to_string())
+}
+
+#[test]
+fn decrypts_under_original_name() {
+    let key = TestKey::new(1);
+    let bank = encrypt_entry(&key, "bank", 0);
+    assert_eq!(decrypt_entry(&key, &bank, "bank", 0).unwrap(), b"bank");
+}
+
+#[test]
+fn rejects_swapped_entries() {
+    let key = TestKey::new(1);
+    let bank = encrypt_entry(&key, "bank", 0);
+    let email = encrypt_entry(&key, "email", 0);
+
+    assert!(decrypt_entry(&key, &email, "bank", 0).is_err());
+    assert!(decrypt_entry(&key, &bank, "email", 0).is_err());
+}
+
+#[test]
+fn rejects_renamed_entry() {
+    let key = TestKey::new(1);
+    let bank = encrypt_entry(&key, "bank", 0);
+    assert!(decrypt_entry(&key, &bank, "bank ", 0).is_err());
+}
+
+#[test]
+fn rejects_entry_from_other_vault() {
+    let key = TestKey::new(1);
+    let bank = encrypt_entry(&key, "bank", 0);
+    assert!(decrypt_entry(&key, &bank, "bank", 1).is_err());
+}
+
+#[test]
+fn rejects_tampered_additional_data() {
+    let key = TestKey::new(1);
+    let bank = encrypt_entry(&key, "bank", 0);
+
+    // Rewrite the stored associated data to claim a different name; the
+    // AES-GCM tag no longer matches.
+    let mut json = serde_json::to_value(&bank).unwrap();
+    json["additional_data"] = serde_json::to_value(
+        passwordless_passwords_prf::data::base64::Base64Wrapper::from(
+            &serde_json::to_vec(&EncryptedContentAdditionalData::new(
+                "email",
+                &user_handle(0),
+            ))
+            .unwrap(),
+        ),
+    )
+    .unwrap();
+    let tampered: EncryptedContent = serde_json::from_value(json).unwrap();
+
+    assert!(decrypt_entry(&key, &tampered, "email", 0).is_err());
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use ::base64::engine::general_purpose::STANDARD;
+use ::base64::Engine;
+use futures::executor::block_on;
+
+use common::user_handle;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::encrypt_with;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::ContentFormat;
+use passwordless_passwords_prf::crypto::EncryptedContent;
+use passwordless_passwords_prf::crypto::EncryptedContentAdditionalData;
+use passwordless_passwords_prf::data::migration::parse_vault_config;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_LENGTH: usize = 16;
+
+fn encrypt(key: &TestKey, data: &[u8]) -> EncryptedContent {
+    block_on(encrypt_with(
+        &RustCrypto,
+        data,
+        &EncryptedContentAdditionalData::new("file", &user_handle(0)),
+        std::slice::from_ref(&key.keypair),
+    ))
+    .unwrap()
+}
+
+fn decrypt(key: &TestKey, content: &EncryptedContent) -> Result<Vec<u8>, String> {
+    block_on(decrypt_with(
+        &RustCrypto,
+        content,
+        &EncryptedContentAdditionalData::new("file", &user_handle(0)),
+        &key.unlock(),
+        None,
+    ))
+    .map_err(|err| err.to_string())
+}
+
+/// Replace the ciphertext of `content` with `f` applied to it.
+fn tamper(content: &EncryptedContent, f: impl FnOnce(&mut Vec<u8>)) -> EncryptedContent {
+    let mut json = serde_json::to_value(content).unwrap();
+    let mut ciphertext = STANDARD
+        .decode(json["ciphertext"]["$base64"].as_str().unwrap())
+        .unwrap();
+    f(&mut ciphertext);
+    json["ciphertext"]["$base64"] = STANDARD.encode(ciphertext).into();
+    serde_json::from_value(json).unwrap()
+}
+
+#[test]
+fn encrypts_new_content_in_chunks() {
+    let key = TestKey::new(1);
+    let content = encrypt(&key, b"hunter2");
+    assert_eq!(
+        content.format().unwrap(),
+        ContentFormat::Stream {
+            chunk_size: CHUNK_SIZE as u32
+        }
+    );
+    assert_eq!(decrypt(&key, &content).unwrap(), b"hunter2");
+}
+
+#[test]
+fn stream_roundtrip_at_chunk_boundaries() {
+    let key = TestKey::new(1);
+    for len in [
+        0,
+        1,
+        CHUNK_SIZE - 1,
+        CHUNK_SIZE,
+        CHUNK_SIZE + 1,
+        3 * CHUNK_SIZE,
+    ] {
+        let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        assert_eq!(decrypt(&key, &encrypt(&key, &data)).unwrap(), data, "{len}");
+    }
+}
+
+#[test]
+fn rejects_truncated_stream() {
+    let key = TestKey::new(1);
+    let content = encrypt(&key, &vec![0; 2 * CHUNK_SIZE + 10]);
+
+    let last_chunk_dropped = tamper(&content, |ciphertext| {
+        ciphertext.truncate(2 * (CHUNK_SIZE + TAG_LENGTH))
+    });
+    assert!(decrypt(&key, &last_chunk_dropped).is_err());
+
+    let emptied = tamper(&content, Vec::clear);
+    assert!(decrypt(&key, &emptied).is_err());
+}
+
+#[test]
+fn rejects_reordered_stream() {
+    let key = TestKey::new(1);
+    let data: Vec<u8> = (0..2 * CHUNK_SIZE)
+        .map(|i| (i / CHUNK_SIZE) as u8)
+        .collect();
+    let content = encrypt(&key, &data);
+
+    let swapped = tamper(&content, |ciphertext| {
+        let (first, second) = ciphertext.split_at_mut(CHUNK_SIZE + TAG_LENGTH);
+        first.swap_with_slice(second);
+    });
+    assert!(decrypt(&key, &swapped).is_err());
+}
+
+#[test]
+fn reads_single_shot_content() {
+    let config = parse_vault_config(include_str!("fixtures/vault-v5.json")).unwrap();
+    assert_eq!(
+        config.contents["fixture"].format().unwrap(),
+        ContentFormat::SingleShot
+    );
+}
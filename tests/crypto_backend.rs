@@ -0,0 +1,116 @@
+mod common;
+
+use std::rc::Rc;
+
+use futures::executor::block_on;
+use serde::Deserialize;
+
+use common::user_handle;
+use common::TestKey;
+
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::encrypt_with;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::unwrap_keypair;
+use passwordless_passwords_prf::crypto::ContentKey;
+use passwordless_passwords_prf::crypto::EncryptedContent;
+use passwordless_passwords_prf::crypto::EncryptedContentAdditionalData;
+use passwordless_passwords_prf::crypto::UnlockedKey;
+use passwordless_passwords_prf::crypto::WrappedKeypair;
+
+/// Values produced with WebCrypto (`crypto.subtle` in Node.js), following the
+/// same steps as the [passwordless_passwords_prf::crypto::webcrypto::WebCrypto]
+/// backend.
+#[derive(Deserialize)]
+struct WebCryptoFixture {
+    #[serde(with = "passwordless_passwords_prf::data::base64")]
+    prf_output: Vec<u8>,
+    wrapped_keypair: WrappedKeypair,
+    content: EncryptedContent,
+}
+
+fn webcrypto_fixture() -> WebCryptoFixture {
+    serde_json::from_str(include_str!("fixtures/webcrypto-v2.json")).unwrap()
+}
+
+#[test]
+fn rustcrypto_decrypts_webcrypto_output() {
+    let fixture = webcrypto_fixture();
+    let unlocked = UnlockedKey {
+        credential_id: match &fixture.content.key {
+            ContentKey::Recipients { recipients } => recipients[0].credential_id.clone(),
+            _ => panic!("Expected format version 2 content"),
+        },
+        private_key: block_on(unwrap_keypair(
+            &RustCrypto,
+            &fixture.wrapped_keypair,
+            &fixture.prf_output,
+        ))
+        .unwrap(),
+    };
+    let content = block_on(decrypt_with(
+        &RustCrypto,
+        &fixture.content,
+        &EncryptedContentAdditionalData::new("fixture", &user_handle(0)),
+        &unlocked,
+        None,
+    ))
+    .unwrap();
+
+    assert_eq!(content, b"correct horse battery staple");
+}
+
+#[test]
+fn unwrap_keypair_fails_with_wrong_prf_output() {
+    let fixture = webcrypto_fixture();
+    let mut prf_output = fixture.prf_output.clone();
+    prf_output[0] ^= 1;
+
+    assert!(block_on(unwrap_keypair(
+        &RustCrypto,
+        &fixture.wrapped_keypair,
+        &prf_output
+    ))
+    .is_err());
+}
+
+#[test]
+fn rustcrypto_roundtrip_to_multiple_recipients() {
+    let keys = [TestKey::new(1), TestKey::new(2)];
+    let keypairs: Vec<Rc<WrappedKeypair>> = keys.iter().map(|k| Rc::clone(&k.keypair)).collect();
+    let additional_data = EncryptedContentAdditionalData::new("test", &user_handle(0));
+
+    let encrypted = block_on(encrypt_with(
+        &RustCrypto,
+        b"hunter2",
+        &additional_data,
+        &keypairs,
+    ))
+    .unwrap();
+    assert!(matches!(
+        &encrypted.key,
+        ContentKey::Recipients { recipients } if recipients.len() == 2
+    ));
+
+    // Survives a trip through the stored JSON representation
+    let encrypted: EncryptedContent =
+        serde_json::from_str(&serde_json::to_string(&encrypted).unwrap()).unwrap();
+
+    for key in &keys {
+        let keypair: WrappedKeypair =
+            serde_json::from_str(&serde_json::to_string(&key.keypair).unwrap()).unwrap();
+        let unlocked = UnlockedKey {
+            credential_id: key.credential_id.clone(),
+            private_key: block_on(unwrap_keypair(&RustCrypto, &keypair, &key.prf_output)).unwrap(),
+        };
+        let decrypted = block_on(decrypt_with(
+            &RustCrypto,
+            &encrypted,
+            &additional_data,
+            &unlocked,
+            None,
+        ))
+        .unwrap();
+        assert_eq!(decrypted, b"hunter2");
+    }
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use std::rc::Rc;
+
+use futures::executor::block_on;
+
+use common::legacy_vault;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::MasterKey;
+use passwordless_passwords_prf::data::migration::parse_vault_config;
+use passwordless_passwords_prf::data::vault::VaultConfig;
+use passwordless_passwords_prf::error::Error;
+
+fn vault_with_entries(key: &TestKey) -> (VaultConfig, MasterKey) {
+    let mut config = legacy_vault(&[key], &[("legacy", b"0000")]);
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    block_on(config.push_content(&RustCrypto, "bank".into(), b"1234".to_vec(), &master_key))
+        .unwrap();
+    block_on(config.push_content(&RustCrypto, "email".into(), b"5678".to_vec(), &master_key))
+        .unwrap();
+    (config, master_key)
+}
+
+fn decrypt_by_name(
+    config: &VaultConfig,
+    key: &TestKey,
+    master_key: &MasterKey,
+    name: &str,
+) -> Vec<u8> {
+    let names = block_on(config.decrypt_names(&RustCrypto, master_key)).unwrap();
+    let (id, _) = names.iter().find(|(_, n)| *n == name).unwrap();
+    block_on(decrypt_with(
+        &RustCrypto,
+        &config.contents[id],
+        &config.content_additional_data(id),
+        &key.unlock(),
+        Some(master_key),
+    ))
+    .unwrap()
+}
+
+#[test]
+fn names_are_not_stored_in_plaintext() {
+    let key = TestKey::new(1);
+    let (mut config, master_key) = vault_with_entries(&key);
+
+    block_on(config.encrypt_names(&RustCrypto, &key.unlock(), &master_key)).unwrap();
+
+    assert!(config.has_encrypted_names());
+    let stored = serde_json::to_string(&config).unwrap();
+    for name in ["legacy", "bank", "email"] {
+        // Base64 never contains quotes, so this only matches a JSON string
+        assert!(
+            !stored.contains(&format!("\"{name}\"")),
+            "{name} found in {stored}"
+        );
+    }
+
+    let reloaded = parse_vault_config(&stored).unwrap();
+    assert_eq!(
+        decrypt_by_name(&reloaded, &key, &master_key, "legacy"),
+        b"0000"
+    );
+    assert_eq!(
+        decrypt_by_name(&reloaded, &key, &master_key, "bank"),
+        b"1234"
+    );
+    assert_eq!(
+        decrypt_by_name(&reloaded, &key, &master_key, "email"),
+        b"5678"
+    );
+}
+
+#[test]
+fn push_content_assigns_ids_and_rejects_duplicate_names() {
+    let key = TestKey::new(1);
+    let (mut config, master_key) = vault_with_entries(&key);
+    block_on(config.encrypt_names(&RustCrypto, &key.unlock(), &master_key)).unwrap();
+
+    block_on(config.push_content(&RustCrypto, "forum".into(), b"9012".to_vec(), &master_key))
+        .unwrap();
+    assert!(!config.contents.contains_key("forum"));
+    assert_eq!(
+        decrypt_by_name(&config, &key, &master_key, "forum"),
+        b"9012"
+    );
+
+    let contents_before = config.contents.clone();
+    assert!(matches!(
+        block_on(config.push_content(&RustCrypto, "bank".into(), b"0".to_vec(), &master_key)),
+        Err(Error::InvalidInput(_))
+    ));
+    assert_eq!(config.contents, contents_before);
+}
+
+#[test]
+fn deleted_entries_drop_out_of_names() {
+    let key = TestKey::new(1);
+    let (mut config, master_key) = vault_with_entries(&key);
+    block_on(config.encrypt_names(&RustCrypto, &key.unlock(), &master_key)).unwrap();
+
+    let names = block_on(config.decrypt_names(&RustCrypto, &master_key)).unwrap();
+    let (bank_id, _) = names.iter().find(|(_, n)| *n == "bank").unwrap();
+    config.contents.remove(bank_id);
+
+    let names = block_on(config.decrypt_names(&RustCrypto, &master_key)).unwrap();
+    assert!(!names.contains_name("bank"));
+    assert!(names.contains_name("email"));
+
+    // The name can be reused
+    block_on(config.push_content(&RustCrypto, "bank".into(), b"4321".to_vec(), &master_key))
+        .unwrap();
+    assert_eq!(decrypt_by_name(&config, &key, &master_key, "bank"), b"4321");
+}
+
+#[test]
+fn encrypt_names_leaves_vault_unchanged_on_failure() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    // "legacy" is wrapped to key1 only, so key2 cannot decrypt it
+    let mut config = legacy_vault(&[&key1], &[("legacy", b"0000")]);
+    Rc::make_mut(&mut Rc::make_mut(&mut config.user).keypairs).push(Rc::clone(&key2.keypair));
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    let before = config.clone();
+
+    assert!(block_on(config.encrypt_names(&RustCrypto, &key2.unlock(), &master_key)).is_err());
+    assert_eq!(config, before);
+}
@@ -0,0 +1,121 @@
+use passwordless_passwords_prf::data::entry::Attachment;
+use passwordless_passwords_prf::data::entry::CustomField;
+use passwordless_passwords_prf::data::entry::Entry;
+use passwordless_passwords_prf::data::entry::Login;
+use passwordless_passwords_prf::data::totp::Totp;
+use passwordless_passwords_prf::error::Error;
+
+#[test]
+fn login_roundtrip() {
+    let entry = Entry::Login(Login {
+        username: "alice".to_string(),
+        password: "hunter2".to_string(),
+        urls: vec!["https://example.org/login".to_string()],
+        notes: "Security question: blue".to_string(),
+        custom_fields: vec![CustomField {
+            name: "PIN".to_string(),
+            value: "1234".to_string(),
+            hidden: true,
+        }],
+        totp: Some(Totp::parse("JBSWY3DPEHPK3PXP").unwrap()),
+    });
+    assert_eq!(
+        Entry::from_bytes(&entry.to_bytes().unwrap()).unwrap(),
+        entry
+    );
+}
+
+#[test]
+fn text_roundtrip() {
+    let entry = Entry::Text {
+        text: "{\"type\": \"login\"}".to_string(),
+    };
+    assert_eq!(
+        Entry::from_bytes(&entry.to_bytes().unwrap()).unwrap(),
+        entry
+    );
+}
+
+#[test]
+fn file_roundtrip() {
+    let data: Vec<u8> = (0..=255).collect();
+    let entry = Entry::File(
+        Attachment::new(
+            "id_ed25519".to_string(),
+            "application/x-pem-file".to_string(),
+            data.clone(),
+            1024,
+        )
+        .unwrap(),
+    );
+    assert_eq!(
+        Entry::from_bytes(&entry.to_bytes().unwrap()).unwrap(),
+        entry
+    );
+
+    let json = serde_json::to_value(&entry).unwrap();
+    assert_eq!(json["type"], "file");
+    assert_eq!(json["filename"], "id_ed25519");
+    assert!(json["data"]["$base64"].is_string());
+}
+
+#[test]
+fn file_defaults_mime_type() {
+    let attachment =
+        Attachment::new("recovery.bin".to_string(), "".to_string(), vec![0], 1).unwrap();
+    assert_eq!(attachment.mime_type, "application/octet-stream");
+}
+
+#[test]
+fn rejects_file_over_size_limit() {
+    assert!(matches!(
+        Attachment::new(
+            "big.pdf".to_string(),
+            "application/pdf".to_string(),
+            vec![0; 11],
+            10
+        ),
+        Err(Error::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn serializes_type_discriminator() {
+    let json = serde_json::to_value(Entry::Login(Login::default())).unwrap();
+    assert_eq!(json["type"], "login");
+}
+
+#[test]
+fn decodes_legacy_plain_text() {
+    assert_eq!(
+        Entry::from_bytes(b"correct horse battery staple").unwrap(),
+        Entry::Text {
+            text: "correct horse battery staple".to_string()
+        }
+    );
+    assert_eq!(
+        Entry::from_bytes(b"{\"not\": \"an entry\"}").unwrap(),
+        Entry::Text {
+            text: "{\"not\": \"an entry\"}".to_string()
+        }
+    );
+}
+
+#[test]
+fn decodes_login_with_missing_fields() {
+    assert_eq!(
+        Entry::from_bytes(b"{\"type\": \"login\", \"password\": \"hunter2\"}").unwrap(),
+        Entry::Login(Login {
+            password: "hunter2".to_string(),
+            ..Login::default()
+        })
+    );
+}
+
+#[test]
+fn rejects_binary_legacy_content() {
+    assert!(matches!(
+        Entry::from_bytes(&[0xff, 0xfe]),
+        Err(Error::MalformedRecord(_))
+    ));
+}
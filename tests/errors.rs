@@ -0,0 +1,108 @@
+mod common;
+
+use futures::executor::block_on;
+
+use common::legacy_vault;
+use common::user_handle;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::encrypt_with;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::unwrap_keypair;
+use passwordless_passwords_prf::crypto::EncryptedContent;
+use passwordless_passwords_prf::crypto::EncryptedContentAdditionalData;
+use passwordless_passwords_prf::data::CredentialId;
+use passwordless_passwords_prf::error::Error;
+
+fn encrypt_entry(key: &TestKey, name: &str) -> EncryptedContent {
+    block_on(encrypt_with(
+        &RustCrypto,
+        b"hunter2",
+        &EncryptedContentAdditionalData::new(name, &user_handle(0)),
+        std::slice::from_ref(&key.keypair),
+    ))
+    .unwrap()
+}
+
+fn decrypt_entry(key: &TestKey, content: &EncryptedContent, name: &str) -> Result<Vec<u8>, Error> {
+    block_on(decrypt_with(
+        &RustCrypto,
+        content,
+        &EncryptedContentAdditionalData::new(name, &user_handle(0)),
+        &key.unlock(),
+        None,
+    ))
+}
+
+#[test]
+fn wrong_prf_output_is_aead_failure() {
+    let key = TestKey::new(1);
+    assert!(matches!(
+        block_on(unwrap_keypair(&RustCrypto, &key.keypair, &[0; 32])),
+        Err(Error::AeadFailure)
+    ));
+}
+
+#[test]
+fn other_credential_is_no_matching_recipient() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let bank = encrypt_entry(&key1, "bank");
+    assert!(matches!(
+        decrypt_entry(&key2, &bank, "bank"),
+        Err(Error::NoMatchingRecipient)
+    ));
+}
+
+#[test]
+fn unknown_credential_is_invalid_input() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let mut config = legacy_vault(&[&key1], &[]);
+    assert!(matches!(
+        config.rename_credential(
+            &CredentialId::from(key2.credential_id.clone()),
+            "key 2".to_string()
+        ),
+        Err(Error::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn modified_ciphertext_is_aead_failure() {
+    let key = TestKey::new(1);
+    let bank = encrypt_entry(&key, "bank");
+
+    let mut json = serde_json::to_value(&bank).unwrap();
+    let ciphertext = json["ciphertext"]["$base64"].as_str().unwrap().to_string();
+    let flipped = if ciphertext.starts_with('A') {
+        "B"
+    } else {
+        "A"
+    };
+    json["ciphertext"]["$base64"] = format!("{flipped}{}", &ciphertext[1..]).into();
+    let tampered: EncryptedContent = serde_json::from_value(json).unwrap();
+
+    assert!(matches!(
+        decrypt_entry(&key, &tampered, "bank"),
+        Err(Error::AeadFailure)
+    ));
+}
+
+#[test]
+fn renamed_entry_is_malformed_record() {
+    let key = TestKey::new(1);
+    let bank = encrypt_entry(&key, "bank");
+    assert!(matches!(
+        decrypt_entry(&key, &bank, "email"),
+        Err(Error::MalformedRecord(_))
+    ));
+}
+
+#[test]
+fn invalid_json_is_malformed_record() {
+    let err: Error = serde_json::from_str::<EncryptedContent>("{\"ciphertext\": 1}")
+        .unwrap_err()
+        .into();
+    assert!(matches!(err, Error::MalformedRecord(_)));
+}
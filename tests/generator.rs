@@ -0,0 +1,130 @@
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::error::Error;
+use passwordless_passwords_prf::generator::generate_passphrase;
+use passwordless_passwords_prf::generator::generate_password;
+use passwordless_passwords_prf::generator::random_below;
+use passwordless_passwords_prf::generator::wordlist;
+use passwordless_passwords_prf::generator::PassphrasePolicy;
+use passwordless_passwords_prf::generator::PasswordPolicy;
+
+#[test]
+fn random_below_is_in_range_and_covers_it() {
+    let mut seen = [false; 7];
+    for _ in 0..1000 {
+        let r = random_below(&RustCrypto, 7).unwrap();
+        seen[r as usize] = true;
+    }
+    assert!(seen.iter().all(|s| *s));
+    assert_eq!(random_below(&RustCrypto, 1).unwrap(), 0);
+    assert!(matches!(
+        random_below(&RustCrypto, 0),
+        Err(Error::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn password_contains_every_required_class() {
+    let policy = PasswordPolicy {
+        length: 4,
+        ..PasswordPolicy::default()
+    };
+    for _ in 0..100 {
+        let password = generate_password(&RustCrypto, &policy).unwrap().value;
+        assert_eq!(password.chars().count(), 4);
+        assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+        assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+        assert!(password.chars().any(|c| c.is_ascii_digit()));
+        assert!(password.chars().any(|c| c.is_ascii_punctuation()));
+    }
+}
+
+#[test]
+fn password_excludes_lookalikes_and_disabled_classes() {
+    let policy = PasswordPolicy {
+        length: 200,
+        symbols: false,
+        exclude_lookalikes: true,
+        ..PasswordPolicy::default()
+    };
+    let password = generate_password(&RustCrypto, &policy).unwrap().value;
+    assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+    assert!(!password.chars().any(|c| "Il1O0o".contains(c)));
+}
+
+#[test]
+fn password_entropy() {
+    let digits_only = PasswordPolicy {
+        length: 10,
+        lowercase: false,
+        uppercase: false,
+        digits: true,
+        symbols: false,
+        exclude_lookalikes: false,
+    };
+    let generated = generate_password(&RustCrypto, &digits_only).unwrap();
+    assert!((generated.entropy_bits - 10.0 * 10_f64.log2()).abs() < 1e-9);
+
+    // Two classes of 10 digits and 26 letters with length 2: 2 * 10 * 26 valid passwords
+    let two_classes = PasswordPolicy {
+        length: 2,
+        lowercase: true,
+        ..digits_only.clone()
+    };
+    let generated = generate_password(&RustCrypto, &two_classes).unwrap();
+    assert!((generated.entropy_bits - 520_f64.log2()).abs() < 1e-9);
+
+    let long = PasswordPolicy {
+        length: 1024,
+        ..PasswordPolicy::default()
+    };
+    let generated = generate_password(&RustCrypto, &long).unwrap();
+    assert!((generated.entropy_bits - 1024.0 * 94_f64.log2()).abs() < 1e-6);
+}
+
+#[test]
+fn rejects_impossible_password_policies() {
+    for policy in [
+        PasswordPolicy {
+            length: 3,
+            ..PasswordPolicy::default()
+        },
+        PasswordPolicy {
+            lowercase: false,
+            uppercase: false,
+            digits: false,
+            symbols: false,
+            ..PasswordPolicy::default()
+        },
+    ] {
+        assert!(matches!(
+            generate_password(&RustCrypto, &policy),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+}
+
+#[test]
+fn passphrase_uses_wordlist() {
+    let words: Vec<&str> = wordlist().collect();
+    assert_eq!(words.len(), 2048);
+
+    let generated = generate_passphrase(&RustCrypto, &PassphrasePolicy::default()).unwrap();
+    let parts: Vec<&str> = generated.value.split('-').collect();
+    assert_eq!(parts.len(), 6);
+    assert!(parts.iter().all(|word| words.contains(word)));
+    assert!((generated.entropy_bits - 66.0).abs() < 1e-9);
+
+    let capitalized = generate_passphrase(
+        &RustCrypto,
+        &PassphrasePolicy {
+            words: 3,
+            separator: " ".to_string(),
+            capitalize: true,
+        },
+    )
+    .unwrap();
+    assert!(capitalized
+        .value
+        .split(' ')
+        .all(|word| word.starts_with(|c: char| c.is_ascii_uppercase())));
+}
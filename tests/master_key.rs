@@ -0,0 +1,120 @@
+mod common;
+
+use std::rc::Rc;
+
+use futures::executor::block_on;
+
+use common::legacy_vault;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::ContentKey;
+use passwordless_passwords_prf::crypto::MasterKey;
+use passwordless_passwords_prf::data::vault::VaultConfig;
+use passwordless_passwords_prf::data::CredentialId;
+
+fn decrypt_entry(config: &VaultConfig, key: &TestKey, name: &str) -> Result<Vec<u8>, String> {
+    let unlocked = key.unlock();
+    let master_key: Option<MasterKey> = config
+        .master_key
+        .as_ref()
+        .and_then(|_| block_on(config.unwrap_master_key(&RustCrypto, &unlocked)).ok());
+    block_on(decrypt_with(
+        &RustCrypto,
+        &config.contents[name],
+        &config.content_additional_data(name),
+        &unlocked,
+        master_key.as_ref(),
+    ))
+    .map_err(|err| err.to_string())
+}
+
+#[test]
+fn new_credential_reads_existing_entries_after_one_rewrap() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let mut config = legacy_vault(&[&key1], &[]);
+
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    block_on(config.push_content(&RustCrypto, "bank".into(), b"1234".to_vec(), &master_key))
+        .unwrap();
+    block_on(config.push_content(&RustCrypto, "email".into(), b"5678".to_vec(), &master_key))
+        .unwrap();
+    let contents_before = config.contents.clone();
+
+    let master_key = block_on(config.unwrap_master_key(&RustCrypto, &key1.unlock())).unwrap();
+    block_on(config.add_credential(&RustCrypto, (*key2.keypair).clone(), &master_key)).unwrap();
+
+    assert_eq!(config.contents, contents_before);
+    assert_eq!(config.master_key.as_ref().unwrap().recipients.len(), 2);
+    assert_eq!(decrypt_entry(&config, &key2, "bank").unwrap(), b"1234");
+    assert_eq!(decrypt_entry(&config, &key2, "email").unwrap(), b"5678");
+}
+
+#[test]
+fn deleted_credential_loses_access_to_master_key() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let mut config = legacy_vault(&[&key1, &key2], &[]);
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    block_on(config.push_content(&RustCrypto, "bank".into(), b"1234".to_vec(), &master_key))
+        .unwrap();
+
+    config
+        .delete_credential(&CredentialId::from(key2.credential_id.clone()))
+        .unwrap();
+
+    assert_eq!(config.user.keypairs.len(), 1);
+    assert_eq!(config.master_key.as_ref().unwrap().recipients.len(), 1);
+    assert!(block_on(config.unwrap_master_key(&RustCrypto, &key2.unlock())).is_err());
+    assert_eq!(decrypt_entry(&config, &key1, "bank").unwrap(), b"1234");
+}
+
+#[test]
+fn migrates_v2_vault() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let mut config = legacy_vault(&[&key1, &key2], &[("bank", b"1234"), ("email", b"5678")]);
+    assert!(config.master_key.is_none());
+
+    // Legacy entries stay readable once the vault has a master key
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    assert_eq!(decrypt_entry(&config, &key2, "bank").unwrap(), b"1234");
+
+    let upgraded =
+        block_on(config.upgrade_content(&RustCrypto, &key1.unlock(), &master_key)).unwrap();
+    assert_eq!(upgraded, vec!["bank".to_string(), "email".to_string()]);
+    assert!(config
+        .contents
+        .values()
+        .all(|content| matches!(content.key, ContentKey::MasterKey { .. })));
+
+    // A key added after the migration can read everything
+    let key3 = TestKey::new(3);
+    block_on(config.add_credential(&RustCrypto, (*key3.keypair).clone(), &master_key)).unwrap();
+    assert_eq!(decrypt_entry(&config, &key3, "bank").unwrap(), b"1234");
+    assert_eq!(decrypt_entry(&config, &key3, "email").unwrap(), b"5678");
+
+    // And the result survives serialization
+    let config: VaultConfig =
+        serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+    assert_eq!(decrypt_entry(&config, &key2, "email").unwrap(), b"5678");
+}
+
+#[test]
+fn upgrade_skips_entries_not_readable_by_unlocked_key() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let mut config = legacy_vault(&[&key1], &[("bank", b"1234")]);
+    Rc::make_mut(&mut Rc::make_mut(&mut config.user).keypairs).push(Rc::clone(&key2.keypair));
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+
+    let upgraded =
+        block_on(config.upgrade_content(&RustCrypto, &key2.unlock(), &master_key)).unwrap();
+
+    assert!(upgraded.is_empty());
+    assert!(matches!(
+        config.contents["bank"].key,
+        ContentKey::Recipients { .. }
+    ));
+}
@@ -0,0 +1,211 @@
+mod common;
+
+use std::collections::HashMap;
+
+use futures::executor::block_on;
+
+use common::legacy_vault;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::MasterKey;
+use passwordless_passwords_prf::data::vault::MergeEntry;
+use passwordless_passwords_prf::data::vault::MergeResolution;
+use passwordless_passwords_prf::data::vault::VaultConfig;
+use passwordless_passwords_prf::error::Error;
+
+fn push(config: &mut VaultConfig, master_key: &MasterKey, name: &str, content: &[u8]) {
+    block_on(config.push_content(&RustCrypto, name.into(), content.to_vec(), master_key)).unwrap();
+}
+
+fn names(entries: &[MergeEntry]) -> Vec<&str> {
+    entries.iter().map(|entry| entry.name.as_str()).collect()
+}
+
+fn decrypt_by_name(config: &VaultConfig, key: &TestKey, name: &str) -> Vec<u8> {
+    let unlocked = key.unlock();
+    let master_key = block_on(config.unwrap_master_key(&RustCrypto, &unlocked)).unwrap();
+    let names = block_on(config.decrypt_names(&RustCrypto, &master_key)).unwrap();
+    let (id, _) = names.iter().find(|(_, n)| *n == name).unwrap();
+    block_on(decrypt_with(
+        &RustCrypto,
+        &config.contents[id],
+        &config.content_additional_data(id),
+        &unlocked,
+        Some(&master_key),
+    ))
+    .unwrap()
+}
+
+/// A vault, and an export of it from another device where `email` was
+/// changed, `bank` deleted, `forum` added and a second credential registered.
+fn diverged(key1: &TestKey, key2: &TestKey) -> (VaultConfig, VaultConfig, MasterKey) {
+    let mut local = legacy_vault(&[key1], &[]);
+    let master_key = block_on(local.create_master_key(&RustCrypto)).unwrap();
+    push(&mut local, &master_key, "bank", b"1234");
+    push(&mut local, &master_key, "email", b"5678");
+
+    let mut imported = local.clone();
+    imported.delete_content("bank").unwrap();
+    push(&mut imported, &master_key, "email", b"8765");
+    push(&mut imported, &master_key, "forum", b"9012");
+    block_on(imported.add_credential(&RustCrypto, (*key2.keypair).clone(), &master_key)).unwrap();
+
+    push(&mut local, &master_key, "news", b"3456");
+
+    (local, imported, master_key)
+}
+
+#[test]
+fn preview_lists_additions_removals_and_conflicts() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let (local, imported, _) = diverged(&key1, &key2);
+
+    let preview = block_on(local.merge_preview(&RustCrypto, &imported, None)).unwrap();
+    assert_eq!(preview.added_credentials.len(), 1);
+    assert!(preview.removed_credentials.is_empty());
+    assert_eq!(names(&preview.added_entries), vec!["forum"]);
+    assert_eq!(names(&preview.removed_entries), vec!["bank"]);
+    assert_eq!(names(&preview.conflicts), vec!["email"]);
+
+    let unchanged = block_on(local.merge_preview(&RustCrypto, &local, None)).unwrap();
+    assert!(unchanged.is_empty());
+}
+
+#[test]
+fn merge_takes_newest_revisions_by_default() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let (local, imported, _) = diverged(&key1, &key2);
+
+    let preview = block_on(local.merge_preview(&RustCrypto, &imported, None)).unwrap();
+    let merged = block_on(local.merge(&RustCrypto, &preview, &HashMap::new(), None)).unwrap();
+
+    assert_eq!(merged.user.keypairs.len(), 2);
+    assert_eq!(merged.master_key.as_ref().unwrap().recipients.len(), 2);
+    assert!(!merged.contents.contains_key("bank"));
+    assert_eq!(decrypt_by_name(&merged, &key1, "email"), b"8765");
+    assert_eq!(decrypt_by_name(&merged, &key1, "news"), b"3456");
+    assert_eq!(decrypt_by_name(&merged, &key2, "forum"), b"9012");
+    assert_eq!(
+        merged,
+        block_on(local.sync_merge(&RustCrypto, &imported, None)).unwrap()
+    );
+}
+
+#[test]
+fn merge_applies_resolutions() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let (local, imported, _) = diverged(&key1, &key2);
+
+    let preview = block_on(local.merge_preview(&RustCrypto, &imported, None)).unwrap();
+    let resolutions = HashMap::from([
+        ("email".to_string(), MergeResolution::KeepLocal),
+        ("bank".to_string(), MergeResolution::KeepLocal),
+        ("forum".to_string(), MergeResolution::TakeMerged),
+    ]);
+    let merged = block_on(local.merge(&RustCrypto, &preview, &resolutions, None)).unwrap();
+
+    let mut keys: Vec<&String> = merged.contents.keys().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["bank", "email", "forum", "news"]);
+    assert_eq!(decrypt_by_name(&merged, &key2, "bank"), b"1234");
+    assert_eq!(decrypt_by_name(&merged, &key2, "email"), b"5678");
+
+    // The kept entries are newer than the imported ones now
+    let remerged = block_on(merged.sync_merge(&RustCrypto, &imported, None)).unwrap();
+    assert_eq!(remerged, merged);
+}
+
+#[test]
+fn sync_needs_review_of_deletions_and_credential_changes() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let (local, imported, master_key) = diverged(&key1, &key2);
+
+    let merged = block_on(local.sync_merge(&RustCrypto, &imported, None)).unwrap();
+    assert!(local.needs_review(&merged));
+
+    // Once merged locally, syncing with the same copy again changes nothing
+    // that needs review
+    let merged_again = block_on(merged.sync_merge(&RustCrypto, &imported, None)).unwrap();
+    assert!(!merged.needs_review(&merged_again));
+
+    // Additions and changes alone are merged without review
+    let mut changed = local.clone();
+    push(&mut changed, &master_key, "email", b"8765");
+    push(&mut changed, &master_key, "forum", b"9012");
+    let merged = block_on(local.sync_merge(&RustCrypto, &changed, None)).unwrap();
+    assert!(!local.needs_review(&merged));
+}
+
+#[test]
+fn deleted_credential_stays_deleted() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let mut local = legacy_vault(&[&key1], &[]);
+    let master_key = block_on(local.create_master_key(&RustCrypto)).unwrap();
+    block_on(local.add_credential(&RustCrypto, (*key2.keypair).clone(), &master_key)).unwrap();
+    push(&mut local, &master_key, "bank", b"1234");
+
+    let stale = local.clone();
+    local
+        .delete_credential(&key2.credential_id.clone().into())
+        .unwrap();
+
+    let preview = block_on(stale.merge_preview(&RustCrypto, &local, None)).unwrap();
+    assert_eq!(preview.removed_credentials.len(), 1);
+
+    let merged = block_on(stale.sync_merge(&RustCrypto, &local, None)).unwrap();
+    assert_eq!(merged.user.keypairs.len(), 1);
+    assert_eq!(merged.master_key.as_ref().unwrap().recipients.len(), 1);
+    assert_eq!(decrypt_by_name(&merged, &key1, "bank"), b"1234");
+}
+
+#[test]
+fn merges_encrypted_names() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let mut local = legacy_vault(&[&key1], &[]);
+    let master_key = block_on(local.create_master_key(&RustCrypto)).unwrap();
+    push(&mut local, &master_key, "bank", b"1234");
+    block_on(local.encrypt_names(&RustCrypto, &key1.unlock(), &master_key)).unwrap();
+
+    let mut imported = local.clone();
+    push(&mut imported, &master_key, "forum", b"9012");
+    push(&mut local, &master_key, "news", b"3456");
+    block_on(imported.add_credential(&RustCrypto, (*key2.keypair).clone(), &master_key)).unwrap();
+
+    assert!(matches!(
+        block_on(local.merge_preview(&RustCrypto, &imported, None)),
+        Err(Error::InvalidInput(_))
+    ));
+
+    let preview = block_on(local.merge_preview(&RustCrypto, &imported, Some(&master_key))).unwrap();
+    assert_eq!(names(&preview.added_entries), vec!["forum"]);
+    assert!(preview.removed_entries.is_empty());
+    assert!(preview.conflicts.is_empty());
+
+    let merged =
+        block_on(local.merge(&RustCrypto, &preview, &HashMap::new(), Some(&master_key))).unwrap();
+    assert_eq!(merged.contents.len(), 3);
+    assert_eq!(decrypt_by_name(&merged, &key2, "bank"), b"1234");
+    assert_eq!(decrypt_by_name(&merged, &key2, "forum"), b"9012");
+    assert_eq!(decrypt_by_name(&merged, &key1, "news"), b"3456");
+}
+
+#[test]
+fn rejects_config_of_another_vault() {
+    let key = TestKey::new(1);
+    let local = legacy_vault(&[&key], &[]);
+    let mut other: serde_json::Value = serde_json::to_value(&local).unwrap();
+    other["user"]["user_handle"] = serde_json::to_value(common::user_handle(1)).unwrap();
+    let other: VaultConfig = serde_json::from_value(other).unwrap();
+
+    assert!(matches!(
+        block_on(local.merge_preview(&RustCrypto, &other, None)),
+        Err(Error::InvalidInput(_))
+    ));
+}
@@ -0,0 +1,227 @@
+use futures::executor::block_on;
+use serde::Deserialize;
+
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::recovery::RecoveryPhrase;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::unwrap_keypair;
+use passwordless_passwords_prf::crypto::Padding;
+use passwordless_passwords_prf::crypto::UnlockedKey;
+use passwordless_passwords_prf::data::migration::migrate;
+use passwordless_passwords_prf::data::migration::parse_vault_config;
+use passwordless_passwords_prf::data::migration::CURRENT_VERSION;
+use passwordless_passwords_prf::data::vault::VaultConfig;
+use passwordless_passwords_prf::error::Error;
+
+const VAULT_V2: &str = include_str!("fixtures/vault-v2.json");
+const VAULT_V3: &str = include_str!("fixtures/vault-v3.json");
+const VAULT_V4: &str = include_str!("fixtures/vault-v4.json");
+const VAULT_V5: &str = include_str!("fixtures/vault-v5.json");
+const VAULT_V6: &str = include_str!("fixtures/vault-v6.json");
+const VAULT_V7: &str = include_str!("fixtures/vault-v7.json");
+const VAULT_V8: &str = include_str!("fixtures/vault-v8.json");
+const VAULT_V9: &str = include_str!("fixtures/vault-v9.json");
+const VAULT_V10: &str = include_str!("fixtures/vault-v10.json");
+const VAULT_V11: &str = include_str!("fixtures/vault-v11.json");
+const VAULT_V12: &str = include_str!("fixtures/vault-v12.json");
+
+/// The recovery phrase set up in the version 6 fixture.
+const RECOVERY_PHRASE: &str = "NFR5-IQYB-AJZ5-PGA3-XPZE-FUPA-P5AJ-PGBN";
+
+/// The passphrase set in the version 7 fixture.
+const PASSPHRASE: &str = "correct horse";
+
+/// The fingerprint of the identity of the vault fixtures from version 9 on.
+const FINGERPRINT: &str = "C55E 1F4F C77E 7539 4A56 5C73 2D4F D7EB";
+
+/// The PRF output that unwraps the keypair in the vault fixtures.
+#[derive(Deserialize)]
+struct PrfOutput {
+    #[serde(with = "passwordless_passwords_prf::data::base64")]
+    prf_output: Vec<u8>,
+}
+
+fn unlock(config: &VaultConfig, credential_id: Vec<u8>) -> UnlockedKey<RustCrypto> {
+    let PrfOutput { prf_output } =
+        serde_json::from_str(include_str!("fixtures/webcrypto-v2.json")).unwrap();
+    UnlockedKey {
+        credential_id,
+        private_key: block_on(unwrap_keypair(
+            &RustCrypto,
+            &config.user.keypairs[0],
+            &prf_output,
+        ))
+        .unwrap(),
+    }
+}
+
+fn version(config: &VaultConfig) -> u64 {
+    serde_json::to_value(config).unwrap()["v"].as_u64().unwrap()
+}
+
+#[test]
+fn migrates_v2_to_current() {
+    let config = parse_vault_config(VAULT_V2).unwrap();
+    assert_eq!(version(&config), u64::from(CURRENT_VERSION));
+    assert!(config.master_key.is_none());
+
+    let recipients = config.content_recipients(&config.contents["fixture"]);
+    let unlocked = unlock(&config, recipients[0].credential_id.clone());
+    let content = block_on(decrypt_with(
+        &RustCrypto,
+        &config.contents["fixture"],
+        &config.content_additional_data("fixture"),
+        &unlocked,
+        None,
+    ))
+    .unwrap();
+    assert_eq!(content, b"correct horse battery staple");
+}
+
+#[test]
+fn upgrade_binds_v2_content_to_its_name() {
+    let mut config = parse_vault_config(VAULT_V2).unwrap();
+    assert!(!config.contents["fixture"].is_bound());
+
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    let recipients = config.content_recipients(&config.contents["fixture"]);
+    let unlocked = unlock(&config, recipients[0].credential_id.clone());
+    block_on(config.upgrade_content(&RustCrypto, &unlocked, &master_key)).unwrap();
+
+    let content = &config.contents["fixture"];
+    assert!(content.is_bound());
+    assert!(content
+        .check_additional_data(&config.content_additional_data("other"))
+        .is_err());
+}
+
+#[test]
+fn migrates_v3_to_current() {
+    let config = parse_vault_config(VAULT_V3).unwrap();
+    assert_eq!(version(&config), u64::from(CURRENT_VERSION));
+    assert!(config.master_key.is_some());
+    assert!(!config.has_encrypted_names());
+}
+
+#[test]
+fn migrates_v4_to_current() {
+    let config = parse_vault_config(VAULT_V4).unwrap();
+    assert_eq!(version(&config), u64::from(CURRENT_VERSION));
+    assert!(config.revisions.is_empty());
+}
+
+#[test]
+fn migrates_v5_to_current() {
+    let config = parse_vault_config(VAULT_V5).unwrap();
+    assert_eq!(version(&config), u64::from(CURRENT_VERSION));
+    assert_eq!(config.revisions.entry("fixture").counter, 1);
+    assert!(config.recovery_key.is_none());
+}
+
+#[test]
+fn migrates_v6_to_current() {
+    let config = parse_vault_config(VAULT_V6).unwrap();
+    assert_eq!(version(&config), u64::from(CURRENT_VERSION));
+    assert_eq!(config.revisions.recovery_key.unwrap().counter, 1);
+    assert!(config.passphrase_key.is_none());
+
+    let phrase: RecoveryPhrase = RECOVERY_PHRASE.parse().unwrap();
+    assert!(block_on(config.recover(&RustCrypto, &phrase)).is_ok());
+}
+
+#[test]
+fn migrates_v7_to_current() {
+    let config = parse_vault_config(VAULT_V7).unwrap();
+    assert_eq!(version(&config), u64::from(CURRENT_VERSION));
+    assert_eq!(config.revisions.passphrase_key.unwrap().counter, 1);
+    assert!(block_on(config.unlock_with_passphrase(&RustCrypto, PASSPHRASE)).is_ok());
+}
+
+#[test]
+fn migrates_v8_to_current() {
+    let config = parse_vault_config(VAULT_V8).unwrap();
+    assert_eq!(version(&config), u64::from(CURRENT_VERSION));
+    assert!(config.contacts.is_empty());
+}
+
+#[test]
+fn migrates_v9_to_current() {
+    let config = parse_vault_config(VAULT_V9).unwrap();
+    assert_eq!(version(&config), u64::from(CURRENT_VERSION));
+    let contact = config.contacts.values().next().unwrap();
+    assert_eq!(contact.identity().unwrap().username, "contact");
+    assert!(config.identity_secret.is_none());
+
+    // The master key is the identity secret until it is replaced
+    let master_key = config.master_key.as_ref().unwrap();
+    let unlocked = unlock(&config, master_key.recipients[0].credential_id.clone());
+    let master_key = block_on(config.unwrap_master_key(&RustCrypto, &unlocked)).unwrap();
+    let signed = block_on(config.export_identity(&RustCrypto, &master_key)).unwrap();
+    assert_eq!(signed.identity().unwrap().fingerprint(), FINGERPRINT);
+}
+
+#[test]
+fn migrates_v10_to_current() {
+    let config = parse_vault_config(VAULT_V10).unwrap();
+    assert_eq!(version(&config), u64::from(CURRENT_VERSION));
+    assert_eq!(config.padding, None);
+    assert_eq!(config.revisions.padding, None);
+    assert!(config.identity_secret.is_some());
+}
+
+#[test]
+fn reads_current_version_unchanged() {
+    let config = parse_vault_config(VAULT_V11).unwrap();
+    assert_eq!(
+        serde_json::to_value(&config).unwrap(),
+        serde_json::from_str::<serde_json::Value>(VAULT_V11).unwrap()
+    );
+    assert_eq!(config.padding, Some(Padding::Padme));
+    assert_eq!(config.revisions.padding.unwrap().counter, 1);
+    assert_eq!(config.revisions.entry("fixture").counter, 2);
+    assert_eq!(config.revisions.master_key.unwrap().counter, 1);
+
+    let master_key = config.master_key.as_ref().unwrap();
+    let unlocked = unlock(&config, master_key.recipients[0].credential_id.clone());
+    let master_key = block_on(config.unwrap_master_key(&RustCrypto, &unlocked)).unwrap();
+    let signed = block_on(config.export_identity(&RustCrypto, &master_key)).unwrap();
+    assert_eq!(signed.identity().unwrap().fingerprint(), FINGERPRINT);
+    for key in ["fixture", "threshold"] {
+        let content = block_on(decrypt_with(
+            &RustCrypto,
+            &config.contents[key],
+            &config.content_additional_data(key),
+            &unlocked,
+            Some(&master_key),
+        ))
+        .unwrap();
+        assert_eq!(content, b"correct horse battery staple", "{key}");
+    }
+}
+
+#[test]
+fn migration_is_idempotent() {
+    let once = migrate(serde_json::from_str(VAULT_V2).unwrap()).unwrap();
+    let twice = migrate(once.clone()).unwrap();
+    assert_eq!(once, twice);
+}
+
+#[test]
+fn rejects_newer_version() {
+    let err = parse_vault_config(VAULT_V12).unwrap_err();
+    assert!(matches!(err, Error::NewerVersion(12)));
+    assert!(err.to_string().contains("newer version of the app"));
+}
+
+#[test]
+fn rejects_unknown_or_missing_version() {
+    let mut config: serde_json::Value = serde_json::from_str(VAULT_V2).unwrap();
+    config["v"] = 1.into();
+    assert!(matches!(
+        migrate(config.clone()),
+        Err(Error::MalformedRecord(_))
+    ));
+
+    config.as_object_mut().unwrap().remove("v");
+    assert!(matches!(migrate(config), Err(Error::MalformedRecord(_))));
+}
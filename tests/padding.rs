@@ -0,0 +1,142 @@
+mod common;
+
+use ::base64::engine::general_purpose::STANDARD;
+use ::base64::Engine;
+use futures::executor::block_on;
+
+use common::user_handle;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::encrypt_with;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::EncryptedContent;
+use passwordless_passwords_prf::crypto::EncryptedContentAdditionalData;
+use passwordless_passwords_prf::crypto::Padding;
+use passwordless_passwords_prf::error::Error;
+
+fn additional_data(padding: Option<Padding>) -> EncryptedContentAdditionalData {
+    EncryptedContentAdditionalData::new("password", &user_handle(0)).with_padding(padding)
+}
+
+fn encrypt(key: &TestKey, data: &[u8], padding: Option<Padding>) -> EncryptedContent {
+    block_on(encrypt_with(
+        &RustCrypto,
+        data,
+        &additional_data(padding),
+        std::slice::from_ref(&key.keypair),
+    ))
+    .unwrap()
+}
+
+fn ciphertext_length(content: &EncryptedContent) -> usize {
+    let json = serde_json::to_value(content).unwrap();
+    STANDARD
+        .decode(json["ciphertext"]["$base64"].as_str().unwrap())
+        .unwrap()
+        .len()
+}
+
+#[test]
+fn padded_lengths() {
+    assert_eq!(Padding::Padme.padded_length(1), 1);
+    assert_eq!(Padding::Padme.padded_length(9), 10);
+    assert_eq!(Padding::Padme.padded_length(100), 104);
+    assert_eq!(Padding::Padme.padded_length(1000), 1024);
+    assert_eq!(Padding::PowerOfTwo.padded_length(13), 16);
+    assert_eq!(Padding::PowerOfTwo.padded_length(16), 16);
+
+    for len in 1..5000 {
+        let padded = Padding::Padme.padded_length(len);
+        assert!(padded >= len && padded <= len + len / 8 + 1, "{len}");
+    }
+}
+
+#[test]
+fn pad_roundtrip() {
+    for padding in [Padding::Padme, Padding::PowerOfTwo] {
+        for data in [&b""[..], b"hunter2", b"\x80\x00", &[0; 100]] {
+            let padded = padding.pad(data);
+            assert_eq!(padded.len(), padding.padded_length(data.len() + 1));
+            assert_eq!(Padding::unpad(padded).unwrap(), data);
+        }
+    }
+}
+
+#[test]
+fn rejects_invalid_padding() {
+    assert!(matches!(
+        Padding::unpad(vec![1, 2, 0, 0]),
+        Err(Error::MalformedRecord(_))
+    ));
+    assert!(matches!(
+        Padding::unpad(vec![0, 0]),
+        Err(Error::MalformedRecord(_))
+    ));
+}
+
+#[test]
+fn hides_length_within_bucket() {
+    let key = TestKey::new(1);
+    let first = encrypt(&key, b"hunter2hunter2", Some(Padding::PowerOfTwo));
+    let second = encrypt(&key, b"correct horse", Some(Padding::PowerOfTwo));
+    assert_eq!(ciphertext_length(&first), ciphertext_length(&second));
+
+    let unpadded_first = encrypt(&key, b"hunter2hunter2", None);
+    let unpadded_second = encrypt(&key, b"correct horse", None);
+    assert_ne!(
+        ciphertext_length(&unpadded_first),
+        ciphertext_length(&unpadded_second)
+    );
+}
+
+#[test]
+fn records_padding_in_additional_data() {
+    let key = TestKey::new(1);
+    for padding in [None, Some(Padding::Padme), Some(Padding::PowerOfTwo)] {
+        let content = encrypt(&key, b"hunter2", padding);
+        assert_eq!(content.padding().unwrap(), padding);
+
+        // The padding recorded when encrypting is used, whatever is expected now
+        let decrypted = block_on(decrypt_with(
+            &RustCrypto,
+            &content,
+            &additional_data(None),
+            &key.unlock(),
+            None,
+        ))
+        .unwrap();
+        assert_eq!(decrypted, b"hunter2");
+    }
+}
+
+#[test]
+fn padded_content_has_its_own_version() {
+    let key = TestKey::new(1);
+    let version = |content: &EncryptedContent| {
+        let json = serde_json::to_value(content).unwrap();
+        let additional_data = STANDARD
+            .decode(json["additional_data"]["$base64"].as_str().unwrap())
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&additional_data).unwrap()["v"].clone()
+    };
+    assert_eq!(version(&encrypt(&key, b"hunter2", None)), 4);
+    assert_eq!(version(&encrypt(&key, b"hunter2", Some(Padding::Padme))), 5);
+
+    // Padding recorded under an older version, which older builds would not strip
+    for (v, padding) in [(4, Some("padme")), (5, None)] {
+        let mut json = serde_json::to_value(encrypt(&key, b"hunter2", None)).unwrap();
+        let mut additional_data: serde_json::Value = serde_json::from_slice(
+            &STANDARD
+                .decode(json["additional_data"]["$base64"].as_str().unwrap())
+                .unwrap(),
+        )
+        .unwrap();
+        additional_data["v"] = v.into();
+        additional_data["padding"] = padding.into();
+        json["additional_data"]["$base64"] = STANDARD
+            .encode(serde_json::to_vec(&additional_data).unwrap())
+            .into();
+        let content: EncryptedContent = serde_json::from_value(json).unwrap();
+        assert!(matches!(content.format(), Err(Error::MalformedRecord(_))));
+    }
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use futures::executor::block_on;
+
+use common::legacy_vault;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::passphrase::create_passphrase_key;
+use passwordless_passwords_prf::crypto::passphrase::unlock_passphrase_key;
+use passwordless_passwords_prf::crypto::passphrase::Argon2Params;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::MasterKey;
+use passwordless_passwords_prf::data::vault::VaultConfig;
+use passwordless_passwords_prf::error::Error;
+
+/// Far too cheap for real use, but keeps the tests fast.
+const PARAMS: Argon2Params = Argon2Params {
+    memory_kib: 64,
+    iterations: 1,
+    parallelism: 1,
+};
+
+/// A vault with a master key and one entry, and the key it is encrypted to.
+fn vault() -> (VaultConfig, TestKey, MasterKey) {
+    let key = TestKey::new(1);
+    let mut config = legacy_vault(&[&key], &[]);
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    block_on(config.push_content(&RustCrypto, "bank".into(), b"1234".to_vec(), &master_key))
+        .unwrap();
+    (config, key, master_key)
+}
+
+#[test]
+fn unlocks_and_decrypts_with_passphrase() {
+    let (mut config, _key, master_key) = vault();
+    block_on(config.set_passphrase(&RustCrypto, Some("hunter2"), PARAMS, &master_key)).unwrap();
+
+    let (unlocked, master_key) =
+        block_on(config.unlock_with_passphrase(&RustCrypto, "hunter2")).unwrap();
+    let content = block_on(decrypt_with(
+        &RustCrypto,
+        &config.contents["bank"],
+        &config.content_additional_data("bank"),
+        &unlocked,
+        Some(&master_key),
+    ))
+    .unwrap();
+    assert_eq!(content, b"1234");
+}
+
+#[test]
+fn rejects_wrong_passphrase() {
+    let (mut config, _key, master_key) = vault();
+    block_on(config.set_passphrase(&RustCrypto, Some("hunter2"), PARAMS, &master_key)).unwrap();
+    assert!(matches!(
+        block_on(config.unlock_with_passphrase(&RustCrypto, "hunter3")),
+        Err(Error::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn rejects_empty_passphrase() {
+    assert!(matches!(
+        block_on(create_passphrase_key(&RustCrypto, "", PARAMS)),
+        Err(Error::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn params_are_stored_and_authenticated() {
+    let key = block_on(create_passphrase_key(&RustCrypto, "hunter2", PARAMS)).unwrap();
+    let mut json = serde_json::to_value(&key).unwrap();
+    assert_eq!(json["params"]["memory_kib"], 64);
+    assert_eq!(json["params"]["iterations"], 1);
+
+    json["params"]["iterations"] = 2.into();
+    let tampered = serde_json::from_value(json).unwrap();
+    assert!(block_on(unlock_passphrase_key(&RustCrypto, &tampered, "hunter2")).is_err());
+    assert!(block_on(unlock_passphrase_key(&RustCrypto, &key, "hunter2")).is_ok());
+}
+
+#[test]
+fn rejects_excessive_params() {
+    let key = block_on(create_passphrase_key(&RustCrypto, "hunter2", PARAMS)).unwrap();
+    for (name, value) in [
+        ("memory_kib", Argon2Params::MAX.memory_kib + 1),
+        ("iterations", Argon2Params::MAX.iterations + 1),
+        ("parallelism", Argon2Params::MAX.parallelism + 1),
+    ] {
+        let mut json = serde_json::to_value(&key).unwrap();
+        json["params"][name] = value.into();
+        let tampered = serde_json::from_value(json).unwrap();
+        assert!(
+            matches!(
+                block_on(unlock_passphrase_key(&RustCrypto, &tampered, "hunter2")),
+                Err(Error::InvalidInput(msg)) if msg.contains("maximum")
+            ),
+            "{name}"
+        );
+    }
+
+    let params = Argon2Params {
+        iterations: Argon2Params::MAX.iterations + 1,
+        ..PARAMS
+    };
+    assert!(matches!(
+        block_on(create_passphrase_key(&RustCrypto, "hunter2", params)),
+        Err(Error::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn replace_and_remove_passphrase() {
+    let (mut config, _key, master_key) = vault();
+    block_on(config.set_passphrase(&RustCrypto, Some("old"), PARAMS, &master_key)).unwrap();
+    block_on(config.set_passphrase(&RustCrypto, Some("new"), PARAMS, &master_key)).unwrap();
+    assert_eq!(config.master_key.as_ref().unwrap().recipients.len(), 2);
+    assert!(block_on(config.unlock_with_passphrase(&RustCrypto, "old")).is_err());
+    assert!(block_on(config.unlock_with_passphrase(&RustCrypto, "new")).is_ok());
+
+    block_on(config.set_passphrase(&RustCrypto, None, PARAMS, &master_key)).unwrap();
+    assert_eq!(config.passphrase_key, None);
+    assert_eq!(config.master_key.as_ref().unwrap().recipients.len(), 1);
+}
+
+#[test]
+fn merge_keeps_passphrase_recipient() {
+    let (mut config, _key, master_key) = vault();
+    let other = config.clone();
+    block_on(config.set_passphrase(&RustCrypto, Some("hunter2"), PARAMS, &master_key)).unwrap();
+
+    let merged = block_on(other.sync_merge(&RustCrypto, &config, None)).unwrap();
+    assert_eq!(merged.passphrase_key, config.passphrase_key);
+    assert!(block_on(merged.unlock_with_passphrase(&RustCrypto, "hunter2")).is_ok());
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use futures::executor::block_on;
+
+use common::legacy_vault;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::recovery::derive_recovery_key;
+use passwordless_passwords_prf::crypto::recovery::RecoveryPhrase;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::MasterKey;
+use passwordless_passwords_prf::data::vault::VaultConfig;
+use passwordless_passwords_prf::data::CredentialId;
+use passwordless_passwords_prf::error::Error;
+
+/// A vault with a master key and one entry, and the key it is encrypted to.
+fn vault() -> (VaultConfig, TestKey, MasterKey) {
+    let key = TestKey::new(1);
+    let mut config = legacy_vault(&[&key], &[]);
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    block_on(config.push_content(&RustCrypto, "bank".into(), b"1234".to_vec(), &master_key))
+        .unwrap();
+    (config, key, master_key)
+}
+
+#[test]
+fn phrase_roundtrip() {
+    let phrase = RecoveryPhrase::generate(&RustCrypto).unwrap();
+    let displayed = phrase.to_string();
+    assert_eq!(displayed.len(), 39);
+    assert_eq!(displayed.matches('-').count(), 7);
+
+    let typed = displayed.to_lowercase().replace('-', " ");
+    assert_eq!(
+        typed.parse::<RecoveryPhrase>().unwrap().to_string(),
+        displayed
+    );
+}
+
+#[test]
+fn rejects_malformed_phrase() {
+    for phrase in ["", "ABCD-EFGH", "ABCD-EFGH-ABCD-EFGH-ABCD-EFGH-ABCD-EFG1"] {
+        assert!(matches!(
+            phrase.parse::<RecoveryPhrase>(),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+}
+
+#[test]
+fn derives_same_key_from_same_phrase() {
+    let phrase = RecoveryPhrase::generate(&RustCrypto).unwrap();
+    let again: RecoveryPhrase = phrase.to_string().parse().unwrap();
+    let other = RecoveryPhrase::generate(&RustCrypto).unwrap();
+
+    let (key, _) = block_on(derive_recovery_key(&RustCrypto, &phrase)).unwrap();
+    let (key_again, _) = block_on(derive_recovery_key(&RustCrypto, &again)).unwrap();
+    let (other_key, _) = block_on(derive_recovery_key(&RustCrypto, &other)).unwrap();
+    assert_eq!(key, key_again);
+    assert_ne!(key, other_key);
+}
+
+#[test]
+fn recovers_after_all_credentials_are_deleted() {
+    let (mut config, key, master_key) = vault();
+    let phrase = block_on(config.set_up_recovery(&RustCrypto, &master_key)).unwrap();
+    config
+        .delete_credential(&CredentialId::from(key.credential_id.clone()))
+        .unwrap();
+
+    let (unlocked, master_key) = block_on(config.recover(&RustCrypto, &phrase)).unwrap();
+    let content = block_on(decrypt_with(
+        &RustCrypto,
+        &config.contents["bank"],
+        &config.content_additional_data("bank"),
+        &unlocked,
+        Some(&master_key),
+    ))
+    .unwrap();
+    assert_eq!(content, b"1234");
+}
+
+#[test]
+fn replaced_phrase_no_longer_recovers() {
+    let (mut config, _key, master_key) = vault();
+    let old_phrase = block_on(config.set_up_recovery(&RustCrypto, &master_key)).unwrap();
+    let new_phrase = block_on(config.set_up_recovery(&RustCrypto, &master_key)).unwrap();
+
+    assert_eq!(config.master_key.as_ref().unwrap().recipients.len(), 2);
+    assert!(matches!(
+        block_on(config.recover(&RustCrypto, &old_phrase)),
+        Err(Error::InvalidInput(_))
+    ));
+    assert!(block_on(config.recover(&RustCrypto, &new_phrase)).is_ok());
+}
+
+#[test]
+fn requires_entries_to_use_master_key() {
+    let key = TestKey::new(1);
+    let mut config = legacy_vault(&[&key], &[("bank", b"1234")]);
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    assert!(matches!(
+        block_on(config.set_up_recovery(&RustCrypto, &master_key)),
+        Err(Error::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn merge_keeps_recovery_recipient() {
+    let (mut config, _key, master_key) = vault();
+    let other = config.clone();
+    let phrase = block_on(config.set_up_recovery(&RustCrypto, &master_key)).unwrap();
+
+    let merged = block_on(other.sync_merge(&RustCrypto, &config, None)).unwrap();
+    assert_eq!(merged.recovery_key, config.recovery_key);
+    assert!(block_on(merged.recover(&RustCrypto, &phrase)).is_ok());
+}
+
+#[test]
+fn merge_keeps_newer_recovery_key() {
+    let (mut config, _key, master_key) = vault();
+    let old_phrase = block_on(config.set_up_recovery(&RustCrypto, &master_key)).unwrap();
+    let other = config.clone();
+    let new_phrase = block_on(config.set_up_recovery(&RustCrypto, &master_key)).unwrap();
+
+    for merged in [
+        block_on(config.sync_merge(&RustCrypto, &other, None)).unwrap(),
+        block_on(other.sync_merge(&RustCrypto, &config, None)).unwrap(),
+    ] {
+        assert_eq!(merged.recovery_key, config.recovery_key);
+        assert_eq!(merged.master_key.as_ref().unwrap().recipients.len(), 2);
+        assert!(block_on(merged.recover(&RustCrypto, &new_phrase)).is_ok());
+        assert!(block_on(merged.recover(&RustCrypto, &old_phrase)).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use std::rc::Rc;
+
+use futures::executor::block_on;
+
+use common::legacy_vault;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::encrypt_with;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::ContentKey;
+use passwordless_passwords_prf::data::vault::VaultConfig;
+use passwordless_passwords_prf::data::CredentialId;
+use passwordless_passwords_prf::error::Error;
+
+fn cred_id(key: &TestKey) -> CredentialId {
+    CredentialId::from(key.credential_id.clone())
+}
+
+fn decrypt_entry(config: &VaultConfig, key: &TestKey, name: &str) -> Vec<u8> {
+    let unlocked = key.unlock();
+    let master_key = block_on(config.unwrap_master_key(&RustCrypto, &unlocked)).unwrap();
+    block_on(decrypt_with(
+        &RustCrypto,
+        &config.contents[name],
+        &config.content_additional_data(name),
+        &unlocked,
+        Some(&master_key),
+    ))
+    .unwrap()
+}
+
+#[test]
+fn enrolls_new_key_into_whole_vault() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let mut config = legacy_vault(&[&key1], &[("bank", b"1234"), ("email", b"5678")]);
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+
+    // Registered without wrapping the master key to it, as in format version 2
+    Rc::make_mut(&mut Rc::make_mut(&mut config.user).keypairs).push(Rc::clone(&key2.keypair));
+
+    let mut progress: Vec<(String, usize, usize, bool)> = Vec::new();
+    let report = block_on(config.reencrypt_all(
+        &RustCrypto,
+        &key1.unlock(),
+        &master_key,
+        &[cred_id(&key2)],
+        |p| progress.push((p.name.to_string(), p.done, p.total, p.result.is_ok())),
+    ))
+    .unwrap();
+
+    assert_eq!(report.reencrypted, vec!["bank", "email"]);
+    assert!(report.failed.is_empty());
+    assert_eq!(
+        progress,
+        vec![
+            ("bank".to_string(), 1, 2, true),
+            ("email".to_string(), 2, 2, true),
+        ]
+    );
+    assert!(config
+        .contents
+        .values()
+        .all(|content| matches!(content.key, ContentKey::MasterKey { .. })));
+    assert_eq!(decrypt_entry(&config, &key2, "bank"), b"1234");
+    assert_eq!(decrypt_entry(&config, &key2, "email"), b"5678");
+}
+
+#[test]
+fn enrolls_only_confirmed_keys() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let key3 = TestKey::new(3);
+    let mut config = legacy_vault(&[&key1], &[("bank", b"1234")]);
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+
+    // Added from elsewhere, e.g. by a synced config
+    Rc::make_mut(&mut Rc::make_mut(&mut config.user).keypairs).push(Rc::clone(&key2.keypair));
+    assert!(config.unenrolled_credentials().unwrap() == vec![cred_id(&key2)]);
+
+    assert!(matches!(
+        block_on(config.reencrypt_all(
+            &RustCrypto,
+            &key1.unlock(),
+            &master_key,
+            &[cred_id(&key3)],
+            |_| {}
+        )),
+        Err(Error::InvalidInput(_))
+    ));
+
+    let report =
+        block_on(config.reencrypt_all(&RustCrypto, &key1.unlock(), &master_key, &[], |_| {}))
+            .unwrap();
+    assert_eq!(report.reencrypted, vec!["bank"]);
+    assert!(config.unenrolled_credentials().unwrap() == vec![cred_id(&key2)]);
+    assert!(block_on(config.unwrap_master_key(&RustCrypto, &key2.unlock())).is_err());
+}
+
+#[test]
+fn reports_failed_entries_and_continues() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let mut config = legacy_vault(&[&key1, &key2], &[("bank", b"1234"), ("email", b"5678")]);
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+
+    // Readable only by key2
+    let other = block_on(encrypt_with(
+        &RustCrypto,
+        b"secret",
+        &config.content_additional_data("other"),
+        &[Rc::clone(&key2.keypair)],
+    ))
+    .unwrap();
+    config.contents.insert("other".to_string(), Rc::new(other));
+
+    // Moved without re-encryption
+    let moved = Rc::clone(&config.contents["email"]);
+    config.contents.insert("moved".to_string(), moved);
+
+    let unchanged = config.contents.clone();
+    let mut num_progress = 0;
+    let report =
+        block_on(
+            config.reencrypt_all(&RustCrypto, &key1.unlock(), &master_key, &[], |_| {
+                num_progress += 1
+            }),
+        )
+        .unwrap();
+
+    assert_eq!(num_progress, 4);
+    assert_eq!(report.reencrypted, vec!["bank", "email"]);
+    assert_eq!(
+        report
+            .failed
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["moved", "other"]
+    );
+    assert_eq!(config.contents["moved"], unchanged["moved"]);
+    assert_eq!(config.contents["other"], unchanged["other"]);
+    assert_eq!(decrypt_entry(&config, &key2, "bank"), b"1234");
+}
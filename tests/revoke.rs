@@ -0,0 +1,321 @@
+mod common;
+
+use futures::executor::block_on;
+
+use common::legacy_vault;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::decrypt_with;
+use passwordless_passwords_prf::crypto::passphrase::Argon2Params;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::MasterKey;
+use passwordless_passwords_prf::crypto::UnlockedKey;
+use passwordless_passwords_prf::data::vault::VaultConfig;
+use passwordless_passwords_prf::data::CredentialId;
+use passwordless_passwords_prf::error::Error;
+
+const PARAMS: Argon2Params = Argon2Params {
+    memory_kib: 64,
+    iterations: 1,
+    parallelism: 1,
+};
+
+fn cred_id(key: &TestKey) -> CredentialId {
+    CredentialId::from(key.credential_id.clone())
+}
+
+fn decrypt(
+    config: &VaultConfig,
+    unlocked: &UnlockedKey<RustCrypto>,
+    master_key: &MasterKey,
+    key: &str,
+) -> Result<Vec<u8>, Error> {
+    block_on(decrypt_with(
+        &RustCrypto,
+        &config.contents[key],
+        &config.content_additional_data(key),
+        unlocked,
+        Some(master_key),
+    ))
+}
+
+/// A vault with two credentials and two entries under the master key.
+fn vault(key1: &TestKey, key2: &TestKey) -> (VaultConfig, MasterKey) {
+    let mut config = legacy_vault(&[key1, key2], &[]);
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    for (name, content) in [("bank", b"1234"), ("email", b"5678")] {
+        block_on(config.push_content(&RustCrypto, name.into(), content.to_vec(), &master_key))
+            .unwrap();
+    }
+    (config, master_key)
+}
+
+#[test]
+fn rotates_master_key_and_entries() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let (mut config, master_key) = vault(&key1, &key2);
+    let phrase = block_on(config.set_up_recovery(&RustCrypto, &master_key)).unwrap();
+    block_on(config.set_passphrase(&RustCrypto, Some("hunter2"), PARAMS, &master_key)).unwrap();
+    let old = config.clone();
+
+    let (new_master_key, report) = block_on(config.revoke_credential(
+        &RustCrypto,
+        &cred_id(&key2),
+        &key1.unlock(),
+        &master_key,
+    ))
+    .unwrap();
+    let new_master_key = new_master_key.unwrap();
+    assert_eq!(report.rotated, vec!["bank", "email"]);
+    assert!(report.failed.is_empty());
+
+    assert_eq!(config.user.keypairs.len(), 1);
+    assert_eq!(config.master_key.as_ref().unwrap().recipients.len(), 3);
+    let unlocked = key1.unlock();
+    assert_eq!(
+        block_on(config.unwrap_master_key(&RustCrypto, &unlocked))
+            .and_then(|mk| decrypt(&config, &unlocked, &mk, "bank"))
+            .unwrap(),
+        b"1234"
+    );
+    assert_eq!(
+        decrypt(&config, &unlocked, &new_master_key, "email").unwrap(),
+        b"5678"
+    );
+
+    // The revoked key still unwraps the master key of the old copy, which
+    // no longer decrypts anything
+    let old_master_key = block_on(old.unwrap_master_key(&RustCrypto, &key2.unlock())).unwrap();
+    assert!(decrypt(&config, &key2.unlock(), &old_master_key, "bank").is_err());
+    assert!(block_on(config.unwrap_master_key(&RustCrypto, &key2.unlock())).is_err());
+
+    // The recovery phrase and passphrase are kept
+    let (unlocked, recovered) = block_on(config.recover(&RustCrypto, &phrase)).unwrap();
+    assert_eq!(
+        decrypt(&config, &unlocked, &recovered, "bank").unwrap(),
+        b"1234"
+    );
+    let (unlocked, unlocked_key) =
+        block_on(config.unlock_with_passphrase(&RustCrypto, "hunter2")).unwrap();
+    assert_eq!(
+        decrypt(&config, &unlocked, &unlocked_key, "bank").unwrap(),
+        b"1234"
+    );
+}
+
+#[test]
+fn rejects_unlocked_and_unknown_credential() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let (mut config, master_key) = vault(&key1, &key2);
+    let before = config.clone();
+
+    assert!(matches!(
+        block_on(config.revoke_credential(
+            &RustCrypto,
+            &cred_id(&key1),
+            &key1.unlock(),
+            &master_key,
+        )),
+        Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(
+        block_on(config.revoke_credential(
+            &RustCrypto,
+            &cred_id(&TestKey::new(3)),
+            &key1.unlock(),
+            &master_key,
+        )),
+        Err(Error::NoMatchingRecipient)
+    ));
+    assert_eq!(config, before);
+}
+
+#[test]
+fn rotates_entries_with_own_recipients() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let key3 = TestKey::new(3);
+    let mut config = legacy_vault(&[&key1, &key2, &key3], &[("bank", b"1234")]);
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    let old = config.contents["bank"].clone();
+
+    let (_, report) = block_on(config.revoke_credential(
+        &RustCrypto,
+        &cred_id(&key2),
+        &key1.unlock(),
+        &master_key,
+    ))
+    .unwrap();
+    assert_eq!(report.rotated, vec!["bank"]);
+
+    let recipients: Vec<&Vec<u8>> = config
+        .content_recipients(&config.contents["bank"])
+        .into_iter()
+        .map(|wck| &wck.credential_id)
+        .collect();
+    assert_eq!(recipients, vec![&key1.credential_id, &key3.credential_id]);
+    let ciphertext = |content| serde_json::to_value(content).unwrap()["ciphertext"].clone();
+    assert_ne!(ciphertext(&config.contents["bank"]), ciphertext(&old));
+    assert_eq!(
+        decrypt(&config, &key3.unlock(), &master_key, "bank").unwrap(),
+        b"1234"
+    );
+}
+
+#[test]
+fn reports_entries_that_need_several_keys() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let key3 = TestKey::new(3);
+    let mut config = legacy_vault(&[&key1, &key2, &key3], &[]);
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    let all = [cred_id(&key1), cred_id(&key2), cred_id(&key3)];
+    for (name, threshold) in [("any", 1), ("two", 2)] {
+        block_on(config.push_threshold_content(
+            &RustCrypto,
+            name.into(),
+            b"1234".to_vec(),
+            threshold,
+            &all,
+            &master_key,
+        ))
+        .unwrap();
+    }
+
+    let (_, report) = block_on(config.revoke_credential(
+        &RustCrypto,
+        &cred_id(&key2),
+        &key1.unlock(),
+        &master_key,
+    ))
+    .unwrap();
+    assert_eq!(report.rotated, vec!["any"]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, "two");
+
+    for name in ["any", "two"] {
+        assert_eq!(
+            config.content_recipients(&config.contents[name]).len(),
+            2,
+            "{name}"
+        );
+    }
+    assert_eq!(
+        decrypt(&config, &key3.unlock(), &master_key, "any").unwrap(),
+        b"1234"
+    );
+}
+
+#[test]
+fn refuses_to_leave_too_few_shares() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let key3 = TestKey::new(3);
+    let mut config = legacy_vault(&[&key1, &key2, &key3], &[]);
+    let master_key = block_on(config.create_master_key(&RustCrypto)).unwrap();
+    for (name, threshold, keys) in [
+        ("both", 2, vec![cred_id(&key1), cred_id(&key2)]),
+        (
+            "two",
+            2,
+            vec![cred_id(&key1), cred_id(&key2), cred_id(&key3)],
+        ),
+    ] {
+        block_on(config.push_threshold_content(
+            &RustCrypto,
+            name.into(),
+            b"1234".to_vec(),
+            threshold,
+            &keys,
+            &master_key,
+        ))
+        .unwrap();
+    }
+    let before = config.clone();
+
+    // Both keys of the 2-of-2 entry are needed
+    assert!(matches!(
+        config.delete_credential(&cred_id(&key2)),
+        Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(
+        block_on(config.revoke_credential(
+            &RustCrypto,
+            &cred_id(&key2),
+            &key3.unlock(),
+            &master_key,
+        )),
+        Err(Error::InvalidInput(_))
+    ));
+    assert_eq!(config, before);
+
+    // Of the 2-of-3 entry, one key can go but not a second one
+    config.delete_credential(&cred_id(&key3)).unwrap();
+    assert_eq!(config.content_recipients(&config.contents["two"]).len(), 2);
+    assert!(matches!(
+        config.delete_credential(&cred_id(&key1)),
+        Err(Error::InvalidInput(_))
+    ));
+    assert_eq!(config.content_recipients(&config.contents["two"]).len(), 2);
+}
+
+#[test]
+fn keeps_encrypted_names() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let (mut config, master_key) = vault(&key1, &key2);
+    block_on(config.encrypt_names(&RustCrypto, &key1.unlock(), &master_key)).unwrap();
+
+    let (new_master_key, report) = block_on(config.revoke_credential(
+        &RustCrypto,
+        &cred_id(&key2),
+        &key1.unlock(),
+        &master_key,
+    ))
+    .unwrap();
+    assert_eq!(report.rotated.len(), 2);
+
+    let names = block_on(config.decrypt_names(&RustCrypto, &new_master_key.unwrap())).unwrap();
+    let mut names: Vec<&str> = names.iter().map(|(_, name)| name).collect();
+    names.sort();
+    assert_eq!(names, vec!["bank", "email"]);
+}
+
+#[test]
+fn merge_keeps_replaced_master_key() {
+    let key1 = TestKey::new(1);
+    let key2 = TestKey::new(2);
+    let (mut config, master_key) = vault(&key1, &key2);
+
+    // Another device adds an entry before it sees the revocation
+    let mut stale = config.clone();
+    block_on(stale.push_content(&RustCrypto, "news".into(), b"3456".to_vec(), &master_key))
+        .unwrap();
+
+    let (new_master_key, _) = block_on(config.revoke_credential(
+        &RustCrypto,
+        &cred_id(&key2),
+        &key1.unlock(),
+        &master_key,
+    ))
+    .unwrap();
+    let new_master_key = new_master_key.unwrap();
+
+    let merged = block_on(config.sync_merge(&RustCrypto, &stale, None)).unwrap();
+    assert_eq!(
+        merged,
+        block_on(stale.sync_merge(&RustCrypto, &config, None)).unwrap()
+    );
+
+    // The new entry is under the old master key, so it cannot be kept
+    let mut keys: Vec<&String> = merged.contents.keys().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["bank", "email"]);
+    assert_eq!(merged.user.keypairs.len(), 1);
+    assert_eq!(merged.master_key, config.master_key);
+    assert_eq!(
+        decrypt(&merged, &key1.unlock(), &new_master_key, "bank").unwrap(),
+        b"1234"
+    );
+}
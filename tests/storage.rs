@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use futures::executor::block_on;
+
+use passwordless_passwords_prf::error::Error;
+use passwordless_passwords_prf::storage::migrate;
+use passwordless_passwords_prf::storage::BackendKind;
+use passwordless_passwords_prf::storage::MemoryStorage;
+use passwordless_passwords_prf::storage::StorageBackend;
+use passwordless_passwords_prf::storage::Stored;
+
+fn value(storage: &MemoryStorage, key: &str) -> Option<String> {
+    block_on(storage.get(key))
+        .unwrap()
+        .map(|stored| stored.value)
+}
+
+#[test]
+fn memory_storage_gets_puts_and_deletes() {
+    let storage = MemoryStorage::default();
+    assert_eq!(block_on(storage.get("vault")).unwrap(), None);
+
+    assert_eq!(block_on(storage.put("vault", "{}", 0)).unwrap(), 1);
+    assert_eq!(
+        block_on(storage.get("vault")).unwrap(),
+        Some(Stored {
+            value: "{}".to_string(),
+            generation: 1,
+        })
+    );
+
+    // Clones share the same values
+    let clone = storage.clone();
+    block_on(clone.delete("vault")).unwrap();
+    assert_eq!(value(&storage, "vault"), None);
+}
+
+#[test]
+fn rejects_write_based_on_old_generation() {
+    let storage = MemoryStorage::default();
+    let tab1 = storage.clone();
+    let tab2 = storage.clone();
+    block_on(storage.put("vault", "v1", 0)).unwrap();
+
+    assert_eq!(block_on(tab1.put("vault", "tab 1", 1)).unwrap(), 2);
+    assert!(matches!(
+        block_on(tab2.put("vault", "tab 2", 1)),
+        Err(Error::Conflict)
+    ));
+    assert!(matches!(
+        block_on(tab2.put("vault", "tab 2", 0)),
+        Err(Error::Conflict)
+    ));
+    assert_eq!(value(&storage, "vault").as_deref(), Some("tab 1"));
+
+    assert_eq!(block_on(tab2.put("vault", "tab 2", 2)).unwrap(), 3);
+}
+
+#[test]
+fn memory_storage_notifies_until_unsubscribed() {
+    let storage = MemoryStorage::default();
+    let changed = Rc::new(RefCell::new(Vec::<String>::new()));
+    let subscription = {
+        let changed = Rc::clone(&changed);
+        storage
+            .subscribe(Rc::new(move |key: &str| {
+                changed.borrow_mut().push(key.to_string())
+            }))
+            .unwrap()
+    };
+
+    block_on(storage.put("vault", "{}", 0)).unwrap();
+    block_on(storage.put("vault", "{}", 0)).unwrap_err();
+    block_on(storage.delete("vault")).unwrap();
+    block_on(storage.delete("vault")).unwrap();
+    assert_eq!(*changed.borrow(), vec!["vault", "vault"]);
+
+    drop(subscription);
+    block_on(storage.put("vault", "{}", 0)).unwrap();
+    assert_eq!(changed.borrow().len(), 2);
+}
+
+#[test]
+fn migrate_moves_value() {
+    let from = MemoryStorage::default();
+    let to = MemoryStorage::default();
+    block_on(from.put("vault", "old", 0)).unwrap();
+
+    assert!(block_on(migrate(&from, &to, "vault")).unwrap());
+    assert_eq!(value(&to, "vault").as_deref(), Some("old"));
+    assert_eq!(value(&from, "vault"), None);
+
+    assert!(!block_on(migrate(&from, &to, "vault")).unwrap());
+}
+
+#[test]
+fn migrate_keeps_existing_value() {
+    let from = MemoryStorage::default();
+    let to = MemoryStorage::default();
+    block_on(from.put("vault", "old", 0)).unwrap();
+    block_on(to.put("vault", "new", 0)).unwrap();
+
+    assert!(!block_on(migrate(&from, &to, "vault")).unwrap());
+    assert_eq!(value(&to, "vault").as_deref(), Some("new"));
+    assert_eq!(value(&from, "vault").as_deref(), Some("old"));
+}
+
+#[test]
+fn parses_backend_kind() {
+    assert_eq!(
+        "indexeddb".parse::<BackendKind>().unwrap(),
+        BackendKind::IndexedDb
+    );
+    assert_eq!(
+        "localstorage".parse::<BackendKind>().unwrap(),
+        BackendKind::LocalStorage
+    );
+    assert_eq!(
+        "memory".parse::<BackendKind>().unwrap(),
+        BackendKind::Memory
+    );
+    assert!("cookies".parse::<BackendKind>().is_err());
+}
@@ -0,0 +1,286 @@
+mod common;
+
+use futures::executor::block_on;
+use proptest::prelude::*;
+
+use common::legacy_vault;
+use common::TestKey;
+use passwordless_passwords_prf::crypto::passphrase::Argon2Params;
+use passwordless_passwords_prf::crypto::rustcrypto::RustCrypto;
+use passwordless_passwords_prf::crypto::MasterKey;
+use passwordless_passwords_prf::crypto::Padding;
+use passwordless_passwords_prf::data::vault::VaultConfig;
+use passwordless_passwords_prf::data::CredentialId;
+use passwordless_passwords_prf::error::Error;
+
+const ENTRIES: usize = 3;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Put(usize, u8),
+    Delete(usize),
+    AddCredential(usize),
+    DeleteCredential(usize),
+    RenameCredential(usize, u8),
+}
+
+/// Operations on the entries with index in `entries`, and on the
+/// credentials other than the first.
+fn ops(entries: std::ops::Range<usize>) -> impl Strategy<Value = Vec<Op>> {
+    let op = prop_oneof![
+        (entries.clone(), any::<u8>()).prop_map(|(i, v)| Op::Put(i, v)),
+        entries.prop_map(Op::Delete),
+        (1..3usize).prop_map(Op::AddCredential),
+        (1..3usize).prop_map(Op::DeleteCredential),
+        (0..3usize, any::<u8>()).prop_map(|(i, v)| Op::RenameCredential(i, v)),
+    ];
+    prop::collection::vec(op, 0..6)
+}
+
+struct Fixture {
+    keys: Vec<TestKey>,
+    base: VaultConfig,
+    master_key: MasterKey,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let keys: Vec<TestKey> = (1..=3).map(TestKey::new).collect();
+        let mut base = legacy_vault(&[&keys[0]], &[]);
+        let master_key = block_on(base.create_master_key(&RustCrypto)).unwrap();
+        for i in 0..ENTRIES {
+            block_on(base.push_content(&RustCrypto, entry(i), vec![0], &master_key)).unwrap();
+        }
+        Self {
+            keys,
+            base,
+            master_key,
+        }
+    }
+
+    fn apply(&self, config: &VaultConfig, ops: &[Op]) -> VaultConfig {
+        let mut config = config.clone();
+        for op in ops {
+            match op {
+                Op::Put(i, v) => {
+                    block_on(config.push_content(
+                        &RustCrypto,
+                        entry(*i),
+                        vec![*v],
+                        &self.master_key,
+                    ))
+                    .unwrap();
+                }
+                Op::Delete(i) => {
+                    config.delete_content(&entry(*i)).unwrap();
+                }
+                Op::AddCredential(i) => {
+                    let key = &self.keys[*i];
+                    let present = config.user.keypairs.iter().any(|wkp| {
+                        wkp.additional_data().unwrap().credential_id().b64url()
+                            == self.credential_id(*i).b64url()
+                    });
+                    if !present {
+                        block_on(config.add_credential(
+                            &RustCrypto,
+                            (*key.keypair).clone(),
+                            &self.master_key,
+                        ))
+                        .unwrap();
+                    }
+                }
+                Op::DeleteCredential(i) => {
+                    config.delete_credential(&self.credential_id(*i)).unwrap();
+                }
+                Op::RenameCredential(i, v) => {
+                    // Fails if the credential is not registered in this copy
+                    match config.rename_credential(&self.credential_id(*i), format!("key {v}")) {
+                        Ok(_) | Err(Error::InvalidInput(_)) => {}
+                        Err(err) => panic!("{err}"),
+                    }
+                }
+            }
+        }
+        config
+    }
+
+    fn credential_id(&self, i: usize) -> CredentialId {
+        self.keys[i].credential_id.clone().into()
+    }
+}
+
+fn entry(i: usize) -> String {
+    format!("entry{i}")
+}
+
+fn merge(a: &VaultConfig, b: &VaultConfig) -> VaultConfig {
+    block_on(a.sync_merge(&RustCrypto, b, None)).unwrap()
+}
+
+/// The config as JSON, ignoring the order of keypairs and recipients.
+fn normalized(config: &VaultConfig) -> serde_json::Value {
+    fn sort(array: &mut serde_json::Value) {
+        if let Some(array) = array.as_array_mut() {
+            array.sort_by_key(|item| item.to_string());
+        }
+    }
+    let mut value = serde_json::to_value(config).unwrap();
+    sort(&mut value["user"]["keypairs"]);
+    sort(&mut value["master_key"]["recipients"]);
+    value
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn merge_is_commutative(ops_a in ops(0..ENTRIES), ops_b in ops(0..ENTRIES)) {
+        let fixture = Fixture::new();
+        let a = fixture.apply(&fixture.base, &ops_a);
+        let b = fixture.apply(&fixture.base, &ops_b);
+        prop_assert_eq!(normalized(&merge(&a, &b)), normalized(&merge(&b, &a)));
+    }
+
+    #[test]
+    fn merge_is_associative(
+        ops_a in ops(0..ENTRIES),
+        ops_b in ops(0..ENTRIES),
+        ops_c in ops(0..ENTRIES),
+    ) {
+        let fixture = Fixture::new();
+        let a = fixture.apply(&fixture.base, &ops_a);
+        let b = fixture.apply(&fixture.base, &ops_b);
+        let c = fixture.apply(&fixture.base, &ops_c);
+        prop_assert_eq!(
+            normalized(&merge(&merge(&a, &b), &c)),
+            normalized(&merge(&a, &merge(&b, &c)))
+        );
+    }
+
+    #[test]
+    fn merge_is_idempotent(ops_a in ops(0..ENTRIES), ops_b in ops(0..ENTRIES)) {
+        let fixture = Fixture::new();
+        let a = fixture.apply(&fixture.base, &ops_a);
+        let b = fixture.apply(&fixture.base, &ops_b);
+        prop_assert_eq!(normalized(&merge(&a, &a)), normalized(&a));
+
+        let merged = merge(&a, &b);
+        prop_assert_eq!(normalized(&merge(&merged, &b)), normalized(&merged));
+    }
+
+    #[test]
+    fn merge_converges_after_further_changes(
+        ops_a in ops(0..ENTRIES),
+        ops_b in ops(0..ENTRIES),
+        ops_after in ops(0..ENTRIES),
+    ) {
+        let fixture = Fixture::new();
+        let a = fixture.apply(&fixture.base, &ops_a);
+        let b = fixture.apply(&fixture.base, &ops_b);
+        let synced = fixture.apply(&merge(&a, &b), &ops_after);
+        prop_assert_eq!(normalized(&merge(&synced, &a)), normalized(&synced));
+        prop_assert_eq!(normalized(&merge(&b, &synced)), normalized(&synced));
+    }
+
+    #[test]
+    fn deleted_entry_is_not_resurrected(
+        ops_stale in ops(1..ENTRIES),
+        ops_after in ops(1..ENTRIES),
+    ) {
+        let fixture = Fixture::new();
+        let stale = fixture.apply(&fixture.base, &ops_stale);
+        let mut deleted = fixture.apply(&fixture.base, &ops_after);
+        deleted.delete_content(&entry(0)).unwrap();
+
+        prop_assert!(!merge(&deleted, &stale).contents.contains_key(&entry(0)));
+        prop_assert!(!merge(&stale, &deleted).contents.contains_key(&entry(0)));
+    }
+}
+
+#[test]
+fn merge_keeps_latest_passphrase_change() {
+    let params = Argon2Params {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+    let fixture = Fixture::new();
+    let set_passphrase = |config: &VaultConfig, passphrase| {
+        let mut config = config.clone();
+        block_on(config.set_passphrase(&RustCrypto, passphrase, params, &fixture.master_key))
+            .unwrap();
+        config
+    };
+    let old = set_passphrase(&fixture.base, Some("old"));
+
+    // Removed on one device, after it was set on the other
+    let removed = set_passphrase(&old, None);
+    for merged in [merge(&old, &removed), merge(&removed, &old)] {
+        assert_eq!(merged.passphrase_key, None);
+        assert_eq!(merged.master_key.as_ref().unwrap().recipients.len(), 1);
+    }
+
+    // Replaced on one device, so the old passphrase does not come back
+    let replaced = set_passphrase(&old, Some("new"));
+    for merged in [merge(&old, &replaced), merge(&replaced, &old)] {
+        assert_eq!(merged.passphrase_key, replaced.passphrase_key);
+        assert_eq!(merged.master_key.as_ref().unwrap().recipients.len(), 2);
+        assert!(block_on(merged.unlock_with_passphrase(&RustCrypto, "old")).is_err());
+        assert!(block_on(merged.unlock_with_passphrase(&RustCrypto, "new")).is_ok());
+    }
+}
+
+#[test]
+fn merge_keeps_latest_padding_change() {
+    let fixture = Fixture::new();
+    let set_padding = |config: &VaultConfig, padding| {
+        let mut config = config.clone();
+        config.set_padding(padding).unwrap();
+        config
+    };
+    let padded = set_padding(&fixture.base, Some(Padding::PowerOfTwo));
+
+    // Turned off on one device after it was turned on, so it stays off
+    // rather than the greater setting winning
+    let unpadded = set_padding(&padded, None);
+    for merged in [merge(&padded, &unpadded), merge(&unpadded, &padded)] {
+        assert_eq!(merged.padding, None);
+        assert_eq!(merged.revisions.padding, unpadded.revisions.padding);
+    }
+}
+
+#[test]
+fn rejects_independently_created_master_keys() {
+    let keys: Vec<TestKey> = (1..=2).map(TestKey::new).collect();
+    let legacy = legacy_vault(&[&keys[0], &keys[1]], &[("entry0", b"1234")]);
+    let create = |config: &VaultConfig| {
+        let mut config = config.clone();
+        block_on(config.create_master_key(&RustCrypto)).unwrap();
+        config
+    };
+    let a = create(&legacy);
+    let b = create(&legacy);
+    assert!(a.revisions.master_key.is_some());
+
+    for (a, b) in [(&a, &b), (&b, &a)] {
+        assert!(matches!(
+            block_on(a.sync_merge(&RustCrypto, b, None)),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    // Also when created before master key revisions were recorded
+    let mut a = a;
+    let mut b = b;
+    a.revisions.master_key = None;
+    b.revisions.master_key = None;
+    assert!(matches!(
+        block_on(a.sync_merge(&RustCrypto, &b, None)),
+        Err(Error::InvalidInput(_))
+    ));
+
+    // A copy from before the master key was created is superseded by it
+    let merged = merge(&legacy, &create(&legacy));
+    assert!(merged.master_key.is_some());
+    assert!(merged.contents.contains_key("entry0"));
+}
@@ -0,0 +1,114 @@
+use passwordless_passwords_prf::data::totp::Algorithm;
+use passwordless_passwords_prf::data::totp::Totp;
+use passwordless_passwords_prf::error::Error;
+
+/// Test vectors from RFC 6238 appendix B: (time, SHA1, SHA256, SHA512).
+const RFC6238_VECTORS: &[(u64, &str, &str, &str)] = &[
+    (59, "94287082", "46119246", "90693936"),
+    (1111111109, "07081804", "68084774", "25091201"),
+    (1111111111, "14050471", "67062674", "99943326"),
+    (1234567890, "89005924", "91819424", "93441116"),
+    (2000000000, "69279037", "90698825", "38618901"),
+    (20000000000, "65353130", "77737706", "47863826"),
+];
+
+fn rfc6238_totp(algorithm: Algorithm) -> Totp {
+    let seed = match algorithm {
+        Algorithm::Sha1 => b"12345678901234567890".to_vec(),
+        Algorithm::Sha256 => b"12345678901234567890123456789012".to_vec(),
+        Algorithm::Sha512 => {
+            b"1234567890123456789012345678901234567890123456789012345678901234".to_vec()
+        }
+    };
+    Totp::new(seed, algorithm, 8, 30).unwrap()
+}
+
+#[test]
+fn rfc6238_test_vectors() {
+    let sha1 = rfc6238_totp(Algorithm::Sha1);
+    let sha256 = rfc6238_totp(Algorithm::Sha256);
+    let sha512 = rfc6238_totp(Algorithm::Sha512);
+    for (time, expected_sha1, expected_sha256, expected_sha512) in RFC6238_VECTORS {
+        assert_eq!(sha1.generate(*time), *expected_sha1, "SHA1 at {time}");
+        assert_eq!(sha256.generate(*time), *expected_sha256, "SHA256 at {time}");
+        assert_eq!(sha512.generate(*time), *expected_sha512, "SHA512 at {time}");
+    }
+}
+
+#[test]
+fn six_digits_truncate_eight_digit_code() {
+    let totp = Totp::new(b"12345678901234567890".to_vec(), Algorithm::Sha1, 6, 30).unwrap();
+    assert_eq!(totp.generate(59), "287082");
+    assert_eq!(totp.generate(1111111109), "081804");
+}
+
+#[test]
+fn custom_period() {
+    let totp = Totp::new(b"12345678901234567890".to_vec(), Algorithm::Sha1, 8, 60).unwrap();
+    // Counter 1 in both cases
+    assert_eq!(
+        totp.generate(60),
+        rfc6238_totp(Algorithm::Sha1).generate(30)
+    );
+    assert_eq!(totp.remaining(60), 60);
+    assert_eq!(totp.remaining(119), 1);
+}
+
+#[test]
+fn parse_otpauth_uri() {
+    let totp = Totp::parse(
+        "otpauth://totp/ACME%20Co:john.doe@email.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=ACME%20Co&algorithm=SHA256&digits=8&period=60",
+    )
+    .unwrap();
+    assert_eq!(totp.algorithm, Algorithm::Sha256);
+    assert_eq!(totp.digits, 8);
+    assert_eq!(totp.period, 60);
+    assert_eq!(totp.issuer.as_deref(), Some("ACME Co"));
+    assert_eq!(totp.account.as_deref(), Some("john.doe@email.com"));
+    assert_eq!(
+        totp.generate(59),
+        Totp::new(b"12345678901234567890".to_vec(), Algorithm::Sha256, 8, 60)
+            .unwrap()
+            .generate(59)
+    );
+}
+
+#[test]
+fn parse_uri_defaults_and_bare_secret() {
+    let uri = Totp::parse("otpauth://totp/Example?secret=JBSWY3DPEHPK3PXP").unwrap();
+    let bare = Totp::parse("jbsw y3dp ehpk 3pxp").unwrap();
+    assert_eq!(uri.algorithm, Algorithm::Sha1);
+    assert_eq!(uri.digits, 6);
+    assert_eq!(uri.period, 30);
+    assert_eq!(uri.account.as_deref(), Some("Example"));
+    assert_eq!(uri.generate(1111111109), bare.generate(1111111109));
+}
+
+#[test]
+fn uri_roundtrip() {
+    let totp = Totp::parse(
+        "otpauth://totp/ACME%20Co:john?secret=JBSWY3DPEHPK3PXP&algorithm=SHA512&digits=8&period=45",
+    )
+    .unwrap();
+    assert_eq!(Totp::from_uri(&totp.to_uri()).unwrap(), totp);
+    let json = serde_json::to_string(&totp).unwrap();
+    assert_eq!(serde_json::from_str::<Totp>(&json).unwrap(), totp);
+}
+
+#[test]
+fn rejects_invalid_parameters() {
+    for uri in [
+        "otpauth://hotp/Example?secret=JBSWY3DPEHPK3PXP&counter=0",
+        "otpauth://totp/Example",
+        "otpauth://totp/Example?secret=not-base32!",
+        "otpauth://totp/Example?secret=JBSWY3DPEHPK3PXP&digits=7",
+        "otpauth://totp/Example?secret=JBSWY3DPEHPK3PXP&period=0",
+        "otpauth://totp/Example?secret=JBSWY3DPEHPK3PXP&algorithm=MD5",
+        "https://example.org",
+    ] {
+        assert!(
+            matches!(Totp::parse(uri), Err(Error::MalformedRecord(_))),
+            "{uri}"
+        );
+    }
+}